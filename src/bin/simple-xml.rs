@@ -0,0 +1,274 @@
+//! Command line tool for formatting, checking, querying and editing xml files
+//! Built with the `cli` feature
+
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use simple_xml::path::{Location, Path, Target};
+use simple_xml::{encoding, entity, Encoding, Error, Node, ParseOptions, Severity, Span};
+
+const USAGE: &str = "Usage: simple-xml <command> [arguments] [files...]
+
+Commands:
+    fmt                     Print the files with pretty formatting
+    check                   Report all well-formedness problems with their line numbers
+    query <path>            Print the nodes or values matching a path
+    get-attr <path> <name>  Print an attribute of the nodes matching a path
+    set <path> <value>      Set the content of the nodes matching a path, or the attribute if the
+                            path ends with @name. Files are edited in place, changing only the
+                            edited values. Elements with child elements can not be set
+
+Reads from standard input when no files are given, and writes edits to standard output";
+
+/// A file or standard input
+enum Input {
+    Stdin,
+    File(PathBuf),
+}
+
+impl Input {
+    fn name(&self) -> String {
+        match self {
+            Input::Stdin => "<stdin>".to_owned(),
+            Input::File(path) => path.display().to_string(),
+        }
+    }
+
+    fn read_bytes(&self) -> Result<Vec<u8>, String> {
+        let result = match self {
+            Input::Stdin => {
+                let mut bytes = Vec::new();
+                io::stdin().read_to_end(&mut bytes).map(|_| bytes)
+            }
+            Input::File(path) => std::fs::read(path),
+        };
+        result.map_err(|e| format!("{}: {}", self.name(), e))
+    }
+
+    /// Reads the input, transcoded to UTF-8 from the encoding it is detected to be in
+    fn read(&self) -> Result<(String, Encoding), String> {
+        encoding::decode(&self.read_bytes()?).map_err(|e| self.error(e))
+    }
+
+    fn parse(&self) -> Result<Node, String> {
+        let (string, _) = self.read()?;
+        simple_xml::from_string(&string).map_err(|e| self.error(e))
+    }
+
+    /// Formats an error with the name of the input, and the line for parse errors
+    fn error(&self, e: Error) -> String {
+        match e {
+            Error::ParseError(e, line) => format!("{}:{}: {}", self.name(), line + 1, e),
+            e => format!("{}: {}", self.name(), e),
+        }
+    }
+}
+
+fn inputs(files: &[String]) -> Vec<Input> {
+    match files.len() {
+        0 => vec![Input::Stdin],
+        _ => files.iter().map(|v| Input::File(v.into())).collect(),
+    }
+}
+
+fn parse_path(path: &str) -> Result<Path, String> {
+    Path::parse(path).map_err(|e| e.to_string())
+}
+
+/// Runs a command on each input, returning false if any of them failed
+fn for_each_input<F>(files: &[String], mut f: F) -> bool
+where
+    F: FnMut(&Input) -> Result<(), String>,
+{
+    inputs(files).iter().fold(true, |ok, input| match f(input) {
+        Ok(()) => ok,
+        Err(e) => {
+            eprintln!("simple-xml: {}", e);
+            false
+        }
+    })
+}
+
+fn fmt(files: &[String]) -> bool {
+    for_each_input(files, |input| {
+        print!("{}", input.parse()?.to_string_pretty());
+        Ok(())
+    })
+}
+
+fn check(files: &[String]) -> bool {
+    for_each_input(files, |input| {
+        let diagnostics = simple_xml::check_well_formed(&input.read()?.0);
+        for diagnostic in &diagnostics {
+            println!("{}:{}", input.name(), diagnostic);
+        }
+
+        match diagnostics.iter().any(|v| v.severity == Severity::Error) {
+            true => Err(format!("{}: not well-formed", input.name())),
+            false => Ok(()),
+        }
+    })
+}
+
+fn query(path: &str, files: &[String]) -> bool {
+    for_each_input(files, |input| {
+        let path = parse_path(path)?;
+        let root = input.parse()?;
+        let nodes = path.select(&root);
+        match path.target() {
+            Target::Element => nodes
+                .iter()
+                .for_each(|v| print!("{}", v.to_string_pretty())),
+            // Values are printed with their references expanded
+            _ => path
+                .values(&root)
+                .iter()
+                .for_each(|v| println!("{}", entity::decode(v))),
+        }
+
+        match nodes.is_empty() {
+            true => Err(format!("{}: no nodes match the path", input.name())),
+            false => Ok(()),
+        }
+    })
+}
+
+fn get_attr(path: &str, name: &str, files: &[String]) -> bool {
+    for_each_input(files, |input| {
+        let root = input.parse()?;
+        let values: Vec<_> = parse_path(path)?
+            .select(&root)
+            .into_iter()
+            .filter_map(|v| v.get_attribute(name))
+            .collect();
+
+        values
+            .iter()
+            .for_each(|v| println!("{}", entity::decode(v)));
+        match values.is_empty() {
+            true => Err(format!("{}: no attribute {:?} found", input.name(), name)),
+            false => Ok(()),
+        }
+    })
+}
+
+fn set(path: &str, value: &str, files: &[String]) -> bool {
+    for_each_input(files, |input| {
+        let path = parse_path(path)?;
+        let bytes = input.read_bytes()?;
+        let (mut string, encoding) = encoding::decode(&bytes).map_err(|e| input.error(e))?;
+        let options = ParseOptions::default();
+        let (root, spans) =
+            simple_xml::from_string_with_spans(&string, &options).map_err(|e| input.error(e))?;
+        let locations = path.locate(&root);
+        if locations.is_empty() {
+            return Err(format!("{}: no nodes match the path", input.name()));
+        }
+
+        // The spans are in the same order as the locations of the root and its descendants
+        let spans: HashMap<Location, Span> = std::iter::once(Location::default())
+            .chain(root.descendants_with_depth().map(|(_, v, _)| v))
+            .zip(spans)
+            .collect();
+
+        // Only the edited values are replaced, so the rest of the file is kept as it is
+        let mut edits = Vec::new();
+        for location in locations {
+            let (node, span) = match (location.get(&root), spans.get(&location)) {
+                (Some(node), Some(span)) => (node, span),
+                _ => continue,
+            };
+            edits.push(match path.target() {
+                Target::Attribute(name) => match span.attributes.get(name) {
+                    Some(range) => (range.clone(), entity::escape_attribute(value)),
+                    None => {
+                        // Added after the last attribute, before the "/>" of empty elements
+                        let end = span.start_tag.end - 1 - span.content.is_none() as usize;
+                        let end = span.start_tag.start
+                            + string[span.start_tag.start..end].trim_end().len();
+                        let attribute =
+                            format!(" {}=\"{}\"", name, entity::escape_attribute(value));
+                        (end..end, attribute)
+                    }
+                },
+                Target::Element | Target::Text if node.child_count() > 0 => {
+                    return Err(format!(
+                        "{}: <{}> has child elements, which setting its content would remove",
+                        input.name(),
+                        node.tag
+                    ));
+                }
+                Target::Element | Target::Text => match &span.content {
+                    Some(range) => (range.clone(), entity::escape_text(value)),
+                    None => {
+                        // Empty elements get a closing tag
+                        let tag = &string[span.start_tag.start..span.start_tag.end - 2];
+                        let element = format!(
+                            "{}>{}</{}>",
+                            tag.trim_end(),
+                            entity::escape_text(value),
+                            node.tag
+                        );
+                        (span.start_tag.clone(), element)
+                    }
+                },
+            });
+        }
+
+        // Replace from the end, so the ranges before each edit stay valid
+        edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+        for (range, text) in edits {
+            string.replace_range(range, &text);
+        }
+
+        match input {
+            Input::Stdin => print!("{}", string),
+            Input::File(file) => {
+                // The byte order mark is not part of the decoded string
+                let mut output = encoding.encode(&string);
+                if encoding == Encoding::Utf8 && bytes.starts_with(b"\xef\xbb\xbf") {
+                    output.splice(0..0, [0xef, 0xbb, 0xbf]);
+                }
+                replace_file(file, &output).map_err(|e| format!("{}: {}", input.name(), e))?
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Replaces a file by writing a temporary file next to it and renaming it over the original, so an
+/// interrupted write does not leave it truncated
+fn replace_file(file: &std::path::Path, bytes: &[u8]) -> io::Result<()> {
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    let temporary = file.with_file_name(format!(".{}.tmp", name));
+    std::fs::write(&temporary, bytes)?;
+    std::fs::rename(&temporary, file).inspect_err(|_| {
+        let _ = std::fs::remove_file(&temporary);
+    })
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let ok = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["fmt", ..] => fmt(&args[1..]),
+        ["check", ..] => check(&args[1..]),
+        ["query", path, ..] => query(path, &args[2..]),
+        ["get-attr", path, name, ..] => get_attr(path, name, &args[3..]),
+        ["set", path, value, ..] => set(path, value, &args[3..]),
+        ["help" | "--help" | "-h"] => {
+            println!("{}", USAGE);
+            true
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match ok {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    }
+}
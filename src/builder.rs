@@ -0,0 +1,94 @@
+//! This is a module providing a fluent builder for xml nodes
+//! ```
+//! let player = simple_xml::Node::builder("player")
+//!     .attr("id", 4)
+//!     .child(simple_xml::Node::builder("name").text("Tim Roberts"))
+//!     .child(simple_xml::Node::builder("health").text(50))
+//!     .build();
+//!
+//! assert_eq!(player["health"][0].content, "50");
+//! assert_eq!(
+//!     player.to_string(),
+//!     "<player id=\"4\"><name>Tim Roberts</name><health>50</health></player>"
+//! );
+//! ```
+
+use std::collections::HashMap;
+
+use crate::{Error, Node};
+
+/// Builds a node with attributes, content and child nodes
+/// Child nodes keep the order they were added in
+#[derive(Debug)]
+pub struct NodeBuilder {
+    tag: String,
+    attributes: HashMap<String, String>,
+    content: String,
+    children: Vec<Node>,
+}
+
+impl NodeBuilder {
+    /// Starts building a node with the given tag
+    pub fn new<T: ToString>(tag: T) -> Self {
+        NodeBuilder {
+            tag: tag.to_string(),
+            attributes: HashMap::new(),
+            content: String::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Adds or updates an attribute
+    pub fn attr<K: ToString, V: ToString>(mut self, key: K, val: V) -> Self {
+        self.attributes.insert(key.to_string(), val.to_string());
+        self
+    }
+
+    /// Sets the text content of the node
+    pub fn text<T: ToString>(mut self, content: T) -> Self {
+        self.content = content.to_string();
+        self
+    }
+
+    /// Appends a child node
+    /// Accepts both nodes and unfinished builders
+    pub fn child<N: Into<Node>>(mut self, node: N) -> Self {
+        self.children.push(node.into());
+        self
+    }
+
+    /// Appends all child nodes from an iterator
+    pub fn children<I>(mut self, nodes: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Node>,
+    {
+        self.children.extend(nodes.into_iter().map(Into::into));
+        self
+    }
+
+    /// Finishes the node without checking any names
+    pub fn build(self) -> Node {
+        let mut node = crate::new(&self.tag, self.content);
+        node.attributes = self.attributes;
+        for child in self.children {
+            node.add_node(child);
+        }
+        node
+    }
+
+    /// Finishes the node and checks the tag and attribute names of it and all its child nodes
+    /// against the xml Name production
+    /// Returns an Err of InvalidName containing the first offending name
+    pub fn try_build(self) -> Result<Node, Error> {
+        let node = self.build();
+        node.validate_names()?;
+        Ok(node)
+    }
+}
+
+impl From<NodeBuilder> for Node {
+    fn from(builder: NodeBuilder) -> Self {
+        builder.build()
+    }
+}
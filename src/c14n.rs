@@ -0,0 +1,279 @@
+//! This is a module providing canonical xml output following Canonical XML 1.0 and Exclusive XML
+//! Canonicalization 1.0 without comments, for comparing, hashing and signing documents byte for byte
+//! The canonical form expands references and CDATA sections, normalizes line endings and attribute
+//! whitespace, sorts namespace declarations and attributes, writes empty elements with both tags and
+//! removes superfluous namespace declarations
+//! The output is the canonical form of the tree as parsed. Comments and processing instructions are
+//! not kept by the parser, and attributes defaulted in a document type declaration are not added
+//! The whitespace between elements is only kept when parsing with `Whitespace::Preserve`, which is
+//! needed for the output to match the canonical form of the document as written
+//! ```
+//! use simple_xml::c14n::CanonicalOptions;
+//!
+//! let root = simple_xml::from_string(r#"<a xmlns:x="urn:x" z="1" x:y="&#65;"><b/></a>"#).unwrap();
+//! let mut output = Vec::new();
+//! root.write_canonical(&mut output, &CanonicalOptions::default()).unwrap();
+//! assert_eq!(output, br#"<a xmlns:x="urn:x" z="1" x:y="A"><b></b></a>"#);
+//! ```
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::entity;
+use crate::{Node, Visitor, Walk};
+
+/// The namespace bound to the `xml` prefix
+pub const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// The canonicalization method
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Method {
+    /// Canonical XML 1.0, where every namespace in scope is declared on the outermost element
+    #[default]
+    Inclusive,
+    /// Exclusive XML Canonicalization 1.0, where namespaces are only declared on the elements using them
+    Exclusive,
+}
+
+/// Options for canonicalization
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CanonicalOptions {
+    pub method: Method,
+    /// Prefixes handled as in inclusive canonicalization when using the exclusive method, as listed in
+    /// InclusiveNamespaces PrefixList
+    /// The default namespace is written as `#default`
+    pub inclusive_prefixes: Vec<String>,
+    /// Namespaces declared on the ancestors of the node being written, as pairs of prefix and uri
+    /// The default namespace has an empty prefix
+    pub inherited_namespaces: Vec<(String, String)>,
+}
+
+impl CanonicalOptions {
+    /// Options for Exclusive XML Canonicalization without inclusive prefixes
+    pub fn exclusive() -> Self {
+        CanonicalOptions {
+            method: Method::Exclusive,
+            ..Default::default()
+        }
+    }
+}
+
+/// Namespaces in scope and namespaces written by the output ancestors, by prefix
+#[derive(Clone, Default)]
+struct Scope {
+    declared: HashMap<String, String>,
+    rendered: HashMap<String, String>,
+}
+
+pub(crate) fn write<W: Write>(
+    node: &Node,
+    writer: &mut W,
+    options: &CanonicalOptions,
+) -> io::Result<()> {
+    // Like Display, nodes without a tag are not written
+    if node.tag.is_empty() {
+        return Ok(());
+    }
+
+    let mut root = Scope::default();
+    for (prefix, uri) in &options.inherited_namespaces {
+        root.declared.insert(prefix.clone(), uri.clone());
+    }
+
+    let mut canonicalizer = Canonicalizer {
+        writer,
+        options,
+        inclusive: options
+            .inclusive_prefixes
+            .iter()
+            .map(|v| match v.as_str() {
+                "#default" => "",
+                v => v,
+            })
+            .collect(),
+        scopes: vec![root],
+        error: None,
+    };
+    node.walk(&mut canonicalizer);
+    canonicalizer.error.map_or(Ok(()), Err)
+}
+
+/// Writes the canonical form while walking a tree, stopping at the first write error
+struct Canonicalizer<'a, W> {
+    writer: &'a mut W,
+    options: &'a CanonicalOptions,
+    inclusive: Vec<&'a str>,
+    scopes: Vec<Scope>,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Canonicalizer<'_, W> {
+    fn start_tag(&mut self, node: &Node) -> io::Result<()> {
+        let mut scope = self.scopes.last().cloned().unwrap_or_default();
+        let mut attributes = Vec::new();
+        for (key, value) in &node.attributes {
+            let value = normalize_attribute(value);
+            match (key.as_str(), key.strip_prefix("xmlns:")) {
+                ("xmlns", _) => {
+                    scope.declared.insert(String::new(), value);
+                }
+                (_, Some(prefix)) => {
+                    scope.declared.insert(prefix.to_owned(), value);
+                }
+                _ => attributes.push((key.as_str(), value)),
+            }
+        }
+
+        let prefixes: Vec<&str> = match self.options.method {
+            Method::Inclusive => scope.declared.keys().map(String::as_str).collect(),
+            Method::Exclusive => {
+                // Namespaces visibly utilized by the element, unprefixed attributes have no namespace
+                let mut used = vec![prefix(&node.tag).unwrap_or("")];
+                used.extend(attributes.iter().filter_map(|(key, _)| prefix(key)));
+                used.extend(
+                    self.inclusive
+                        .iter()
+                        .filter(|v| scope.declared.contains_key(**v)),
+                );
+                used
+            }
+        };
+
+        let mut declarations = Vec::new();
+        for prefix in prefixes {
+            let uri = match (prefix, scope.declared.get(prefix)) {
+                ("xml", _) => continue,
+                ("", uri) => uri.map(String::as_str).unwrap_or(""),
+                (_, Some(uri)) => uri,
+                (_, None) => continue,
+            };
+
+            // An empty default namespace only needs declaring if a non-empty one was written
+            let rendered = scope.rendered.get(prefix).map(String::as_str);
+            if rendered.unwrap_or("") != uri {
+                declarations.push((prefix.to_owned(), uri.to_owned()));
+            }
+        }
+        declarations.sort();
+        declarations.dedup();
+
+        // Attributes are sorted by namespace uri, then by local name
+        let mut attributes: Vec<_> = attributes
+            .into_iter()
+            .map(|(key, value)| {
+                let uri = match prefix(key) {
+                    None => "",
+                    Some("xml") => XML_NAMESPACE,
+                    Some(prefix) => scope.declared.get(prefix).map(String::as_str).unwrap_or(""),
+                };
+                ((uri.to_owned(), local_name(key)), key, value)
+            })
+            .collect();
+        attributes.sort();
+
+        write!(self.writer, "<{}", node.tag)?;
+        for (prefix, uri) in &declarations {
+            match prefix.as_str() {
+                "" => write!(self.writer, " xmlns=\"")?,
+                prefix => write!(self.writer, " xmlns:{}=\"", prefix)?,
+            }
+            write_attribute(self.writer, uri)?;
+            write!(self.writer, "\"")?;
+        }
+        for (_, key, value) in &attributes {
+            write!(self.writer, " {}=\"", key)?;
+            write_attribute(self.writer, value)?;
+            write!(self.writer, "\"")?;
+        }
+        write!(self.writer, ">")?;
+
+        for (prefix, uri) in declarations {
+            scope.rendered.insert(prefix, uri);
+        }
+        self.scopes.push(scope);
+        Ok(())
+    }
+
+    /// Keeps the first error, after which the walk stops at the next element
+    fn result(&mut self, result: io::Result<()>) -> Walk {
+        if let Err(e) = result {
+            self.error.get_or_insert(e);
+        }
+        match self.error {
+            Some(_) => Walk::Stop,
+            None => Walk::Continue,
+        }
+    }
+}
+
+impl<W: Write> Visitor for Canonicalizer<'_, W> {
+    fn enter_element(&mut self, node: &Node) -> Walk {
+        if self.error.is_some() {
+            return Walk::Stop;
+        }
+        let result = self.start_tag(node);
+        self.result(result)
+    }
+
+    fn leave_element(&mut self, node: &Node) {
+        self.scopes.pop();
+        let result = write!(self.writer, "</{}>", node.tag);
+        self.result(result);
+    }
+
+    fn text(&mut self, _node: &Node, text: &str) {
+        let result = write_text(self.writer, text);
+        self.result(result);
+    }
+}
+
+fn prefix(name: &str) -> Option<&str> {
+    name.split_once(':').map(|(prefix, _)| prefix)
+}
+
+fn local_name(name: &str) -> &str {
+    name.split_once(':').map_or(name, |(_, local)| local)
+}
+
+/// Normalizes line endings to line feeds, as done by xml parsers
+fn normalize_newlines(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Decodes an attribute value with its whitespace characters normalized to spaces
+/// Whitespace written as character references is kept
+fn normalize_attribute(value: &str) -> String {
+    let value = normalize_newlines(value).replace(['\t', '\n'], " ");
+    entity::decode(&value)
+}
+
+fn write_text<W: Write>(writer: &mut W, raw: &str) -> io::Result<()> {
+    let text = entity::decode(&normalize_newlines(raw));
+    let mut buffer = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => buffer.push_str("&amp;"),
+            '<' => buffer.push_str("&lt;"),
+            '>' => buffer.push_str("&gt;"),
+            '\r' => buffer.push_str("&#xD;"),
+            c => buffer.push(c),
+        }
+    }
+    writer.write_all(buffer.as_bytes())
+}
+
+fn write_attribute<W: Write>(writer: &mut W, value: &str) -> io::Result<()> {
+    let mut buffer = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => buffer.push_str("&amp;"),
+            '<' => buffer.push_str("&lt;"),
+            '"' => buffer.push_str("&quot;"),
+            '\t' => buffer.push_str("&#x9;"),
+            '\n' => buffer.push_str("&#xA;"),
+            '\r' => buffer.push_str("&#xD;"),
+            c => buffer.push(c),
+        }
+    }
+    writer.write_all(buffer.as_bytes())
+}
@@ -0,0 +1,229 @@
+//! This is a module providing the built in simple types of XML Schema
+//! They are shared by XML Schema validation, the XML Schema datatype library of RELAX NG, and
+//! inference of schemas
+
+/// The supported built in simple types
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Builtin {
+    String,
+    Int,
+    Long,
+    Short,
+    Byte,
+    Integer,
+    NonNegativeInteger,
+    PositiveInteger,
+    UnsignedInt,
+    Decimal,
+    Double,
+    Boolean,
+    Date,
+    DateTime,
+    AnyType,
+}
+
+impl Builtin {
+    pub(crate) fn from_name(name: &str) -> Option<Builtin> {
+        let v = match name {
+            "string" | "normalizedString" | "token" | "anyURI" | "ID" | "IDREF" | "NCName"
+            | "Name" | "NMTOKEN" | "language" => Builtin::String,
+            "int" => Builtin::Int,
+            "long" => Builtin::Long,
+            "short" => Builtin::Short,
+            "byte" => Builtin::Byte,
+            "integer" => Builtin::Integer,
+            "nonNegativeInteger" => Builtin::NonNegativeInteger,
+            "positiveInteger" => Builtin::PositiveInteger,
+            "unsignedInt" => Builtin::UnsignedInt,
+            "decimal" => Builtin::Decimal,
+            "float" | "double" => Builtin::Double,
+            "boolean" => Builtin::Boolean,
+            "date" => Builtin::Date,
+            "dateTime" => Builtin::DateTime,
+            "anyType" | "anySimpleType" => Builtin::AnyType,
+            _ => return None,
+        };
+        Some(v)
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Builtin::String => "string",
+            Builtin::Int => "int",
+            Builtin::Long => "long",
+            Builtin::Short => "short",
+            Builtin::Byte => "byte",
+            Builtin::Integer => "integer",
+            Builtin::NonNegativeInteger => "nonNegativeInteger",
+            Builtin::PositiveInteger => "positiveInteger",
+            Builtin::UnsignedInt => "unsignedInt",
+            Builtin::Decimal => "decimal",
+            Builtin::Double => "double",
+            Builtin::Boolean => "boolean",
+            Builtin::Date => "date",
+            Builtin::DateTime => "dateTime",
+            Builtin::AnyType => "anyType",
+        }
+    }
+
+    /// Returns true if the value is in the lexical space of the type
+    /// Surrounding whitespace is collapsed for all types except string
+    pub(crate) fn accepts(self, value: &str) -> bool {
+        let value = value.trim();
+        let integer = |v: &str| {
+            let digits = v.strip_prefix(|c| c == '+' || c == '-').unwrap_or(v);
+            !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+        };
+
+        match self {
+            Builtin::String | Builtin::AnyType => true,
+            Builtin::Int => value.parse::<i32>().is_ok(),
+            Builtin::Long => value.parse::<i64>().is_ok(),
+            Builtin::Short => value.parse::<i16>().is_ok(),
+            Builtin::Byte => value.parse::<i8>().is_ok(),
+            Builtin::UnsignedInt => value.parse::<u32>().is_ok(),
+            Builtin::Integer => integer(value),
+            Builtin::NonNegativeInteger => {
+                integer(value) && !(value.starts_with('-') && value[1..].bytes().any(|c| c != b'0'))
+            }
+            Builtin::PositiveInteger => {
+                integer(value)
+                    && !value.starts_with('-')
+                    && value.bytes().any(|c| (b'1'..=b'9').contains(&c))
+            }
+            Builtin::Decimal => {
+                let digits = value
+                    .strip_prefix(|c| c == '+' || c == '-')
+                    .unwrap_or(value);
+                let mut parts = digits.splitn(2, '.');
+                let int = parts.next().unwrap_or_default();
+                let frac = parts.next().unwrap_or_default();
+                (!int.is_empty() || !frac.is_empty())
+                    && int.chars().chain(frac.chars()).all(|c| c.is_ascii_digit())
+            }
+            Builtin::Double => {
+                matches!(value, "INF" | "-INF" | "NaN")
+                    || (!value.contains(|c: char| c.is_alphabetic() && c != 'e' && c != 'E')
+                        && value.parse::<f64>().is_ok())
+            }
+            Builtin::Boolean => matches!(value, "true" | "false" | "1" | "0"),
+            Builtin::Date => is_date(value),
+            Builtin::DateTime => match value.find('T') {
+                Some(i) => is_date_part(&value[..i]) && is_time(&value[i + 1..]),
+                None => false,
+            },
+        }
+    }
+
+    pub(crate) fn is_numeric(self) -> bool {
+        !matches!(
+            self,
+            Builtin::String
+                | Builtin::AnyType
+                | Builtin::Boolean
+                | Builtin::Date
+                | Builtin::DateTime
+        )
+    }
+}
+
+/// Checks YYYY-MM-DD with an optional timezone
+fn is_date(value: &str) -> bool {
+    let (date, tz) = split_timezone(value, 10);
+    is_date_part(date) && is_timezone(tz)
+}
+
+fn is_date_part(value: &str) -> bool {
+    let value = value.strip_prefix('-').unwrap_or(value);
+    let mut parts = value.split('-');
+    let (year, month, day) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(y), Some(m), Some(d), None) => (y, m, d),
+        _ => return false,
+    };
+
+    let digits = |v: &str, len: usize| v.len() == len && v.bytes().all(|c| c.is_ascii_digit());
+    if year.len() < 4 || !digits(year, year.len()) || !digits(month, 2) || !digits(day, 2) {
+        return false;
+    }
+
+    let year: u64 = year.parse().unwrap_or_default();
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month.parse::<u8>().unwrap_or_default() {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+
+    matches!(day.parse::<u8>(), Ok(d) if d >= 1 && d <= days)
+}
+
+/// Checks hh:mm:ss with optional fractional seconds and timezone
+fn is_time(value: &str) -> bool {
+    let (time, tz) = split_timezone(value, 8);
+    let (time, frac) = match time.find('.') {
+        Some(i) => (&time[..i], Some(&time[i + 1..])),
+        None => (time, None),
+    };
+
+    let mut parts = time.split(':');
+    let field = |v: Option<&str>, max: u8| match v {
+        Some(v) if v.len() == 2 => v.parse::<u8>().ok().filter(|n| *n <= max),
+        _ => None,
+    };
+    let (hour, minute, second) = match (
+        field(parts.next(), 24),
+        field(parts.next(), 59),
+        field(parts.next(), 59),
+    ) {
+        (Some(hour), Some(minute), Some(second)) => (hour, minute, second),
+        _ => return false,
+    };
+
+    // 24:00:00 is the end of the day, so no other time in hour 24 exists
+    let zero_frac = frac.map_or(true, |f| f.bytes().all(|c| c == b'0'));
+    let end_of_day = minute == 0 && second == 0 && zero_frac;
+
+    parts.next().is_none()
+        && (hour < 24 || end_of_day)
+        && frac.map_or(true, |f| {
+            !f.is_empty() && f.bytes().all(|c| c.is_ascii_digit())
+        })
+        && is_timezone(tz)
+}
+
+/// Splits a value into the part before the timezone and the timezone
+/// The timezone is searched for after min_len bytes to not confuse it with the date separators
+fn split_timezone(value: &str, min_len: usize) -> (&str, &str) {
+    let start = value.starts_with('-') as usize;
+    match value
+        .char_indices()
+        .skip(min_len + start)
+        .find(|(_, c)| matches!(c, 'Z' | '+' | '-'))
+    {
+        Some((i, _)) => (&value[..i], &value[i..]),
+        None => (value, ""),
+    }
+}
+
+fn is_timezone(tz: &str) -> bool {
+    match tz {
+        "" | "Z" => true,
+        _ => {
+            let b = tz.as_bytes();
+            b.len() == 6
+                && (b[0] == b'+' || b[0] == b'-')
+                && b[3] == b':'
+                && b[1..3].iter().chain(&b[4..]).all(|c| c.is_ascii_digit())
+        }
+    }
+}
+
+/// Namespace declarations and schema instance attributes are not validated
+pub(crate) fn is_reserved_attribute(key: &str) -> bool {
+    key == "xmlns"
+        || key.starts_with("xmlns:")
+        || key.starts_with("xsi:")
+        || key.starts_with("xml:")
+}
@@ -0,0 +1,436 @@
+//! This is a module providing a structural diff between two node trees
+//! Child elements are matched by tag and by equality, so reordered, inserted and removed elements are
+//! reported as such instead of as changes to every following sibling
+//! ```
+//! use simple_xml::diff::Edit;
+//!
+//! let old = simple_xml::from_string("<list><a/><b>x</b><c/></list>").unwrap();
+//! let new = simple_xml::from_string("<list><b>y</b><c/><a/></list>").unwrap();
+//!
+//! let edits = simple_xml::diff(&old, &new);
+//! assert_eq!(edits.len(), 2);
+//! assert_eq!(edits[0].to_string(), "changed /b[1]/text() from \"x\" to \"y\"");
+//! assert!(matches!(&edits[1], Edit::Moved { .. }));
+//! ```
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::path::{self, Location};
+use crate::Node;
+
+/// The largest table of element pairs compared when matching child elements
+/// Longer lists of child elements are only matched on the elements occurring once in both, as in a
+/// patience diff, which can report more removed and added elements
+const MAX_TABLE: usize = 1 << 20;
+
+/// Options for comparing trees
+/// Attribute order is never significant, as attributes are stored unordered
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DiffOptions {
+    /// Treats content that only differs in whitespace as equal
+    /// Leading and trailing whitespace is ignored and runs of whitespace compare equal to a single space
+    pub ignore_whitespace: bool,
+}
+
+/// A single difference between two trees
+/// Locations in the old tree refer to the tree before any edit is applied, and locations in the new
+/// tree to the tree after all edits are applied
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edit {
+    /// An element only present in the new tree, at its location in the new tree
+    Added { location: Location, node: Node },
+    /// An element only present in the old tree, at its location in the old tree
+    Removed { location: Location, node: Node },
+    /// An unchanged element found at a different location
+    Moved { from: Location, to: Location },
+    AttributeAdded {
+        location: Location,
+        name: String,
+        value: String,
+    },
+    AttributeRemoved {
+        location: Location,
+        name: String,
+        value: String,
+    },
+    AttributeChanged {
+        location: Location,
+        name: String,
+        old: String,
+        new: String,
+    },
+    /// Changed content of an element, at its location in the old tree
+    ContentChanged {
+        location: Location,
+        old: String,
+        new: String,
+    },
+}
+
+impl fmt::Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Edit::Added { location, .. } => write!(f, "added {}", Root(location)),
+            Edit::Removed { location, .. } => write!(f, "removed {}", Root(location)),
+            Edit::Moved { from, to } => write!(f, "moved {} to {}", Root(from), Root(to)),
+            Edit::AttributeAdded {
+                location,
+                name,
+                value,
+            } => write!(f, "added {}/@{} = {:?}", Root(location), name, value),
+            Edit::AttributeRemoved { location, name, .. } => {
+                write!(f, "removed {}/@{}", Root(location), name)
+            }
+            Edit::AttributeChanged {
+                location,
+                name,
+                old,
+                new,
+            } => write!(
+                f,
+                "changed {}/@{} from {:?} to {:?}",
+                Root(location),
+                name,
+                old,
+                new
+            ),
+            Edit::ContentChanged { location, old, new } => {
+                write!(
+                    f,
+                    "changed {}/text() from {:?} to {:?}",
+                    Root(location),
+                    old,
+                    new
+                )
+            }
+        }
+    }
+}
+
+/// Formats a location, using `/` for the root
+struct Root<'a>(&'a Location);
+
+impl fmt::Display for Root<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 .0.is_empty() {
+            true => write!(f, "/"),
+            false => write!(f, "{}", self.0),
+        }
+    }
+}
+
+/// Compares two trees and returns the edits turning old into new
+/// Edits are ordered by kind: changed attributes and content, then removed, added and moved elements
+/// The content of an element is compared as a whole, regardless of where its text sits between
+/// the child elements
+pub fn diff(old: &Node, new: &Node) -> Vec<Edit> {
+    diff_with_options(old, new, &DiffOptions::default())
+}
+
+/// Compares two trees with the given options and returns the edits turning old into new
+pub fn diff_with_options(old: &Node, new: &Node, options: &DiffOptions) -> Vec<Edit> {
+    let root = Location::default();
+    if old.tag != new.tag {
+        return vec![
+            Edit::Removed {
+                location: root.clone(),
+                node: old.clone(),
+            },
+            Edit::Added {
+                location: root,
+                node: new.clone(),
+            },
+        ];
+    }
+
+    let mut fingerprints = Fingerprints::default();
+    fingerprints.add(old, options);
+    fingerprints.add(new, options);
+
+    let mut changes = Vec::new();
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+
+    // Pairs of matched elements left to compare
+    let mut stack = vec![(old, new, root.clone(), root)];
+    while let Some((old, new, old_location, new_location)) = stack.pop() {
+        diff_attributes(old, new, &old_location, &mut changes);
+        if !content_equal(&old.content, &new.content, options) {
+            changes.push(Edit::ContentChanged {
+                location: old_location.clone(),
+                old: old.content.clone(),
+                new: new.content.clone(),
+            });
+        }
+
+        let old_children: Vec<_> = old
+            .children()
+            .zip(path::children(old, &old_location))
+            .collect();
+        let new_children: Vec<_> = new
+            .children()
+            .zip(path::children(new, &new_location))
+            .collect();
+
+        // Identical elements are anchors, other elements are matched by tag between the anchors
+        let anchors = lcs(
+            &fingerprints.of(old_children.iter().map(|v| v.0)),
+            &fingerprints.of(new_children.iter().map(|v| v.0)),
+        );
+        let mut matched = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        for (k, l) in anchors
+            .into_iter()
+            .chain(std::iter::once((old_children.len(), new_children.len())))
+        {
+            let (old_gap, new_gap) = (&old_children[i..k], &new_children[j..l]);
+            let old_tags: Vec<_> = old_gap.iter().map(|v| v.0.tag.as_str()).collect();
+            let new_tags: Vec<_> = new_gap.iter().map(|v| v.0.tag.as_str()).collect();
+            let pairs = lcs(&old_tags, &new_tags);
+            let old_matched: BTreeSet<_> = pairs.iter().map(|v| v.0).collect();
+            let new_matched: BTreeSet<_> = pairs.iter().map(|v| v.1).collect();
+
+            for (index, child) in old_gap.iter().enumerate() {
+                if !old_matched.contains(&index) {
+                    removed.push(child.clone());
+                }
+            }
+            for (index, child) in new_gap.iter().enumerate() {
+                if !new_matched.contains(&index) {
+                    added.push(child.clone());
+                }
+            }
+            matched.extend(pairs.into_iter().map(|(a, b)| (&old_gap[a], &new_gap[b])));
+            i = k + 1;
+            j = l + 1;
+        }
+
+        for (a, b) in matched.into_iter().rev() {
+            stack.push((a.0, b.0, a.1.clone(), b.1.clone()));
+        }
+    }
+
+    // Elements removed in one place and added unchanged in another were moved
+    let mut moved = Vec::new();
+    let mut used = vec![false; removed.len()];
+    let mut candidates: HashMap<u64, VecDeque<usize>> = HashMap::new();
+    for (i, (node, _)) in removed.iter().enumerate() {
+        candidates
+            .entry(fingerprints.get(node))
+            .or_default()
+            .push_back(i);
+    }
+    added.retain(|(node, to)| {
+        let found = candidates
+            .get_mut(&fingerprints.get(node))
+            .and_then(VecDeque::pop_front);
+        match found {
+            Some(i) => {
+                used[i] = true;
+                moved.push(Edit::Moved {
+                    from: removed[i].1.clone(),
+                    to: to.clone(),
+                });
+                false
+            }
+            None => true,
+        }
+    });
+
+    let mut edits = changes;
+    edits.extend(removed.into_iter().zip(used).filter(|(_, used)| !used).map(
+        |((node, location), _)| Edit::Removed {
+            location,
+            node: node.clone(),
+        },
+    ));
+    edits.extend(added.into_iter().map(|(node, location)| Edit::Added {
+        location,
+        node: node.clone(),
+    }));
+    edits.extend(moved);
+    edits
+}
+
+fn diff_attributes(old: &Node, new: &Node, location: &Location, edits: &mut Vec<Edit>) {
+    let names: BTreeSet<_> = old.attributes.keys().chain(new.attributes.keys()).collect();
+    for name in names {
+        let edit = match (old.attributes.get(name), new.attributes.get(name)) {
+            (Some(old), Some(new)) if old == new => continue,
+            (Some(old), Some(new)) => Edit::AttributeChanged {
+                location: location.clone(),
+                name: name.clone(),
+                old: old.clone(),
+                new: new.clone(),
+            },
+            (Some(value), None) => Edit::AttributeRemoved {
+                location: location.clone(),
+                name: name.clone(),
+                value: value.clone(),
+            },
+            (None, Some(value)) => Edit::AttributeAdded {
+                location: location.clone(),
+                name: name.clone(),
+                value: value.clone(),
+            },
+            (None, None) => continue,
+        };
+        edits.push(edit);
+    }
+}
+
+fn content_equal(a: &str, b: &str, options: &DiffOptions) -> bool {
+    match options.ignore_whitespace {
+        true => a.split_whitespace().eq(b.split_whitespace()),
+        false => a == b,
+    }
+}
+
+/// Fingerprints of subtrees by node address, so that subtrees are compared without walking them
+/// again
+/// Subtrees with equal fingerprints are taken as equal under the options
+#[derive(Default)]
+struct Fingerprints(HashMap<*const Node, u64>);
+
+impl Fingerprints {
+    /// Fingerprints a tree, with each node after its child nodes
+    fn add(&mut self, root: &Node, options: &DiffOptions) {
+        let mut stack = vec![(root, false)];
+        while let Some((node, visited)) = stack.pop() {
+            if !visited {
+                stack.push((node, true));
+                stack.extend(node.children().map(|v| (v, false)));
+                continue;
+            }
+
+            let mut hasher = DefaultHasher::new();
+            node.tag.hash(&mut hasher);
+            let mut attributes: Vec<_> = node.attributes.iter().collect();
+            attributes.sort();
+            attributes.hash(&mut hasher);
+            match options.ignore_whitespace {
+                true => node
+                    .content
+                    .split_whitespace()
+                    .for_each(|v| v.hash(&mut hasher)),
+                false => node.content.hash(&mut hasher),
+            }
+            node.child_count().hash(&mut hasher);
+            for child in node.children() {
+                self.get(child).hash(&mut hasher);
+            }
+            self.0.insert(node, hasher.finish());
+        }
+    }
+
+    fn get(&self, node: &Node) -> u64 {
+        self.0[&(node as *const Node)]
+    }
+
+    fn of<'a>(&self, nodes: impl Iterator<Item = &'a Node>) -> Vec<u64> {
+        nodes.map(|v| self.get(v)).collect()
+    }
+}
+
+/// Returns the index pairs of a longest common subsequence of a and b
+/// Above the table limit, only elements occurring once in both are matched
+fn lcs<T: Eq + Hash>(a: &[T], b: &[T]) -> Vec<(usize, usize)> {
+    // Common prefixes and suffixes are matched directly, which covers most small edits cheaply
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (n, m) = (a.len() - prefix - suffix, b.len() - prefix - suffix);
+    let (middle_a, middle_b) = (&a[prefix..prefix + n], &b[prefix..prefix + m]);
+
+    let mut pairs: Vec<_> = (0..prefix).map(|i| (i, i)).collect();
+    let middle = match (n + 1).saturating_mul(m + 1) > MAX_TABLE {
+        true => patience(middle_a, middle_b),
+        false => table(middle_a, middle_b),
+    };
+    pairs.extend(middle.into_iter().map(|(i, j)| (prefix + i, prefix + j)));
+    pairs.extend((0..suffix).map(|k| (a.len() - suffix + k, b.len() - suffix + k)));
+    pairs
+}
+
+/// Returns the index pairs of a longest common subsequence of a and b from a table of lengths
+fn table<T: Eq>(a: &[T], b: &[T]) -> Vec<(usize, usize)> {
+    let (n, m) = (a.len(), b.len());
+    // lengths[i][j] is the length of the longest common subsequence of a[i..] and b[j..]
+    let mut lengths = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = match a[i] == b[j] {
+                true => lengths[i + 1][j + 1] + 1,
+                false => lengths[i + 1][j].max(lengths[i][j + 1]),
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if a[i] == b[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+/// Returns the index pairs of a longest common subsequence of the elements occurring once in both a
+/// and b
+fn patience<T: Eq + Hash>(a: &[T], b: &[T]) -> Vec<(usize, usize)> {
+    // The number of occurrences in a and b, and the last position in b
+    let mut counts: HashMap<&T, (usize, usize, usize)> = HashMap::new();
+    for v in a {
+        counts.entry(v).or_default().0 += 1;
+    }
+    for (j, v) in b.iter().enumerate() {
+        let count = counts.entry(v).or_default();
+        count.1 += 1;
+        count.2 = j;
+    }
+    let unique: Vec<(usize, usize)> = a
+        .iter()
+        .enumerate()
+        .filter_map(|(i, v)| match counts[v] {
+            (1, 1, j) => Some((i, j)),
+            _ => None,
+        })
+        .collect();
+
+    // tails[k] is the pair ending the increasing subsequence of length k + 1 with the smallest end
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous = vec![None; unique.len()];
+    for (k, &(_, j)) in unique.iter().enumerate() {
+        let length = tails.partition_point(|&t| unique[t].1 < j);
+        if length > 0 {
+            previous[k] = Some(tails[length - 1]);
+        }
+        match tails.get_mut(length) {
+            Some(tail) => *tail = k,
+            None => tails.push(k),
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let mut current = tails.last().copied();
+    while let Some(k) = current {
+        pairs.push(unique[k]);
+        current = previous[k];
+    }
+    pairs.reverse();
+    pairs
+}
@@ -0,0 +1,369 @@
+//! This is a module providing verification of enveloped XML digital signatures
+//! The `<ds:Signature>` element is found in the tree, and the digest of each referenced element is
+//! computed after applying the listed transforms and compared to the signed digest value
+//! Hashing is done through the `Digest` trait so any implementation can be plugged in
+//! Verifying the signature value itself is left to the caller, using `Signature::canonical_signed_info`
+//! Signatures usually cover the whitespace between elements, so signed documents should be parsed
+//! with `Whitespace::Preserve`
+//! References to an id used by more than one element are rejected
+//! ```
+//! use simple_xml::dsig::{self, Digest};
+//!
+//! /// Sums the bytes, for illustration only
+//! struct Checksum;
+//!
+//! impl Digest for Checksum {
+//!     fn digest(&self, algorithm: &str, data: &[u8]) -> Option<Vec<u8>> {
+//!         match algorithm {
+//!             "urn:example:checksum" => Some(vec![data.iter().fold(0u8, |a, b| a.wrapping_add(*b))]),
+//!             _ => None,
+//!         }
+//!     }
+//! }
+//!
+//! let root = simple_xml::from_string(
+//!     r##"<doc>data<ds:Signature xmlns:ds="http://www.w3.org/2000/09/xmldsig#">
+//!         <ds:SignedInfo>
+//!             <ds:CanonicalizationMethod Algorithm="http://www.w3.org/2001/10/xml-exc-c14n#"/>
+//!             <ds:SignatureMethod Algorithm="urn:example:signature"/>
+//!             <ds:Reference URI="">
+//!                 <ds:Transforms>
+//!                     <ds:Transform Algorithm="http://www.w3.org/2000/09/xmldsig#enveloped-signature"/>
+//!                 </ds:Transforms>
+//!                 <ds:DigestMethod Algorithm="urn:example:checksum"/>
+//!                 <ds:DigestValue>KQ==</ds:DigestValue>
+//!             </ds:Reference>
+//!         </ds:SignedInfo>
+//!         <ds:SignatureValue></ds:SignatureValue>
+//!     </ds:Signature></doc>"##,
+//! )
+//! .unwrap();
+//!
+//! // The checksum of "<doc>data</doc>" is 0x29
+//! dsig::verify(&root, &Checksum).unwrap();
+//! ```
+
+use std::collections::HashMap;
+
+use crate::c14n::{CanonicalOptions, Method};
+use crate::path::{self, Location};
+use crate::{entity, visit, Error, Node};
+
+/// The namespace of signature elements
+pub const DSIG_NAMESPACE: &str = "http://www.w3.org/2000/09/xmldsig#";
+pub const ENVELOPED_SIGNATURE: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+pub const C14N: &str = "http://www.w3.org/TR/2001/REC-xml-c14n-20010315";
+pub const C14N_WITH_COMMENTS: &str = "http://www.w3.org/TR/2001/REC-xml-c14n-20010315#WithComments";
+pub const EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+pub const EXC_C14N_WITH_COMMENTS: &str = "http://www.w3.org/2001/10/xml-exc-c14n#WithComments";
+pub const SHA1: &str = "http://www.w3.org/2000/09/xmldsig#sha1";
+pub const SHA256: &str = "http://www.w3.org/2001/04/xmlenc#sha256";
+pub const SHA512: &str = "http://www.w3.org/2001/04/xmlenc#sha512";
+
+/// A hash function implementation for the digest algorithms
+pub trait Digest {
+    /// Returns the digest of data using the algorithm identified by a uri such as `SHA256`,
+    /// or None if the algorithm is not supported
+    fn digest(&self, algorithm: &str, data: &[u8]) -> Option<Vec<u8>>;
+}
+
+/// A parsed `<ds:Signature>` element
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    /// The location of the signature element in the tree
+    pub location: Location,
+    pub canonicalization: Transform,
+    pub signature_method: String,
+    pub references: Vec<Reference>,
+    /// The decoded signature value
+    pub value: Vec<u8>,
+}
+
+/// A reference to signed data
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    /// Either empty for the whole document, or `#` followed by the id of an element
+    pub uri: String,
+    pub transforms: Vec<Transform>,
+    pub digest_method: String,
+    /// The decoded digest value
+    pub digest_value: Vec<u8>,
+}
+
+/// A transform or canonicalization algorithm
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transform {
+    pub algorithm: String,
+    /// The InclusiveNamespaces PrefixList of exclusive canonicalization
+    pub inclusive_prefixes: Vec<String>,
+}
+
+/// Finds the signature in a tree and verifies the digests of all its references
+/// Returns the signature so its value can be verified against `Signature::canonical_signed_info`
+pub fn verify(root: &Node, digest: &impl Digest) -> Result<Signature, Error> {
+    let signature = Signature::find(root)?;
+    signature.verify_references(root, digest)?;
+    Ok(signature)
+}
+
+impl Signature {
+    /// Finds and parses the first signature element in document order
+    /// Returns an Err of InvalidSignature if there is none or it is malformed
+    pub fn find(root: &Node) -> Result<Signature, Error> {
+        for (location, node) in visit::with_locations(root) {
+            let namespace = namespaces(root, &location, true).remove(prefix(&node.tag));
+            if local_name(&node.tag) == "Signature" && namespace.as_deref() == Some(DSIG_NAMESPACE)
+            {
+                return Signature::parse(node, location);
+            }
+        }
+        Err(invalid("no Signature element found"))
+    }
+
+    fn parse(node: &Node, location: Location) -> Result<Signature, Error> {
+        let signed_info = child(node, "SignedInfo")?;
+        let canonicalization = transform(child(signed_info, "CanonicalizationMethod")?)?;
+        let signature_method = algorithm(child(signed_info, "SignatureMethod")?)?;
+
+        let references = children(signed_info, "Reference")
+            .map(|reference| {
+                let transforms = match children(reference, "Transforms").next() {
+                    Some(v) => children(v, "Transform").map(transform).collect(),
+                    None => Ok(Vec::new()),
+                }?;
+
+                Ok(Reference {
+                    uri: reference.get_attribute("URI").cloned().unwrap_or_default(),
+                    transforms,
+                    digest_method: algorithm(child(reference, "DigestMethod")?)?,
+                    digest_value: decode_base64(&child(reference, "DigestValue")?.content)?,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        if references.is_empty() {
+            return Err(invalid("SignedInfo contains no Reference"));
+        }
+
+        Ok(Signature {
+            location,
+            canonicalization,
+            signature_method,
+            references,
+            value: decode_base64(&child(node, "SignatureValue")?.content)?,
+        })
+    }
+
+    /// Computes the digest of every reference and compares it to the signed digest value
+    /// Returns an Err of DigestMismatch containing the uri of the first reference that does not match,
+    /// an Err of UnsupportedAlgorithm for unknown transforms and digests, or an Err of InvalidSignature
+    /// if a reference can not be resolved
+    pub fn verify_references(&self, root: &Node, digest: &impl Digest) -> Result<(), Error> {
+        for reference in &self.references {
+            let data = self.dereference(root, reference)?;
+            let value = digest
+                .digest(&reference.digest_method, &data)
+                .ok_or_else(|| Error::UnsupportedAlgorithm(reference.digest_method.clone()))?;
+            if value != reference.digest_value {
+                return Err(Error::DigestMismatch(reference.uri.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the canonical form of the SignedInfo element, which is the data the signature value signs
+    pub fn canonical_signed_info(&self, root: &Node) -> Result<Vec<u8>, Error> {
+        let signature = self
+            .location
+            .get(root)
+            .ok_or_else(|| invalid("the Signature element is not in the tree"))?;
+        let signed_info = child(signature, "SignedInfo")?;
+
+        let mut location = self.location.clone();
+        location.0.push((signed_info.tag.clone(), 0));
+        canonicalize(root, &location, signed_info, &self.canonicalization)
+    }
+
+    /// Applies the transforms of a reference and returns the octets to digest
+    fn dereference(&self, root: &Node, reference: &Reference) -> Result<Vec<u8>, Error> {
+        let location = match reference.uri.strip_prefix('#') {
+            _ if reference.uri.is_empty() => Location::default(),
+            Some(id) => find_id(root, id)?,
+            None => {
+                let e = format!("unsupported reference uri {:?}", reference.uri);
+                return Err(invalid(&e));
+            }
+        };
+
+        let mut node = location
+            .get(root)
+            .ok_or_else(|| invalid("the referenced element is not in the tree"))?
+            .clone();
+
+        // Without a canonicalization transform the result is canonicalized with C14N 1.0
+        let mut canonicalization = Transform {
+            algorithm: C14N.to_owned(),
+            inclusive_prefixes: Vec::new(),
+        };
+        for transform in &reference.transforms {
+            match transform.algorithm.as_str() {
+                ENVELOPED_SIGNATURE => {
+                    if let Some(relative) = self.location.0.strip_prefix(&location.0[..]) {
+                        remove(&mut node, relative);
+                    }
+                }
+                C14N | C14N_WITH_COMMENTS | EXC_C14N | EXC_C14N_WITH_COMMENTS => {
+                    canonicalization = transform.clone();
+                }
+                algorithm => return Err(Error::UnsupportedAlgorithm(algorithm.to_owned())),
+            }
+        }
+
+        canonicalize(root, &location, &node, &canonicalization)
+    }
+}
+
+/// Removes the node at a location relative to node, the signature of an enveloped-signature transform
+fn remove(node: &mut Node, relative: &[(String, usize)]) {
+    let (last, parent) = match relative.split_last() {
+        Some(v) => v,
+        None => return,
+    };
+    let parent = Location(parent.to_vec());
+    if let Some(parent) = parent.get_mut(node) {
+        if let Some(position) = parent.position(&last.0, last.1) {
+            parent.remove_at(position);
+        }
+    }
+}
+
+/// Canonicalizes a node found at a location, with the namespaces declared by its ancestors
+fn canonicalize(
+    root: &Node,
+    location: &Location,
+    node: &Node,
+    transform: &Transform,
+) -> Result<Vec<u8>, Error> {
+    let method = match transform.algorithm.as_str() {
+        C14N | C14N_WITH_COMMENTS => Method::Inclusive,
+        EXC_C14N | EXC_C14N_WITH_COMMENTS => Method::Exclusive,
+        algorithm => return Err(Error::UnsupportedAlgorithm(algorithm.to_owned())),
+    };
+
+    let options = CanonicalOptions {
+        method,
+        inclusive_prefixes: transform.inclusive_prefixes.clone(),
+        inherited_namespaces: namespaces(root, location, false).into_iter().collect(),
+    };
+
+    let mut output = Vec::new();
+    node.write_canonical(&mut output, &options)?;
+    Ok(output)
+}
+
+/// Returns the namespaces in scope at a location by prefix, from the ancestors and optionally the
+/// element itself
+fn namespaces(root: &Node, location: &Location, inclusive: bool) -> HashMap<String, String> {
+    let mut scope = HashMap::new();
+    let mut node = Some(root);
+    let steps = location.0.len() + usize::from(inclusive);
+    for i in 0..steps {
+        let current = match node {
+            Some(v) => v,
+            None => break,
+        };
+
+        for (key, value) in &current.attributes {
+            let prefix = match (key.as_str(), key.strip_prefix("xmlns:")) {
+                ("xmlns", _) => "",
+                (_, Some(prefix)) => prefix,
+                _ => continue,
+            };
+            scope.insert(prefix.to_owned(), entity::decode(value));
+        }
+
+        node = location
+            .0
+            .get(i)
+            .and_then(|(tag, index)| current.get_nodes(tag)?.get(*index));
+    }
+    scope
+}
+
+/// Finds the element with an `ID`, `Id` or `id` attribute equal to id
+/// Returns an error if there is no such element or more than one, as an element with the same id
+/// elsewhere in the document could otherwise be verified in place of the one that is used
+fn find_id(root: &Node, id: &str) -> Result<Location, Error> {
+    let mut found = path::find_ids(root, &["ID", "Id", "id"], id);
+    match (found.next(), found.next()) {
+        (Some(location), None) => Ok(location),
+        (Some(_), Some(_)) => Err(invalid(&format!("duplicate id {:?}", id))),
+        (None, _) => Err(invalid(&format!("no element with id {:?}", id))),
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::InvalidSignature(message.to_owned())
+}
+
+fn prefix(name: &str) -> &str {
+    name.split_once(':').map_or("", |(prefix, _)| prefix)
+}
+
+fn local_name(name: &str) -> &str {
+    name.split_once(':').map_or(name, |(_, local)| local)
+}
+
+fn children<'a>(node: &'a Node, name: &'a str) -> impl Iterator<Item = &'a Node> {
+    node.children().filter(move |v| local_name(&v.tag) == name)
+}
+
+fn child<'a>(node: &'a Node, name: &'a str) -> Result<&'a Node, Error> {
+    children(node, name)
+        .next()
+        .ok_or_else(|| invalid(&format!("{} is missing {}", local_name(&node.tag), name)))
+}
+
+fn algorithm(node: &Node) -> Result<String, Error> {
+    node.get_attribute("Algorithm")
+        .cloned()
+        .ok_or_else(|| invalid(&format!("{} is missing Algorithm", local_name(&node.tag))))
+}
+
+fn transform(node: &Node) -> Result<Transform, Error> {
+    let inclusive_prefixes = children(node, "InclusiveNamespaces")
+        .filter_map(|v| v.get_attribute("PrefixList"))
+        .flat_map(|v| v.split_whitespace().map(str::to_owned))
+        .collect();
+
+    Ok(Transform {
+        algorithm: algorithm(node)?,
+        inclusive_prefixes,
+    })
+}
+
+/// Decodes base64, ignoring whitespace
+fn decode_base64(text: &str) -> Result<Vec<u8>, Error> {
+    let mut output = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => continue,
+            _ => return Err(invalid(&format!("invalid base64 {:?}", text.trim()))),
+        };
+
+        buffer = (buffer << 6 | u32::from(value)) & 0xffff;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(output)
+}
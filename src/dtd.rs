@@ -0,0 +1,934 @@
+//! This is a module providing parsing of document type definitions and validation of documents against them
+//! ```
+//! let document = simple_xml::document_from_string(
+//!     r#"<!DOCTYPE note [
+//!         <!ELEMENT note (to, body?)>
+//!         <!ELEMENT to (#PCDATA)>
+//!         <!ELEMENT body (#PCDATA)>
+//!         <!ATTLIST note lang (en|sv) #REQUIRED>
+//!     ]>
+//!     <note lang="de"><body>Hello</body></note>"#,
+//! )
+//! .unwrap();
+//!
+//! let dtd = document.doctype.as_ref().unwrap().internal_subset.as_ref().unwrap();
+//! let violations = simple_xml::dtd::validate(&document, dtd);
+//! assert_eq!(violations.len(), 2);
+//! assert_eq!(violations[0].path, "/note");
+//! ```
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::{newlines_in_slice, Document, Error, Node, ParseError};
+
+/// A parsed document type definition
+/// Contains the declarations of either an external DTD file or an internal subset
+#[derive(Debug, Default, Clone)]
+pub struct Dtd {
+    pub elements: HashMap<String, ContentSpec>,
+    pub attributes: HashMap<String, Vec<AttributeDecl>>,
+    pub entities: HashMap<String, EntityDecl>,
+    pub parameter_entities: HashMap<String, EntityDecl>,
+    pub notations: HashMap<String, ExternalId>,
+}
+
+/// The <!DOCTYPE> declaration of a document
+#[derive(Debug, Clone)]
+pub struct Doctype {
+    /// The name of the root element
+    pub name: String,
+    pub external_id: Option<ExternalId>,
+    pub internal_subset: Option<Dtd>,
+}
+
+/// A reference to an external resource by system and optionally public identifier
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExternalId {
+    System(String),
+    /// Public identifier and system literal
+    /// The system literal is optional only for notations
+    Public(String, Option<String>),
+}
+
+/// The allowed content of an element as declared by <!ELEMENT>
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContentSpec {
+    Empty,
+    Any,
+    /// Text mixed with any of the listed elements
+    Mixed(Vec<String>),
+    /// Element only content
+    Children(ContentParticle),
+}
+
+/// A node in an element content model
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentParticle {
+    pub kind: ParticleKind,
+    pub repeat: Repeat,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParticleKind {
+    Name(String),
+    Seq(Vec<ContentParticle>),
+    Choice(Vec<ContentParticle>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Repeat {
+    Once,
+    /// ?
+    Optional,
+    /// *
+    ZeroOrMore,
+    /// +
+    OneOrMore,
+}
+
+/// A single attribute definition of an <!ATTLIST>
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeDecl {
+    pub name: String,
+    pub kind: AttributeType,
+    pub default: DefaultDecl,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeType {
+    CData,
+    Id,
+    IdRef,
+    IdRefs,
+    Entity,
+    Entities,
+    NmToken,
+    NmTokens,
+    Notation(Vec<String>),
+    Enumeration(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DefaultDecl {
+    Required,
+    Implied,
+    Fixed(String),
+    Default(String),
+}
+
+/// An <!ENTITY> declaration
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntityDecl {
+    /// The literal replacement text
+    Internal(String),
+    External {
+        id: ExternalId,
+        /// The notation of an unparsed entity
+        ndata: Option<String>,
+    },
+}
+
+/// A single problem found when validating a document against a DTD
+#[derive(Debug, Error, Clone, PartialEq)]
+#[error("{path}: {kind}")]
+pub struct Violation {
+    /// The path to the offending node, e.g; /note/to[2]
+    pub path: String,
+    pub kind: ViolationKind,
+}
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum ViolationKind {
+    #[error("Root element {found:?} does not match the doctype name {expected:?}")]
+    RootMismatch { expected: String, found: String },
+    #[error("Element {0:?} is not declared")]
+    UndeclaredElement(String),
+    #[error("Children {found:?} do not match the content model {expected}")]
+    InvalidContent {
+        expected: String,
+        found: Vec<String>,
+    },
+    #[error("Text is not allowed in element only content")]
+    UnexpectedText,
+    #[error("Missing required attribute {0:?}")]
+    MissingAttribute(String),
+    #[error("Attribute {0:?} is not declared")]
+    UndeclaredAttribute(String),
+    #[error("Value {value:?} of attribute {name:?} is not one of {allowed:?}")]
+    InvalidAttributeValue {
+        name: String,
+        value: String,
+        allowed: Vec<String>,
+    },
+    #[error("Attribute {name:?} must have the fixed value {expected:?}")]
+    FixedAttributeMismatch { name: String, expected: String },
+    #[error("Duplicate ID {0:?}")]
+    DuplicateId(String),
+}
+
+/// Loads a DTD from a file
+pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Dtd, Error> {
+    Dtd::parse(&std::fs::read_to_string(path)?)
+}
+
+impl Dtd {
+    /// Parses the declarations of an external DTD or internal subset
+    /// Comments and processing instructions are skipped
+    pub fn parse(string: &str) -> Result<Dtd, Error> {
+        let mut dtd = Dtd::default();
+        let mut scanner = Scanner::new(string);
+
+        loop {
+            scanner.skip_whitespace();
+            if scanner.is_empty() {
+                return Ok(dtd);
+            }
+
+            let start = scanner.pos;
+            if scanner.eat("<!--") {
+                scanner
+                    .skip_past("-->")
+                    .ok_or_else(|| scanner.error_at(start))?;
+            } else if scanner.eat("<?") {
+                scanner
+                    .skip_past("?>")
+                    .ok_or_else(|| scanner.error_at(start))?;
+            } else if scanner.eat("<!ELEMENT") {
+                dtd.parse_element(&mut scanner)
+                    .ok_or_else(|| scanner.error_at(start))?;
+            } else if scanner.eat("<!ATTLIST") {
+                dtd.parse_attlist(&mut scanner)
+                    .ok_or_else(|| scanner.error_at(start))?;
+            } else if scanner.eat("<!ENTITY") {
+                dtd.parse_entity(&mut scanner)
+                    .ok_or_else(|| scanner.error_at(start))?;
+            } else if scanner.eat("<!NOTATION") {
+                dtd.parse_notation(&mut scanner)
+                    .ok_or_else(|| scanner.error_at(start))?;
+            } else if scanner.eat("%") {
+                // Parameter entity references between declarations are not expanded
+                scanner.name().ok_or_else(|| scanner.error_at(start))?;
+                if !scanner.eat(";") {
+                    return Err(scanner.error_at(start));
+                }
+            } else {
+                return Err(scanner.error_at(start));
+            }
+        }
+    }
+
+    /// Adds all declarations of other which are not already declared in self
+    /// Used to let the internal subset take precedence over an external DTD
+    pub fn merge(&mut self, other: &Dtd) {
+        for (k, v) in &other.elements {
+            self.elements.entry(k.clone()).or_insert_with(|| v.clone());
+        }
+        for (k, v) in &other.attributes {
+            let decls = self.attributes.entry(k.clone()).or_default();
+            for decl in v {
+                if !decls.iter().any(|d| d.name == decl.name) {
+                    decls.push(decl.clone());
+                }
+            }
+        }
+        for (k, v) in &other.entities {
+            self.entities.entry(k.clone()).or_insert_with(|| v.clone());
+        }
+        for (k, v) in &other.parameter_entities {
+            self.parameter_entities
+                .entry(k.clone())
+                .or_insert_with(|| v.clone());
+        }
+        for (k, v) in &other.notations {
+            self.notations.entry(k.clone()).or_insert_with(|| v.clone());
+        }
+    }
+
+    fn parse_element(&mut self, scanner: &mut Scanner) -> Option<()> {
+        scanner.require_whitespace()?;
+        let name = scanner.name()?.to_owned();
+        scanner.require_whitespace()?;
+
+        let spec = if scanner.eat("EMPTY") {
+            ContentSpec::Empty
+        } else if scanner.eat("ANY") {
+            ContentSpec::Any
+        } else {
+            let open = scanner.pos;
+            scanner.expect("(")?;
+            scanner.skip_whitespace();
+            if scanner.eat("#PCDATA") {
+                let mut names = Vec::new();
+                loop {
+                    scanner.skip_whitespace();
+                    if scanner.eat(")") {
+                        break;
+                    }
+                    scanner.expect("|")?;
+                    scanner.skip_whitespace();
+                    names.push(scanner.name()?.to_owned());
+                }
+                // (#PCDATA) may omit the star, but not when names are listed
+                if !scanner.eat("*") && !names.is_empty() {
+                    return None;
+                }
+                ContentSpec::Mixed(names)
+            } else {
+                scanner.pos = open;
+                ContentSpec::Children(parse_particle(scanner, 0)?)
+            }
+        };
+
+        scanner.skip_whitespace();
+        scanner.expect(">")?;
+        self.elements.insert(name, spec);
+        Some(())
+    }
+
+    fn parse_attlist(&mut self, scanner: &mut Scanner) -> Option<()> {
+        scanner.require_whitespace()?;
+        let element = scanner.name()?.to_owned();
+        let decls = self.attributes.entry(element).or_default();
+
+        loop {
+            scanner.skip_whitespace();
+            if scanner.eat(">") {
+                return Some(());
+            }
+
+            let name = scanner.name()?.to_owned();
+            scanner.require_whitespace()?;
+            let kind = if scanner.eat("CDATA") {
+                AttributeType::CData
+            } else if scanner.eat("IDREFS") {
+                AttributeType::IdRefs
+            } else if scanner.eat("IDREF") {
+                AttributeType::IdRef
+            } else if scanner.eat("ID") {
+                AttributeType::Id
+            } else if scanner.eat("ENTITY") {
+                AttributeType::Entity
+            } else if scanner.eat("ENTITIES") {
+                AttributeType::Entities
+            } else if scanner.eat("NMTOKENS") {
+                AttributeType::NmTokens
+            } else if scanner.eat("NMTOKEN") {
+                AttributeType::NmToken
+            } else if scanner.eat("NOTATION") {
+                scanner.require_whitespace()?;
+                AttributeType::Notation(parse_enumeration(scanner)?)
+            } else {
+                AttributeType::Enumeration(parse_enumeration(scanner)?)
+            };
+
+            scanner.require_whitespace()?;
+            let default = if scanner.eat("#REQUIRED") {
+                DefaultDecl::Required
+            } else if scanner.eat("#IMPLIED") {
+                DefaultDecl::Implied
+            } else if scanner.eat("#FIXED") {
+                scanner.require_whitespace()?;
+                DefaultDecl::Fixed(scanner.quoted()?.to_owned())
+            } else {
+                DefaultDecl::Default(scanner.quoted()?.to_owned())
+            };
+
+            // The first declaration of an attribute is binding
+            if !decls.iter().any(|d| d.name == name) {
+                decls.push(AttributeDecl {
+                    name,
+                    kind,
+                    default,
+                });
+            }
+        }
+    }
+
+    fn parse_entity(&mut self, scanner: &mut Scanner) -> Option<()> {
+        scanner.require_whitespace()?;
+        let parameter = scanner.eat("%");
+        if parameter {
+            scanner.require_whitespace()?;
+        }
+
+        let name = scanner.name()?.to_owned();
+        scanner.require_whitespace()?;
+
+        let decl = match scanner.quoted() {
+            Some(value) => EntityDecl::Internal(value.to_owned()),
+            None => {
+                let id = parse_external_id(scanner, false)?;
+                let mark = scanner.pos;
+                let ndata = if scanner.require_whitespace().is_some() && scanner.eat("NDATA") {
+                    scanner.require_whitespace()?;
+                    Some(scanner.name()?.to_owned())
+                } else {
+                    scanner.pos = mark;
+                    None
+                };
+                EntityDecl::External { id, ndata }
+            }
+        };
+
+        scanner.skip_whitespace();
+        scanner.expect(">")?;
+
+        let entities = if parameter {
+            &mut self.parameter_entities
+        } else {
+            &mut self.entities
+        };
+        // The first declaration of an entity is binding
+        entities.entry(name).or_insert(decl);
+        Some(())
+    }
+
+    fn parse_notation(&mut self, scanner: &mut Scanner) -> Option<()> {
+        scanner.require_whitespace()?;
+        let name = scanner.name()?.to_owned();
+        scanner.require_whitespace()?;
+        let id = parse_external_id(scanner, true)?;
+        scanner.skip_whitespace();
+        scanner.expect(">")?;
+        self.notations.insert(name, id);
+        Some(())
+    }
+}
+
+impl Doctype {
+    /// Parses a full <!DOCTYPE ...> declaration
+    pub fn parse(string: &str) -> Result<Doctype, Error> {
+        let mut scanner = Scanner::new(string);
+        Doctype::parse_inner(&mut scanner).ok_or_else(|| scanner.error_at(0))?
+    }
+
+    fn parse_inner(scanner: &mut Scanner) -> Option<Result<Doctype, Error>> {
+        scanner.expect("<!DOCTYPE")?;
+        scanner.require_whitespace()?;
+        let name = scanner.name()?.to_owned();
+
+        let mark = scanner.pos;
+        scanner.skip_whitespace();
+        let external_id = if scanner.peek_is("SYSTEM") || scanner.peek_is("PUBLIC") {
+            Some(parse_external_id(scanner, false)?)
+        } else {
+            scanner.pos = mark;
+            None
+        };
+
+        scanner.skip_whitespace();
+        let internal_subset = if scanner.eat("[") {
+            let start = scanner.pos;
+            let end = start + subset_len(scanner.rest())?;
+            let subset = match Dtd::parse(&scanner.string[start..end]) {
+                Ok(v) => v,
+                Err(Error::ParseError(e, ln)) => {
+                    return Some(Err(Error::ParseError(
+                        e,
+                        ln + newlines_in_slice(&scanner.string[..start]),
+                    )))
+                }
+                Err(e) => return Some(Err(e)),
+            };
+            scanner.pos = end;
+            scanner.expect("]")?;
+            scanner.skip_whitespace();
+            Some(subset)
+        } else {
+            None
+        };
+
+        scanner.expect(">")?;
+        Some(Ok(Doctype {
+            name,
+            external_id,
+            internal_subset,
+        }))
+    }
+}
+
+/// Returns the length of a <!DOCTYPE ...> declaration at the start of the string including the closing delimiter
+/// Quoted literals, comments, and the internal subset may contain '>' and are skipped
+pub(crate) fn doctype_len(string: &str) -> Option<usize> {
+    let mut pos = 0;
+    let mut in_subset = false;
+    while pos < string.len() {
+        let rest = &string[pos..];
+        let c = rest.chars().next()?;
+        if rest.starts_with("<!--") {
+            pos += rest.find("-->")? + 3;
+            continue;
+        }
+
+        match c {
+            '"' | '\'' => pos += rest[1..].find(c)? + 1,
+            '[' => in_subset = true,
+            ']' => in_subset = false,
+            '>' if !in_subset => return Some(pos + 1),
+            _ => {}
+        }
+        pos += c.len_utf8();
+    }
+    None
+}
+
+/// Returns the length of an internal subset up to but not including the closing ']'
+fn subset_len(string: &str) -> Option<usize> {
+    let mut pos = 0;
+    while pos < string.len() {
+        let rest = &string[pos..];
+        let c = rest.chars().next()?;
+        if rest.starts_with("<!--") {
+            pos += rest.find("-->")? + 3;
+            continue;
+        }
+
+        match c {
+            '"' | '\'' => pos += rest[1..].find(c)? + 1,
+            ']' => return Some(pos),
+            _ => {}
+        }
+        pos += c.len_utf8();
+    }
+    None
+}
+
+/// The deepest nesting of groups in a content model
+/// Content models are parsed, validated and dropped recursively, so deeper nesting is rejected instead
+/// of overflowing the stack
+const MAX_GROUP_DEPTH: usize = 256;
+
+fn parse_particle(scanner: &mut Scanner, depth: usize) -> Option<ContentParticle> {
+    let kind = if scanner.eat("(") {
+        if depth >= MAX_GROUP_DEPTH {
+            return None;
+        }
+        let mut items = Vec::new();
+        let mut separator = None;
+        loop {
+            scanner.skip_whitespace();
+            items.push(parse_particle(scanner, depth + 1)?);
+            scanner.skip_whitespace();
+            if scanner.eat(")") {
+                break;
+            }
+
+            let c = if scanner.eat(",") {
+                ','
+            } else if scanner.eat("|") {
+                '|'
+            } else {
+                return None;
+            };
+            // Separators may not be mixed within a group
+            if *separator.get_or_insert(c) != c {
+                return None;
+            }
+        }
+
+        match separator {
+            Some('|') => ParticleKind::Choice(items),
+            _ => ParticleKind::Seq(items),
+        }
+    } else {
+        ParticleKind::Name(scanner.name()?.to_owned())
+    };
+
+    let repeat = if scanner.eat("?") {
+        Repeat::Optional
+    } else if scanner.eat("*") {
+        Repeat::ZeroOrMore
+    } else if scanner.eat("+") {
+        Repeat::OneOrMore
+    } else {
+        Repeat::Once
+    };
+
+    Some(ContentParticle { kind, repeat })
+}
+
+/// Parses (a | b | c)
+fn parse_enumeration(scanner: &mut Scanner) -> Option<Vec<String>> {
+    scanner.expect("(")?;
+    let mut values = Vec::new();
+    loop {
+        scanner.skip_whitespace();
+        values.push(scanner.nmtoken()?.to_owned());
+        scanner.skip_whitespace();
+        if scanner.eat(")") {
+            return Some(values);
+        }
+        scanner.expect("|")?;
+    }
+}
+
+fn parse_external_id(scanner: &mut Scanner, notation: bool) -> Option<ExternalId> {
+    if scanner.eat("SYSTEM") {
+        scanner.require_whitespace()?;
+        Some(ExternalId::System(scanner.quoted()?.to_owned()))
+    } else if scanner.eat("PUBLIC") {
+        scanner.require_whitespace()?;
+        let public = scanner.quoted()?.to_owned();
+        let mark = scanner.pos;
+        scanner.skip_whitespace();
+        match scanner.quoted() {
+            Some(system) => Some(ExternalId::Public(public, Some(system.to_owned()))),
+            None if notation => {
+                scanner.pos = mark;
+                Some(ExternalId::Public(public, None))
+            }
+            None => None,
+        }
+    } else {
+        None
+    }
+}
+
+/// A cursor over a declaration string
+struct Scanner<'a> {
+    string: &'a str,
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(string: &'a str) -> Self {
+        Scanner { string, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.string[self.pos..]
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.string.len()
+    }
+
+    fn error_at(&self, pos: usize) -> Error {
+        let decl = self.string[pos..]
+            .split('>')
+            .next()
+            .unwrap_or_default()
+            .to_owned();
+
+        Error::ParseError(
+            ParseError::InvalidDeclaration(decl),
+            newlines_in_slice(&self.string[..pos]),
+        )
+    }
+
+    fn peek_is(&self, s: &str) -> bool {
+        self.rest().starts_with(s)
+    }
+
+    fn eat(&mut self, s: &str) -> bool {
+        if self.peek_is(s) {
+            self.pos += s.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, s: &str) -> Option<()> {
+        if self.eat(s) {
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn require_whitespace(&mut self) -> Option<()> {
+        let pos = self.pos;
+        self.skip_whitespace();
+        if self.pos == pos {
+            None
+        } else {
+            Some(())
+        }
+    }
+
+    fn skip_past(&mut self, s: &str) -> Option<()> {
+        self.pos += self.rest().find(s)? + s.len();
+        Some(())
+    }
+
+    fn take_while(&mut self, f: impl Fn(usize, char) -> bool) -> Option<&'a str> {
+        let rest = self.rest();
+        let len = rest
+            .char_indices()
+            .find(|&(i, c)| !f(i, c))
+            .map(|(i, _)| i)
+            .unwrap_or(rest.len());
+
+        if len == 0 {
+            return None;
+        }
+        self.pos += len;
+        Some(&rest[..len])
+    }
+
+    fn name(&mut self) -> Option<&'a str> {
+        self.take_while(|i, c| match i {
+            0 => crate::name::is_name_start_char(c),
+            _ => crate::name::is_name_char(c),
+        })
+    }
+
+    fn nmtoken(&mut self) -> Option<&'a str> {
+        self.take_while(|_, c| crate::name::is_name_char(c))
+    }
+
+    fn quoted(&mut self) -> Option<&'a str> {
+        let rest = self.rest();
+        let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let len = rest[1..].find(quote)?;
+        self.pos += len + 2;
+        Some(&rest[1..len + 1])
+    }
+}
+
+/// Validates a document against a DTD and returns all violations found
+/// Declarations in the internal subset of the document take precedence over the given DTD
+/// An empty list means the document is valid
+pub fn validate(document: &Document, dtd: &Dtd) -> Vec<Violation> {
+    let mut violations = Vec::new();
+
+    let merged;
+    let dtd = match document
+        .doctype
+        .as_ref()
+        .and_then(|d| d.internal_subset.as_ref())
+    {
+        Some(internal) => {
+            let mut v = internal.clone();
+            v.merge(dtd);
+            merged = v;
+            &merged
+        }
+        None => dtd,
+    };
+
+    let root = &document.root;
+    let path = format!("/{}", root.tag);
+    if let Some(doctype) = &document.doctype {
+        if doctype.name != root.tag {
+            violations.push(Violation {
+                path: path.clone(),
+                kind: ViolationKind::RootMismatch {
+                    expected: doctype.name.clone(),
+                    found: root.tag.clone(),
+                },
+            });
+        }
+    }
+
+    let mut ids = HashSet::new();
+    validate_node(root, dtd, path, &mut ids, &mut violations);
+    violations
+}
+
+fn validate_node<'a>(
+    node: &'a Node,
+    dtd: &Dtd,
+    path: String,
+    ids: &mut HashSet<&'a str>,
+    violations: &mut Vec<Violation>,
+) {
+    let mut report = |kind| {
+        violations.push(Violation {
+            path: path.clone(),
+            kind,
+        })
+    };
+
+    let names = node.children().map(|n| n.tag.as_str()).collect::<Vec<_>>();
+    match dtd.elements.get(&node.tag) {
+        None => report(ViolationKind::UndeclaredElement(node.tag.clone())),
+        Some(ContentSpec::Any) => {}
+        Some(ContentSpec::Empty) => {
+            if !names.is_empty() || !node.content.is_empty() {
+                report(ViolationKind::InvalidContent {
+                    expected: "EMPTY".into(),
+                    found: names.iter().map(|v| v.to_string()).collect(),
+                })
+            }
+        }
+        Some(ContentSpec::Mixed(allowed)) => {
+            if !names.iter().all(|n| allowed.iter().any(|a| a == n)) {
+                report(ViolationKind::InvalidContent {
+                    expected: ContentSpec::Mixed(allowed.clone()).to_string(),
+                    found: names.iter().map(|v| v.to_string()).collect(),
+                })
+            }
+        }
+        Some(ContentSpec::Children(particle)) => {
+            if !node.content.trim().is_empty() {
+                report(ViolationKind::UnexpectedText);
+            }
+            if !particle.match_at(&names, 0).contains(&names.len()) {
+                report(ViolationKind::InvalidContent {
+                    expected: particle.to_string(),
+                    found: names.iter().map(|v| v.to_string()).collect(),
+                })
+            }
+        }
+    }
+
+    let decls = dtd
+        .attributes
+        .get(&node.tag)
+        .map(|v| &v[..])
+        .unwrap_or_default();
+    for decl in decls {
+        let value = match (node.attributes.get(&decl.name), &decl.default) {
+            (Some(v), _) => v,
+            (None, DefaultDecl::Required) => {
+                report(ViolationKind::MissingAttribute(decl.name.clone()));
+                continue;
+            }
+            (None, _) => continue,
+        };
+
+        if let DefaultDecl::Fixed(expected) = &decl.default {
+            if value != expected {
+                report(ViolationKind::FixedAttributeMismatch {
+                    name: decl.name.clone(),
+                    expected: expected.clone(),
+                });
+            }
+        }
+
+        match &decl.kind {
+            AttributeType::Enumeration(allowed) | AttributeType::Notation(allowed)
+                if !allowed.iter().any(|a| a == value.trim()) =>
+            {
+                report(ViolationKind::InvalidAttributeValue {
+                    name: decl.name.clone(),
+                    value: value.clone(),
+                    allowed: allowed.clone(),
+                })
+            }
+            AttributeType::Id if !ids.insert(value.trim()) => {
+                report(ViolationKind::DuplicateId(value.trim().to_owned()));
+            }
+            _ => {}
+        }
+    }
+
+    // Only report undeclared attributes on declared elements to avoid duplicate reports
+    if dtd.elements.contains_key(&node.tag) {
+        let mut undeclared = node
+            .attributes
+            .keys()
+            .filter(|k| !decls.iter().any(|d| &d.name == *k))
+            .collect::<Vec<_>>();
+        undeclared.sort();
+        for key in undeclared {
+            report(ViolationKind::UndeclaredAttribute(key.clone()));
+        }
+    }
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for child in node.children() {
+        let index = counts.entry(&child.tag).or_default();
+        *index += 1;
+        let path = format!("{}/{}[{}]", path, child.tag, index);
+        validate_node(child, dtd, path, ids, violations);
+    }
+}
+
+impl ContentParticle {
+    /// Returns all positions in names where a match of self starting at pos can end
+    fn match_at(&self, names: &[&str], pos: usize) -> BTreeSet<usize> {
+        let once = |pos| self.match_once(names, pos);
+        match self.repeat {
+            Repeat::Once => once(pos),
+            Repeat::Optional => {
+                let mut ends = once(pos);
+                ends.insert(pos);
+                ends
+            }
+            Repeat::ZeroOrMore | Repeat::OneOrMore => {
+                let mut ends = match self.repeat {
+                    Repeat::ZeroOrMore => std::iter::once(pos).collect(),
+                    _ => once(pos),
+                };
+                let mut frontier = ends.clone();
+                while !frontier.is_empty() {
+                    let next = frontier
+                        .iter()
+                        .flat_map(|&p| once(p))
+                        .filter(|p| !ends.contains(p))
+                        .collect::<BTreeSet<_>>();
+                    ends.extend(next.iter().copied());
+                    frontier = next;
+                }
+                ends
+            }
+        }
+    }
+
+    fn match_once(&self, names: &[&str], pos: usize) -> BTreeSet<usize> {
+        match &self.kind {
+            ParticleKind::Name(name) => match names.get(pos) {
+                Some(v) if v == name => std::iter::once(pos + 1).collect(),
+                _ => BTreeSet::new(),
+            },
+            ParticleKind::Seq(items) => items
+                .iter()
+                .fold(std::iter::once(pos).collect(), |ends, item| {
+                    ends.iter().flat_map(|&p| item.match_at(names, p)).collect()
+                }),
+            ParticleKind::Choice(items) => items
+                .iter()
+                .flat_map(|item| item.match_at(names, pos))
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for ContentParticle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |items: &[ContentParticle], sep| {
+            items
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(sep)
+        };
+
+        match &self.kind {
+            ParticleKind::Name(name) => write!(f, "{}", name)?,
+            ParticleKind::Seq(items) => write!(f, "({})", join(items, ", "))?,
+            ParticleKind::Choice(items) => write!(f, "({})", join(items, " | "))?,
+        }
+
+        match self.repeat {
+            Repeat::Once => Ok(()),
+            Repeat::Optional => write!(f, "?"),
+            Repeat::ZeroOrMore => write!(f, "*"),
+            Repeat::OneOrMore => write!(f, "+"),
+        }
+    }
+}
+
+impl fmt::Display for ContentSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentSpec::Empty => write!(f, "EMPTY"),
+            ContentSpec::Any => write!(f, "ANY"),
+            ContentSpec::Mixed(names) if names.is_empty() => write!(f, "(#PCDATA)"),
+            ContentSpec::Mixed(names) => write!(f, "(#PCDATA | {})*", names.join(" | ")),
+            ContentSpec::Children(particle) => write!(f, "{}", particle),
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! This is a module providing detection of the character encoding of a document and transcoding
+//! between it and UTF-8
+//! The encoding is taken from the byte order mark, or else from the encoding declaration, and
+//! defaults to UTF-8. Supported are UTF-8, UTF-16 in both byte orders, ISO-8859-1 and Windows-1252
+//! ```
+//! use simple_xml::Encoding;
+//!
+//! let bytes = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><name>Andr\xe9</name>";
+//! let (string, encoding) = simple_xml::encoding::decode(bytes).unwrap();
+//! assert_eq!(encoding, Encoding::Latin1);
+//!
+//! let root = simple_xml::from_string(&string).unwrap();
+//! assert_eq!(root.content, "André");
+//! assert_eq!(
+//!     root.to_bytes(encoding),
+//!     b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><name>Andr\xe9</name>"
+//! );
+//! ```
+
+use std::fmt;
+
+use crate::{newlines_in_slice, Error, ParseError};
+
+/// A character encoding documents can be read from and written in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// UTF-16 in little endian byte order
+    Utf16Le,
+    /// UTF-16 in big endian byte order
+    Utf16Be,
+    /// ISO-8859-1, where every byte is the code point of the same value
+    Latin1,
+    /// Windows-1252, which is ISO-8859-1 with printable characters in place of most C1 controls
+    Windows1252,
+}
+
+/// The characters of Windows-1252 from 0x80 to 0x9f
+/// The five undefined bytes map to the control characters of the same value
+const WINDOWS_1252: [char; 32] = [
+    '\u{20ac}', '\u{81}', '\u{201a}', '\u{192}', '\u{201e}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2c6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8d}', '\u{17d}', '\u{8f}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201c}', '\u{201d}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2dc}', '\u{2122}', '\u{161}', '\u{203a}', '\u{153}', '\u{9d}', '\u{17e}', '\u{178}',
+];
+
+impl Encoding {
+    /// Returns the encoding for a name used in encoding declarations, ignoring case
+    pub fn from_label(label: &str) -> Option<Encoding> {
+        let encoding = match label.to_ascii_uppercase().as_str() {
+            "UTF-8" | "UTF8" | "US-ASCII" | "ASCII" => Encoding::Utf8,
+            "UTF-16LE" => Encoding::Utf16Le,
+            // Without a byte order mark UTF-16 is big endian
+            "UTF-16" | "UTF-16BE" => Encoding::Utf16Be,
+            "ISO-8859-1" | "ISO_8859-1" | "LATIN1" | "L1" => Encoding::Latin1,
+            "WINDOWS-1252" | "CP1252" => Encoding::Windows1252,
+            _ => return None,
+        };
+        Some(encoding)
+    }
+
+    /// Returns the name written in encoding declarations
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le | Encoding::Utf16Be => "UTF-16",
+            Encoding::Latin1 => "ISO-8859-1",
+            Encoding::Windows1252 => "windows-1252",
+        }
+    }
+
+    /// Encodes a string, starting with a byte order mark for UTF-16
+    /// Characters the encoding can not represent are written as character references, so the string
+    /// should only contain such characters in content and attribute values
+    pub fn encode(&self, string: &str) -> Vec<u8> {
+        match self {
+            Encoding::Utf8 => string.as_bytes().to_vec(),
+            Encoding::Utf16Le => std::iter::once(0xfeff)
+                .chain(string.encode_utf16())
+                .flat_map(u16::to_le_bytes)
+                .collect(),
+            Encoding::Utf16Be => std::iter::once(0xfeff)
+                .chain(string.encode_utf16())
+                .flat_map(u16::to_be_bytes)
+                .collect(),
+            Encoding::Latin1 | Encoding::Windows1252 => {
+                let mut bytes = Vec::with_capacity(string.len());
+                for c in string.chars() {
+                    match self.single_byte(c) {
+                        Some(v) => bytes.push(v),
+                        None => bytes.extend(format!("&#x{:x};", c as u32).bytes()),
+                    }
+                }
+                bytes
+            }
+        }
+    }
+
+    /// Returns the byte of a character in a single byte encoding
+    fn single_byte(&self, c: char) -> Option<u8> {
+        match (self, c as u32) {
+            (_, v @ 0..=0x7f) => Some(v as u8),
+            (Encoding::Latin1, v @ 0x80..=0xff) => Some(v as u8),
+            (Encoding::Windows1252, v @ 0xa0..=0xff) => Some(v as u8),
+            (Encoding::Windows1252, _) => WINDOWS_1252
+                .iter()
+                .position(|v| *v == c)
+                .map(|v| 0x80 + v as u8),
+            _ => None,
+        }
+    }
+
+    /// Decodes bytes without a byte order mark into a string
+    pub fn decode(&self, bytes: &[u8]) -> Result<String, Error> {
+        match self {
+            Encoding::Utf8 => match std::str::from_utf8(bytes) {
+                Ok(v) => Ok(v.to_owned()),
+                Err(e) => {
+                    let valid = &bytes[..e.valid_up_to()];
+                    // The valid prefix is known to be UTF-8
+                    let line = newlines_in_slice(std::str::from_utf8(valid).unwrap_or_default());
+                    let e = ParseError::InvalidUtf8(e.valid_up_to());
+                    Err(Error::ParseError(e, line))
+                }
+            },
+            Encoding::Utf16Le => decode_utf16(bytes, u16::from_le_bytes),
+            Encoding::Utf16Be => decode_utf16(bytes, u16::from_be_bytes),
+            Encoding::Latin1 => Ok(bytes.iter().map(|v| *v as char).collect()),
+            Encoding::Windows1252 => Ok(bytes
+                .iter()
+                .map(|v| match v {
+                    0x80..=0x9f => WINDOWS_1252[*v as usize - 0x80],
+                    _ => *v as char,
+                })
+                .collect()),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Result<String, Error> {
+    let units = bytes.chunks_exact(2).map(|v| from_bytes([v[0], v[1]]));
+    let mut string = String::with_capacity(bytes.len() / 2);
+    let mut offset = 0;
+    for c in std::char::decode_utf16(units) {
+        match c {
+            Ok(c) => {
+                string.push(c);
+                offset += 2 * c.len_utf16();
+            }
+            Err(_) => return Err(invalid_utf16(&string, offset)),
+        }
+    }
+
+    // A trailing byte can not form a code unit
+    match bytes.len() % 2 {
+        0 => Ok(string),
+        _ => Err(invalid_utf16(&string, bytes.len() - 1)),
+    }
+}
+
+/// Returns the error for invalid UTF-16 at a byte offset, on the line after the decoded string
+fn invalid_utf16(decoded: &str, offset: usize) -> Error {
+    Error::ParseError(ParseError::InvalidUtf16(offset), newlines_in_slice(decoded))
+}
+
+/// Detects the encoding of a document, returning it along with the length of its byte order mark
+/// Returns an error if the encoding declaration names an unsupported encoding
+pub fn detect(bytes: &[u8]) -> Result<(Encoding, usize), Error> {
+    match bytes {
+        [0xef, 0xbb, 0xbf, ..] => return Ok((Encoding::Utf8, 3)),
+        [0xff, 0xfe, ..] => return Ok((Encoding::Utf16Le, 2)),
+        [0xfe, 0xff, ..] => return Ok((Encoding::Utf16Be, 2)),
+        // The start of a declaration in UTF-16 without a byte order mark
+        [b'<', 0, b'?', 0, ..] => return Ok((Encoding::Utf16Le, 0)),
+        [0, b'<', 0, b'?', ..] => return Ok((Encoding::Utf16Be, 0)),
+        _ => {}
+    }
+
+    match declared_encoding(bytes) {
+        Some(label) => match Encoding::from_label(label) {
+            // The declaration was read as ASCII, so the document can not actually be in UTF-16
+            Some(Encoding::Utf16Le | Encoding::Utf16Be) => Ok((Encoding::Utf8, 0)),
+            Some(encoding) => Ok((encoding, 0)),
+            None => Err(Error::ParseError(
+                ParseError::UnsupportedEncoding(label.to_owned()),
+                0,
+            )),
+        },
+        None => Ok((Encoding::Utf8, 0)),
+    }
+}
+
+/// Returns the encoding named in the xml declaration of a document in an ASCII compatible encoding
+fn declared_encoding(bytes: &[u8]) -> Option<&str> {
+    let bytes = bytes.strip_prefix(b"<?xml")?;
+    let end = bytes.windows(2).position(|v| v == b"?>")?;
+    let declaration = std::str::from_utf8(&bytes[..end]).ok()?;
+
+    let rest = &declaration[declaration.find("encoding")? + "encoding".len()..];
+    let rest = rest.trim_start().strip_prefix('=')?.trim_start();
+    let quote = rest.chars().next().filter(|v| *v == '"' || *v == '\'')?;
+    let rest = &rest[1..];
+    Some(&rest[..rest.find(quote)?])
+}
+
+/// Detects the encoding of a document and decodes it into a string without the byte order mark
+/// Returns the detected encoding along with the string, to be able to write the document back in it
+pub fn decode(bytes: &[u8]) -> Result<(String, Encoding), Error> {
+    let (encoding, bom) = detect(bytes)?;
+    let string = encoding.decode(&bytes[bom..]).map_err(|e| match e {
+        // Report offsets into the whole input
+        Error::ParseError(ParseError::InvalidUtf8(offset), line) => {
+            Error::ParseError(ParseError::InvalidUtf8(offset + bom), line)
+        }
+        Error::ParseError(ParseError::InvalidUtf16(offset), line) => {
+            Error::ParseError(ParseError::InvalidUtf16(offset + bom), line)
+        }
+        e => e,
+    })?;
+    Ok((string, encoding))
+}
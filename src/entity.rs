@@ -0,0 +1,218 @@
+//! This is a module providing expansion of internal entities declared in a document type definition
+//! Expansion is bounded in both nesting depth and total size to guard against exponential entity
+//! declarations such as the billion laughs attack
+//! ```
+//! let document = simple_xml::document_from_string(
+//!     r#"<!DOCTYPE note [
+//!         <!ENTITY company "Acme">
+//!         <!ENTITY signature "&company; Inc">
+//!     ]>
+//!     <note from="&company;">Regards, &signature; &amp; friends</note>"#,
+//! )
+//! .unwrap();
+//!
+//! assert_eq!(document.root.attributes["from"], "Acme");
+//! assert_eq!(document.root.content, "Regards, Acme Inc &amp; friends");
+//! ```
+
+use crate::dtd::{Dtd, EntityDecl};
+use crate::{is_valid_name, Error, Node};
+
+/// The predefined entities which are kept escaped in content and attributes
+pub(crate) const PREDEFINED: [&str; 5] = ["lt", "gt", "amp", "apos", "quot"];
+
+/// Limits for expanding entity references
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EntityLimits {
+    /// The maximum number of nested entity references
+    pub max_depth: usize,
+    /// The maximum number of bytes all entity references in a document may expand to in total
+    pub max_expanded_size: usize,
+}
+
+impl Default for EntityLimits {
+    fn default() -> Self {
+        EntityLimits {
+            max_depth: 16,
+            max_expanded_size: 1024 * 1024,
+        }
+    }
+}
+
+/// Expands references to internal entities in a single string
+/// Character references, predefined entities, and references to undeclared or external entities
+/// are left untouched
+pub fn expand(text: &str, dtd: &Dtd, limits: &EntityLimits) -> Result<String, Error> {
+    Expander::new(dtd, limits).expand(text)
+}
+
+/// Expands references to internal entities in the content and attributes of a node and all its
+/// child nodes
+/// The size limit applies to the node as a whole
+pub fn expand_node(node: &mut Node, dtd: &Dtd, limits: &EntityLimits) -> Result<(), Error> {
+    Expander::new(dtd, limits).expand_node(node)
+}
+
+struct Expander<'a> {
+    dtd: &'a Dtd,
+    limits: &'a EntityLimits,
+    /// The entities currently being expanded, used to detect recursion
+    stack: Vec<&'a str>,
+    /// The total number of bytes produced by entity references so far
+    expanded: usize,
+}
+
+impl<'a> Expander<'a> {
+    fn new(dtd: &'a Dtd, limits: &'a EntityLimits) -> Self {
+        Expander {
+            dtd,
+            limits,
+            stack: Vec::new(),
+            expanded: 0,
+        }
+    }
+
+    fn expand_node(&mut self, node: &mut Node) -> Result<(), Error> {
+        let texts = node
+            .texts()
+            .into_iter()
+            .map(|v| self.expand(v))
+            .collect::<Result<Vec<_>, _>>()?;
+        node.set_texts(&texts);
+        for v in node.attributes.values_mut() {
+            *v = self.expand(v)?;
+        }
+
+        node.nodes
+            .values_mut()
+            .flat_map(|nodes| nodes.iter_mut())
+            .try_for_each(|child| self.expand_node(child))
+    }
+
+    fn expand(&mut self, text: &str) -> Result<String, Error> {
+        // Avoid allocating for the common case
+        if !text.contains('&') {
+            return Ok(text.to_owned());
+        }
+
+        let mut output = String::with_capacity(text.len());
+        self.expand_into(text, &mut output)?;
+        Ok(output)
+    }
+
+    fn expand_into(&mut self, text: &str, output: &mut String) -> Result<(), Error> {
+        let mut rest = text;
+        while let Some(start) = rest.find('&') {
+            self.push(output, &rest[..start])?;
+            let after = &rest[start + 1..];
+
+            let reference = after
+                .find(';')
+                .map(|end| &after[..end])
+                .filter(|name| is_valid_name(name) && !PREDEFINED.contains(name));
+
+            let (name, value) =
+                match reference.and_then(|name| self.dtd.entities.get_key_value(name)) {
+                    Some((name, EntityDecl::Internal(value))) => (name, value),
+                    // Not a reference to an internal entity, keep as is
+                    _ => {
+                        self.push(output, "&")?;
+                        rest = after;
+                        continue;
+                    }
+                };
+
+            if self.stack.contains(&name.as_str()) {
+                return Err(Error::RecursiveEntity(name.to_owned()));
+            }
+            if self.stack.len() >= self.limits.max_depth {
+                return Err(Error::EntityDepthExceeded(name.to_owned()));
+            }
+
+            self.stack.push(name);
+            self.expand_into(value, output)?;
+            self.stack.pop();
+
+            rest = &after[name.len() + 1..];
+        }
+
+        self.push(output, rest)
+    }
+
+    /// Appends text to the output while accounting for the expanded size
+    fn push(&mut self, output: &mut String, text: &str) -> Result<(), Error> {
+        if !self.stack.is_empty() {
+            self.expanded += text.len();
+            if self.expanded > self.limits.max_expanded_size {
+                return Err(Error::EntityExpansionTooLarge(
+                    self.limits.max_expanded_size,
+                ));
+            }
+        }
+
+        output.push_str(text);
+        Ok(())
+    }
+}
+
+/// Decodes raw content or attribute text into the characters it represents
+/// Predefined entities and character references are replaced and CDATA sections are unwrapped,
+/// references to other entities and malformed references are left as is
+pub fn decode(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(['&', '<']) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(section) = rest.strip_prefix("<![CDATA[") {
+            let end = section.find("]]>").unwrap_or(section.len());
+            output.push_str(&section[..end]);
+            rest = section.get(end + 3..).unwrap_or("");
+            continue;
+        }
+
+        let reference = rest[1..].find(';').map(|end| &rest[1..end + 1]);
+        let decoded = match reference {
+            Some("lt") => Some('<'),
+            Some("gt") => Some('>'),
+            Some("amp") => Some('&'),
+            Some("apos") => Some('\''),
+            Some("quot") => Some('"'),
+            Some(name) => match name.strip_prefix("#x") {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => name.strip_prefix('#').and_then(|v| v.parse().ok()),
+            }
+            .and_then(char::from_u32),
+            None => None,
+        };
+
+        match (decoded, reference) {
+            (Some(c), Some(name)) => {
+                output.push(c);
+                rest = &rest[name.len() + 2..];
+            }
+            _ => {
+                output.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Escapes text for use as content
+/// Content is kept raw in nodes, so text from outside has to be escaped before being stored in them
+pub fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Escapes text for use as a double quoted attribute value
+pub fn escape_attribute(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('"', "&quot;")
+}
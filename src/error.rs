@@ -1,3 +1,5 @@
+use std::fmt;
+
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -12,16 +14,105 @@ pub enum Error {
     TagNotFound(String, String),
     #[error("No such attribute {1:?} inside {0:?}")]
     AttributeNotFound(String, String),
+    #[error("Invalid xml name {0:?}")]
+    InvalidName(String),
+    #[error("Failed to parse attribute {1:?} inside {0:?}: {2}")]
+    AttributeParse(
+        String,
+        String,
+        #[source] Box<dyn std::error::Error + Send + Sync>,
+    ),
+    #[error("Failed to parse content of {0:?}: {1}")]
+    ContentParse(String, #[source] Box<dyn std::error::Error + Send + Sync>),
+    #[error("Entity {0:?} references itself")]
+    RecursiveEntity(String),
+    #[error("Expanding entity {0:?} exceeds the maximum nesting depth")]
+    EntityDepthExceeded(String),
+    #[error("Entity expansion exceeds the maximum size of {0} bytes")]
+    EntityExpansionTooLarge(usize),
+    #[error("Invalid schema: {0}")]
+    InvalidSchema(String),
+    #[error("Invalid path {0:?}")]
+    InvalidPath(String),
+    #[error("Patch selector {0:?} matches nothing")]
+    SelectorNotFound(String),
+    #[error("Invalid patch: {0}")]
+    InvalidPatch(String),
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+    #[error("Unsupported algorithm {0:?}")]
+    UnsupportedAlgorithm(String),
+    #[error("Digest of reference {0:?} does not match")]
+    DigestMismatch(String),
+    #[error("Invalid stylesheet: {0}")]
+    InvalidStylesheet(String),
+    #[error("Inclusion of {0:?} loops back to itself")]
+    InclusionLoop(String),
+    #[error("Invalid include: {0}")]
+    InvalidInclude(String),
 }
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, PartialEq, Error)]
 pub enum ParseError {
     #[error("Missing closing tag for {0:?}")]
     MissingClosingTag(String),
     #[error("Missing closing delimiter")]
     MissingClosingDelimiter,
+    #[error("Missing tag name")]
+    MissingTagName,
+    #[error("Closing tag {0:?} does not match any open element")]
+    UnexpectedClosingTag(String),
+    #[error("Found extra content before or after the root node")]
+    ContentOutsideRoot,
+    #[error("Missing root node")]
+    MissingRoot,
+    #[error("Invalid xml name {0:?}")]
+    InvalidName(String),
+    #[error("Duplicate attribute {0:?}")]
+    DuplicateAttribute(String),
+    #[error("Invalid reference {0:?}")]
+    InvalidReference(String),
+    #[error("Undeclared entity {0:?}")]
+    UndeclaredEntity(String),
+    #[error("Illegal character {0:?}")]
+    IllegalCharacter(char),
+    #[error("Discouraged character {0:?}")]
+    DiscouragedCharacter(char),
     #[error("Missing attribute value for {0:?}")]
     MissingAttributeValue(String),
     #[error("Missing quotes for {0:?}")]
     MissingQuotes(String),
+    #[error("Invalid declaration {0:?}")]
+    InvalidDeclaration(String),
+    #[error("Exceeded the maximum {0} of {1}")]
+    LimitExceeded(Limit, usize),
+    #[error("Unsupported encoding {0:?}")]
+    UnsupportedEncoding(String),
+    #[error("Invalid UTF-16 at byte {0}")]
+    InvalidUtf16(usize),
+    #[error("Invalid UTF-8 at byte {0}")]
+    InvalidUtf8(usize),
+}
+
+/// The limits of `ParseOptions`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Depth,
+    Attributes,
+    NameLength,
+    TextSize,
+    Nodes,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Limit::Depth => "depth",
+            Limit::Attributes => "number of attributes",
+            Limit::NameLength => "name length",
+            Limit::TextSize => "text size",
+            Limit::Nodes => "number of nodes",
+        };
+        write!(f, "{}", name)
+    }
 }
@@ -0,0 +1,622 @@
+//! This is a module providing inference of a schema from sample documents
+//! Elements with the same name are merged, regardless of where in the tree they appear
+//!
+//! The inferred schema can be written as an XML Schema, which can be loaded by `xsd::Schema`,
+//! or as a skeleton of Rust structs which are read using the typed accessors of `Node`
+//! ```
+//! let samples = [
+//!     simple_xml::from_string(r#"<note lang="en"><to>Tove</to><to>Jani</to><priority>1</priority></note>"#).unwrap(),
+//!     simple_xml::from_string(r#"<note lang="sv"><to>Bert</to></note>"#).unwrap(),
+//! ];
+//!
+//! let schema = simple_xml::infer::infer(&samples);
+//! let note = &schema.elements["note"];
+//! assert_eq!(note.children[0].name, "to");
+//! assert!(note.children[0].repeated);
+//! assert!(note.children[1].optional);
+//! assert_eq!(schema.elements["priority"].content, Some(simple_xml::infer::ValueType::Integer));
+//! ```
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::datatype::{is_reserved_attribute, Builtin};
+use crate::{Node, NodeBuilder};
+
+/// Values with at most this many distinct values which occur repeatedly are inferred as enumerations
+const MAX_ENUM_VALUES: usize = 8;
+
+/// The inferred type of an attribute or text content
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueType {
+    Boolean,
+    Integer,
+    Float,
+    Date,
+    /// One of a small set of values
+    Enum(Vec<String>),
+    String,
+}
+
+/// The inferred structure of all elements with the same name
+/// Namespace declarations and attributes in the `xml` and `xsi` namespaces are left out
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElementInfo {
+    pub attributes: BTreeMap<String, AttributeInfo>,
+    /// The child elements in the order they first appeared
+    pub children: Vec<ChildInfo>,
+    /// True if the child elements always appeared in the same relative order
+    pub ordered: bool,
+    /// The type of the text content, or None if the element never had any
+    pub content: Option<ValueType>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeInfo {
+    pub ty: ValueType,
+    /// True if the attribute was missing on some elements
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChildInfo {
+    pub name: String,
+    /// True if some elements did not contain the child
+    pub optional: bool,
+    /// True if some elements contained the child more than once
+    pub repeated: bool,
+}
+
+/// A schema inferred from sample documents
+#[derive(Debug, Clone, PartialEq)]
+pub struct InferredSchema {
+    /// The names of the root elements of the samples
+    pub roots: Vec<String>,
+    pub elements: BTreeMap<String, ElementInfo>,
+}
+
+/// The values collected for all elements with the same name
+#[derive(Default)]
+struct Stats<'a> {
+    count: usize,
+    attributes: BTreeMap<&'a str, Vec<&'a str>>,
+    /// Child name, minimum and maximum occurrences
+    children: Vec<(&'a str, usize, usize)>,
+    ordered: bool,
+    content: Vec<&'a str>,
+}
+
+/// Infers a schema from one or more sample documents
+pub fn infer<'a, I>(samples: I) -> InferredSchema
+where
+    I: IntoIterator<Item = &'a Node>,
+{
+    let mut stats: BTreeMap<&str, Stats> = BTreeMap::new();
+    let mut roots = Vec::new();
+    for root in samples {
+        if !roots.contains(&root.tag) {
+            roots.push(root.tag.clone());
+        }
+        collect(root, &mut stats);
+    }
+
+    let elements = stats
+        .into_iter()
+        .map(|(name, stats)| {
+            let attributes = stats
+                .attributes
+                .iter()
+                .map(|(k, values)| {
+                    let info = AttributeInfo {
+                        ty: infer_type(values),
+                        optional: values.len() < stats.count,
+                    };
+                    (k.to_string(), info)
+                })
+                .collect();
+
+            let children = stats
+                .children
+                .iter()
+                .map(|(name, min, max)| ChildInfo {
+                    name: name.to_string(),
+                    optional: *min == 0,
+                    repeated: *max > 1,
+                })
+                .collect();
+
+            let content = match stats.content.is_empty() {
+                true => None,
+                false => Some(infer_type(&stats.content)),
+            };
+
+            let info = ElementInfo {
+                attributes,
+                children,
+                ordered: stats.ordered,
+                content,
+            };
+            (name.to_owned(), info)
+        })
+        .collect();
+
+    InferredSchema { roots, elements }
+}
+
+fn collect<'a>(node: &'a Node, stats: &mut BTreeMap<&'a str, Stats<'a>>) {
+    let entry = stats.entry(&node.tag).or_insert_with(|| Stats {
+        ordered: true,
+        ..Default::default()
+    });
+
+    entry.count += 1;
+    for (k, v) in &node.attributes {
+        if is_reserved_attribute(k) {
+            continue;
+        }
+        entry.attributes.entry(k).or_default().push(v);
+    }
+    if !node.content.trim().is_empty() {
+        entry.content.push(node.content.trim());
+    }
+
+    // Count the occurrences of each child and check that they keep the known relative order
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    let mut last = None;
+    for child in node.children() {
+        match counts.iter_mut().find(|(name, _)| *name == child.tag) {
+            Some((_, count)) if last == Some(child.tag.as_str()) => *count += 1,
+            Some((_, count)) => {
+                // Reappeared after another child
+                *count += 1;
+                entry.ordered = false;
+            }
+            None => counts.push((&child.tag, 1)),
+        }
+        last = Some(&child.tag);
+    }
+
+    let first = entry.count == 1;
+    let mut position = 0;
+    for (name, count) in &counts {
+        match entry.children.iter().position(|(v, _, _)| v == name) {
+            Some(i) => {
+                if i < position {
+                    entry.ordered = false;
+                }
+                position = position.max(i + 1);
+                let (_, min, max) = &mut entry.children[i];
+                *min = (*min).min(*count);
+                *max = (*max).max(*count);
+            }
+            None => {
+                // Children which were missing in earlier elements are optional
+                let min = if first { *count } else { 0 };
+                entry.children.insert(position, (name, min, *count));
+                position += 1;
+            }
+        }
+    }
+
+    // Known children which are missing in this element are optional
+    for (name, min, _) in &mut entry.children {
+        if !counts.iter().any(|(v, _)| v == name) {
+            *min = 0;
+        }
+    }
+
+    for child in node.children() {
+        collect(child, stats);
+    }
+}
+
+fn infer_type(values: &[&str]) -> ValueType {
+    let all = |f: fn(&str) -> bool| values.iter().all(|v| f(v));
+
+    if all(|v| v == "true" || v == "false") {
+        ValueType::Boolean
+    } else if all(|v| v.parse::<i64>().is_ok()) {
+        ValueType::Integer
+    } else if all(|v| Builtin::Double.accepts(v) && v.parse::<f64>().is_ok()) {
+        ValueType::Float
+    } else if all(|v| Builtin::Date.accepts(v)) {
+        ValueType::Date
+    } else {
+        let mut distinct = Vec::new();
+        for v in values {
+            if !distinct.contains(v) {
+                distinct.push(*v);
+            }
+        }
+
+        // Only consider values enum like if they repeat and don't contain whitespace
+        if distinct.len() <= MAX_ENUM_VALUES
+            && distinct.len() < values.len()
+            && distinct.iter().all(|v| !v.contains(char::is_whitespace))
+        {
+            distinct.sort_unstable();
+            ValueType::Enum(distinct.into_iter().map(str::to_owned).collect())
+        } else {
+            ValueType::String
+        }
+    }
+}
+
+impl ValueType {
+    fn xsd_name(&self) -> &'static str {
+        match self {
+            ValueType::Boolean => "xs:boolean",
+            ValueType::Integer => "xs:long",
+            ValueType::Float => "xs:double",
+            ValueType::Date => "xs:date",
+            ValueType::Enum(_) | ValueType::String => "xs:string",
+        }
+    }
+
+    fn rust_name(&self) -> &'static str {
+        match self {
+            ValueType::Boolean => "bool",
+            ValueType::Integer => "i64",
+            ValueType::Float => "f64",
+            ValueType::Date | ValueType::Enum(_) | ValueType::String => "String",
+        }
+    }
+
+    /// Adds the type as a type attribute or an anonymous simpleType to a declaration
+    fn xsd_type(&self, decl: NodeBuilder) -> NodeBuilder {
+        match self {
+            ValueType::Enum(values) => decl.child(
+                NodeBuilder::new("xs:simpleType").child(
+                    NodeBuilder::new("xs:restriction")
+                        .attr("base", "xs:string")
+                        .children(
+                            values
+                                .iter()
+                                .map(|v| NodeBuilder::new("xs:enumeration").attr("value", v)),
+                        ),
+                ),
+            ),
+            v => decl.attr("type", v.xsd_name()),
+        }
+    }
+}
+
+impl InferredSchema {
+    /// Writes the schema as an XML Schema document
+    /// All elements are declared globally and referenced from their parents
+    pub fn to_xsd(&self) -> Node {
+        let elements = self
+            .elements
+            .iter()
+            .map(|(name, info)| self.xsd_element(name, info));
+
+        Node::builder("xs:schema")
+            .attr("xmlns:xs", "http://www.w3.org/2001/XMLSchema")
+            .children(elements)
+            .build()
+    }
+
+    fn xsd_element(&self, name: &str, info: &ElementInfo) -> NodeBuilder {
+        let decl = NodeBuilder::new("xs:element").attr("name", name);
+        if info.children.is_empty() && info.attributes.is_empty() {
+            return match &info.content {
+                Some(ty) => ty.xsd_type(decl),
+                None => decl.child(NodeBuilder::new("xs:complexType")),
+            };
+        }
+
+        let attributes = info.attributes.iter().map(|(name, attr)| {
+            let decl = NodeBuilder::new("xs:attribute").attr("name", name);
+            let decl = match attr.optional {
+                true => decl,
+                false => decl.attr("use", "required"),
+            };
+            attr.ty.xsd_type(decl)
+        });
+
+        let mut ty = NodeBuilder::new("xs:complexType");
+        if info.children.is_empty() {
+            // Text with attributes
+            let base = info.content.as_ref().map_or("xs:string", |v| match v {
+                ValueType::Enum(_) => "xs:string",
+                v => v.xsd_name(),
+            });
+            let extension = NodeBuilder::new("xs:extension")
+                .attr("base", base)
+                .children(attributes);
+            return decl.child(ty.child(NodeBuilder::new("xs:simpleContent").child(extension)));
+        }
+
+        if info.content.is_some() {
+            ty = ty.attr("mixed", "true");
+        }
+
+        let children = info.children.iter().map(|child| {
+            let particle = NodeBuilder::new("xs:element").attr("ref", &child.name);
+            match (info.ordered, child.optional, child.repeated) {
+                // Unordered children are wrapped in a repeated choice instead
+                (false, _, _) => particle,
+                (true, true, true) => particle.attr("minOccurs", 0).attr("maxOccurs", "unbounded"),
+                (true, true, false) => particle.attr("minOccurs", 0),
+                (true, false, true) => particle.attr("maxOccurs", "unbounded"),
+                (true, false, false) => particle,
+            }
+        });
+
+        let group = match info.ordered {
+            true => NodeBuilder::new("xs:sequence"),
+            false => NodeBuilder::new("xs:choice")
+                .attr("minOccurs", 0)
+                .attr("maxOccurs", "unbounded"),
+        };
+
+        decl.child(ty.child(group.children(children)).children(attributes))
+    }
+
+    /// Writes the schema as a skeleton of Rust structs with one struct per element
+    /// Each struct gets a `from_node` function reading it with the typed accessors of `Node`
+    /// Types from the crate are written with their full path, so structs can be named like them
+    pub fn to_rust(&self) -> String {
+        // Elements with names that only differ in separators or case get numbered struct names
+        let mut types = HashMap::new();
+        let mut used = HashSet::new();
+        for (name, info) in &self.elements {
+            if !self.is_struct(info) {
+                continue;
+            }
+            let mut ty = pascal_case(name);
+            if RESERVED_TYPES.contains(&ty.as_str()) {
+                ty.push_str("Element");
+            }
+            let unique = (1..)
+                .map(|n| match n {
+                    1 => ty.clone(),
+                    n => format!("{}{}", ty, n),
+                })
+                .find(|v| !used.contains(v))
+                .expect("Some name is unused");
+            used.insert(unique.clone());
+            types.insert(name.as_str(), unique);
+        }
+
+        let mut output = String::new();
+        for (name, info) in &self.elements {
+            if !self.is_struct(info) {
+                continue;
+            }
+            self.rust_struct(&mut output, name, info, &types)
+                .expect("Writing to a string can not fail");
+        }
+        output.trim_start().to_owned()
+    }
+
+    /// Elements with only text content are read as fields of their parent
+    fn is_struct(&self, info: &ElementInfo) -> bool {
+        !info.children.is_empty() || !info.attributes.is_empty()
+    }
+
+    /// Returns true if the element can contain the target element at any depth
+    fn reaches(&self, from: &str, target: &str, visited: &mut HashSet<String>) -> bool {
+        if !visited.insert(from.to_owned()) {
+            return false;
+        }
+
+        self.elements[from]
+            .children
+            .iter()
+            .any(|child| child.name == target || self.reaches(&child.name, target, visited))
+    }
+
+    fn rust_struct(
+        &self,
+        output: &mut String,
+        name: &str,
+        info: &ElementInfo,
+        types: &HashMap<&str, String>,
+    ) -> std::fmt::Result {
+        let mut fields = Vec::new();
+        let mut used: HashMap<String, usize> = HashMap::new();
+        let mut field_name = |name: &str| {
+            let field = snake_case(name);
+            let count = used.entry(field.clone()).or_default();
+            *count += 1;
+            match count {
+                1 => field,
+                n => format!("{}_{}", field, n),
+            }
+        };
+
+        for (key, attr) in &info.attributes {
+            let ty = attr.ty.rust_name();
+            let (ty, read) = match attr.optional {
+                false => (ty.to_owned(), format!("node.attr_as({:?})?", key)),
+                true => (
+                    format!("Option<{}>", ty),
+                    format!(
+                        "match node.get_attribute({key:?}) {{ Some(_) => Some(node.attr_as({key:?})?), None => None }}",
+                        key = key
+                    ),
+                ),
+            };
+            fields.push((field_name(key), ty, read, describe(&attr.ty)));
+        }
+
+        for child in &info.children {
+            let child_info = &self.elements[&child.name];
+            let (ty, read_one, read_fn) = match self.is_struct(child_info) {
+                true => {
+                    let ty = types[child.name.as_str()].clone();
+                    let read_fn = format!("{}::from_node", ty);
+                    (ty, format!("{}(n)", read_fn), read_fn)
+                }
+                false => {
+                    let ty = child_info.content.as_ref().unwrap_or(&ValueType::String);
+                    let read_one = "n.content_as()".to_owned();
+                    (
+                        ty.rust_name().to_owned(),
+                        read_one,
+                        "|n| n.content_as()".to_owned(),
+                    )
+                }
+            };
+
+            let tag = &child.name;
+            let (ty, read) = match (child.repeated, child.optional) {
+                (true, _) => (
+                    format!("Vec<{}>", ty),
+                    format!(
+                        "node[{:?}].iter().map({}).collect::<Result<_, _>>()?",
+                        tag, read_fn
+                    ),
+                ),
+                (false, optional) => {
+                    let boxed = self.reaches(tag, name, &mut HashSet::new()) || tag == name;
+                    let (ty, value) = match boxed {
+                        true => (format!("Box<{}>", ty), format!("Box::new({}?)", read_one)),
+                        false => (ty, format!("{}?", read_one)),
+                    };
+
+                    match optional {
+                        true => (
+                            format!("Option<{}>", ty),
+                            format!(
+                                "match node[{:?}].first() {{ Some(n) => Some({}), None => None }}",
+                                tag, value
+                            ),
+                        ),
+                        false => (
+                            ty,
+                            format!(
+                                "{{ let n = &node.try_get_nodes({:?})?[0]; {} }}",
+                                tag, value
+                            ),
+                        ),
+                    }
+                }
+            };
+            fields.push((field_name(tag), ty, read, describe_child(child_info)));
+        }
+
+        if let Some(content) = &info.content {
+            fields.push((
+                field_name("content"),
+                content.rust_name().to_owned(),
+                "node.content_as()?".to_owned(),
+                describe(content),
+            ));
+        }
+
+        let ty = &types[name];
+        writeln!(output)?;
+        writeln!(output, "/// <{}>", name)?;
+        writeln!(output, "#[derive(Debug, Clone, PartialEq)]")?;
+        writeln!(output, "pub struct {} {{", ty)?;
+        for (field, ty, _, doc) in &fields {
+            if let Some(doc) = doc {
+                writeln!(output, "    /// {}", doc)?;
+            }
+            writeln!(output, "    pub {}: {},", field, ty)?;
+        }
+        writeln!(output, "}}")?;
+        writeln!(output)?;
+        writeln!(output, "impl {} {{", ty)?;
+        writeln!(
+            output,
+            "    pub fn from_node(node: &simple_xml::Node) -> Result<Self, simple_xml::Error> {{"
+        )?;
+        writeln!(output, "        Ok({} {{", ty)?;
+        for (field, _, read, _) in &fields {
+            writeln!(output, "            {}: {},", field, read)?;
+        }
+        writeln!(output, "        }})")?;
+        writeln!(output, "    }}")?;
+        writeln!(output, "}}")
+    }
+}
+
+fn describe(ty: &ValueType) -> Option<String> {
+    match ty {
+        ValueType::Date => Some("A date formatted as YYYY-MM-DD".into()),
+        ValueType::Enum(values) => Some(format!("One of {:?}", values)),
+        _ => None,
+    }
+}
+
+fn describe_child(info: &ElementInfo) -> Option<String> {
+    info.content.as_ref().and_then(describe)
+}
+
+/// Converts a name into the words it consists of, splitting on separators and case changes
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if !c.is_alphanumeric() {
+            prev_lower = false;
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+
+        if c.is_uppercase() && prev_lower {
+            words.push(std::mem::take(&mut word));
+        }
+        prev_lower = c.is_lowercase() || c.is_ascii_digit();
+        word.extend(c.to_lowercase());
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// The types used by the generated code, which structs can not be named
+const RESERVED_TYPES: [&str; 6] = ["Box", "Option", "Result", "Self", "String", "Vec"];
+
+fn snake_case(name: &str) -> String {
+    let name = words(name).join("_");
+    if name.is_empty() {
+        return "unnamed".to_owned();
+    }
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        return format!("_{}", name);
+    }
+
+    // Keywords and reserved words which can be used as raw identifiers
+    const KEYWORDS: [&str; 48] = [
+        "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do",
+        "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in",
+        "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+        "return", "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe",
+        "unsized", "use", "virtual", "where", "while", "yield",
+    ];
+
+    match name.as_str() {
+        // These can not be raw identifiers
+        "self" | "super" | "crate" => format!("{}_", name),
+        v if KEYWORDS.contains(&v) => format!("r#{}", name),
+        _ => name,
+    }
+}
+
+fn pascal_case(name: &str) -> String {
+    let name = words(name)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(c) => c.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<String>();
+
+    match name.is_empty() {
+        true => "Unnamed".to_owned(),
+        false if name.starts_with(|c: char| c.is_ascii_digit()) => format!("_{}", name),
+        false => name,
+    }
+}
@@ -32,50 +32,256 @@ use std::fs::File;
 use std::io;
 use std::io::Write;
 use std::path::Path;
+use std::str::FromStr;
 use std::{fmt, ops};
 
 mod split_unquoted;
-use split_unquoted::SplitUnquoted;
+
+mod parser;
+pub use parser::{Diagnostic, ParseOptions, Severity, Span, Whitespace};
+
+mod name;
+pub use name::is_valid_name;
+
+pub mod builder;
+pub use builder::NodeBuilder;
+
+pub mod dtd;
+pub use dtd::{Doctype, Dtd};
+
+pub mod entity;
+pub use entity::EntityLimits;
+
+// Without XML Schema validation only inference uses the built in types
+#[cfg_attr(not(feature = "xsd"), allow(dead_code))]
+mod datatype;
+#[cfg(feature = "xsd")]
+pub mod xsd;
+
+pub mod relaxng;
+
+pub mod infer;
+
+pub mod path;
+
+pub mod diff;
+pub use diff::diff;
+
+pub mod patch;
+
+pub mod c14n;
+
+pub mod dsig;
+
+mod xpath;
+pub mod xslt;
+
+pub mod xinclude;
+
+pub mod visit;
+pub use visit::{Fold, Visitor, Walk};
+
+pub mod encoding;
+pub use encoding::Encoding;
 
 pub mod error;
 pub use error::Error;
 pub use error::ParseError;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Node {
     pub tag: String,
     pub attributes: HashMap<String, String>,
     nodes: HashMap<String, Vec<Node>>,
+    /// The tags of the child nodes in document order
+    /// The n:th occurrence of a tag refers to the n:th node in `nodes` with that tag
+    order: Vec<String>,
+    /// All text of the node, which is written after the child nodes unless its position between
+    /// them is known
     pub content: String,
+    /// The text before each child node in document order, which together form the start of
+    /// `content`. Missing trailing entries are empty
+    /// Ignored once `content` no longer starts with them, the text then follows the child nodes
+    before: Vec<String>,
 }
 
-struct Payload<'a> {
-    prolog: &'a str,
-    node: Option<Node>,
-    remaining: &'a str,
-}
-
-fn validate_root(root: Result<Payload, Error>) -> Result<Node, Error> {
-    match root {
-        Ok(v) if !v.prolog.is_empty() => Err(Error::ContentOutsideRoot),
-        Ok(v) => Ok(v.node.unwrap_or(Node {
-            tag: String::new(),
-            content: String::new(),
-            nodes: HashMap::new(),
-            attributes: HashMap::new(),
-        })),
-        Err(e) => Err(e),
-    }
+/// A parsed xml document
+/// Contains the root node along with the document type declaration, if any
+#[derive(Debug)]
+pub struct Document {
+    pub doctype: Option<Doctype>,
+    pub root: Node,
+    /// The encoding the document was read in, which is UTF-8 for documents read from a string
+    pub encoding: Encoding,
 }
 
 /// Loads an xml structure from a file and returns appropriate errors
+/// The file is transcoded to UTF-8 from the encoding detected by `encoding::decode`
 pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Node, Error> {
-    validate_root(load_from_slice(&std::fs::read_to_string(path)?))
+    from_file_with_options(path, &ParseOptions::default())
 }
 
 /// Loads an xml structure from a string and returns appropriate errors
 pub fn from_string(string: &str) -> Result<Node, Error> {
-    validate_root(load_from_slice(string))
+    from_string_with_options(string, &ParseOptions::default())
+}
+
+/// Loads an xml structure from a file
+/// Returns an Err if the document exceeds the limits in the given options
+pub fn from_file_with_options<P: AsRef<Path>>(
+    path: P,
+    options: &ParseOptions,
+) -> Result<Node, Error> {
+    from_bytes_with_options(&std::fs::read(path)?, options)
+}
+
+/// Loads an xml structure from bytes, such as a `Vec<u8>` or a buffer received over the network
+/// The bytes are transcoded to UTF-8 from the encoding detected by `encoding::decode`
+/// Invalid UTF-8 is reported as `ParseError::InvalidUtf8` with the byte offset of the first invalid
+/// sequence
+/// ```
+/// let root = simple_xml::from_bytes(b"\xef\xbb\xbf<a>caf\xc3\xa9</a>").unwrap();
+/// assert_eq!(root.content, "café");
+///
+/// match simple_xml::from_bytes(b"<a>\n\xff</a>") {
+///     Err(simple_xml::Error::ParseError(simple_xml::ParseError::InvalidUtf8(offset), line)) => {
+///         assert_eq!((offset, line), (4, 1))
+///     }
+///     v => panic!("Expected invalid UTF-8, got {:?}", v),
+/// }
+/// ```
+pub fn from_bytes(bytes: &[u8]) -> Result<Node, Error> {
+    from_bytes_with_options(bytes, &ParseOptions::default())
+}
+
+/// Loads an xml structure from bytes
+/// Returns an Err if the document exceeds the limits in the given options
+pub fn from_bytes_with_options(bytes: &[u8], options: &ParseOptions) -> Result<Node, Error> {
+    let (string, _) = encoding::decode(bytes)?;
+    from_string_with_options(&string, options)
+}
+
+/// Loads an xml structure from a string
+/// Returns an Err if the document exceeds the limits in the given options
+pub fn from_string_with_options(string: &str, options: &ParseOptions) -> Result<Node, Error> {
+    parser::parse(string, options)
+}
+
+/// Loads an xml structure from a string along with the spans of its elements in the string
+/// The spans are in document order, which is the root followed by `Node::descendants`
+/// ```
+/// let string = r#"<a><b x="1"/>text</a>"#;
+/// let (_, spans) = simple_xml::from_string_with_spans(string, &Default::default()).unwrap();
+/// assert_eq!(spans.len(), 2);
+/// assert_eq!(&string[spans[0].content.clone().unwrap()], r#"<b x="1"/>text"#);
+/// assert_eq!(&string[spans[1].attributes["x"].clone()], "1");
+/// assert_eq!(spans[1].content, None);
+/// ```
+pub fn from_string_with_spans(
+    string: &str,
+    options: &ParseOptions,
+) -> Result<(Node, Vec<Span>), Error> {
+    parser::parse_with_spans(string, options)
+}
+
+/// Loads an xml structure from a string, recovering from errors instead of returning them
+/// Elements missing their closing tags are closed automatically and unparseable fragments are kept as text
+/// The problems found are returned along with the tree
+/// If the string does not consist of exactly one root element, the top level elements and text are
+/// returned as the children and content of a node with an empty tag
+pub fn from_string_lenient(string: &str) -> (Node, Vec<Diagnostic>) {
+    from_string_lenient_with_options(string, &ParseOptions::default())
+}
+
+/// Loads an xml structure from a string, recovering from errors instead of returning them
+/// Parsing stops at the first exceeded limit, returning the elements read so far
+pub fn from_string_lenient_with_options(
+    string: &str,
+    options: &ParseOptions,
+) -> (Node, Vec<Diagnostic>) {
+    parser::parse_lenient(string, options)
+}
+
+/// Checks that a string is well-formed xml, reporting every problem found instead of stopping at the first
+/// Besides the errors returned by `from_string`, this reports invalid names, duplicate attributes,
+/// malformed or undeclared references, and illegal characters
+/// ```
+/// let diagnostics = simple_xml::check_well_formed("<a b=\"1\" b=\"2\">&nbsp;</c>");
+/// assert_eq!(diagnostics.len(), 4);
+/// assert_eq!(diagnostics[0].to_string(), "1:10: error: Duplicate attribute \"b\"");
+/// ```
+pub fn check_well_formed(string: &str) -> Vec<Diagnostic> {
+    parser::parse_lenient(string, &ParseOptions::default()).1
+}
+
+/// Loads an xml document including the document type declaration from a file
+/// The file is transcoded to UTF-8, and the detected encoding is kept to be able to save it back in it
+pub fn document_from_file<P: AsRef<Path>>(path: P) -> Result<Document, Error> {
+    let (string, encoding) = encoding::decode(&std::fs::read(path)?)?;
+    let document = document_from_string(&string)?;
+    Ok(Document {
+        encoding,
+        ..document
+    })
+}
+
+/// Loads an xml document including the document type declaration from a string
+/// Internal entities declared in the document type declaration are expanded in content and attributes
+/// using the default limits
+pub fn document_from_string(string: &str) -> Result<Document, Error> {
+    document_from_string_with_limits(string, &EntityLimits::default())
+}
+
+/// Loads an xml document including the document type declaration from a string
+/// Internal entities declared in the document type declaration are expanded in content and attributes
+/// Returns an Err if the expansion exceeds the given limits
+pub fn document_from_string_with_limits(
+    string: &str,
+    limits: &EntityLimits,
+) -> Result<Document, Error> {
+    let mut doctype = None;
+
+    // Look for a doctype in the prolog, skipping declarations and comments
+    let mut rest = string.trim_start();
+    loop {
+        let skip = if rest.starts_with("<?") {
+            rest.find("?>").map(|v| v + 2)
+        } else if rest.starts_with("<!--") {
+            rest.find("-->").map(|v| v + 3)
+        } else if rest.starts_with("<!DOCTYPE") {
+            let len = dtd::doctype_len(rest).ok_or(Error::ParseError(
+                ParseError::MissingClosingDelimiter,
+                newlines_in_slice(&string[..string.len() - rest.len()]),
+            ))?;
+
+            doctype = Some(Doctype::parse(&rest[..len]).map_err(|e| match e {
+                Error::ParseError(e, ln) => Error::ParseError(
+                    e,
+                    ln + newlines_in_slice(&string[..string.len() - rest.len()]),
+                ),
+                e => e,
+            })?);
+            Some(len)
+        } else {
+            None
+        };
+
+        match skip {
+            Some(v) => rest = rest[v..].trim_start(),
+            None => break,
+        }
+    }
+
+    let mut root = from_string(string)?;
+    if let Some(dtd) = doctype.as_ref().and_then(|d| d.internal_subset.as_ref()) {
+        entity::expand_node(&mut root, dtd, limits)?;
+    }
+
+    Ok(Document {
+        doctype,
+        root,
+        encoding: Encoding::Utf8,
+    })
 }
 
 /// Creates a new empty node
@@ -88,21 +294,31 @@ pub fn new(tag: &str, content: String) -> Node {
         content,
         tag: tag.to_owned(),
         nodes: HashMap::new(),
+        order: Vec::new(),
+        before: Vec::new(),
     }
 }
 
 /// Creates a new node with given tag, attributes content, and child nodes
+/// The order between child nodes of different tags is unspecified, use `Node::builder` to keep the order
 pub fn new_filled(
     tag: &str,
     attributes: HashMap<String, String>,
     content: String,
     nodes: HashMap<String, Vec<Node>>,
 ) -> Node {
+    let order = nodes
+        .iter()
+        .flat_map(|(tag, nodes)| nodes.iter().map(move |_| tag.clone()))
+        .collect();
+
     Node {
         tag: tag.to_owned(),
         attributes,
         nodes,
+        order,
         content,
+        before: Vec::new(),
     }
 }
 
@@ -111,151 +327,49 @@ fn newlines_in_slice(string: &str) -> usize {
     string.chars().filter(|c| *c == '\n').count()
 }
 
-/// Loads a xml structure from a slice
-/// Ok variant contains a payload with the child node, name prolog, and remaining stringtuple with (prolog, tag_name, tag_data, remaining_from_in)
-fn load_from_slice(string: &str) -> Result<Payload, Error> {
-    let opening_del = match string.find('<') {
-        Some(v) => v,
-        None => {
-            return Ok(Payload {
-                prolog: "",
-                node: None,
-                remaining: string,
-            });
-        }
-    };
-
-    let closing_del = match string.find('>') {
-        Some(v) => v,
-        None => {
-            return Err(Error::ParseError(
-                ParseError::MissingClosingDelimiter,
-                newlines_in_slice(&string[..opening_del]),
-            ))
-        }
-    };
-
-    // Do not consider / of empty as a part
-    let attr_end = if &string[closing_del - 1..closing_del] == "/" {
-        closing_del - 1
-    } else {
-        closing_del
-    };
-
-    let mut tag_parts =
-        SplitUnquoted::split(&string[opening_del + 1..attr_end], |c| c.is_whitespace());
-
-    let tag_name = tag_parts.next().unwrap().trim();
-
-    // Collect the prolog as everything before opening tag excluding whitespace
-    let prolog = string[..opening_del].trim();
-
-    // Is a comment
-    // Attempt to read past comment
-    if &tag_name[0..1] == "?" {
-        return load_from_slice(&string[closing_del + 1..]);
-    }
-
-    let mut attributes = HashMap::new();
-    for part in tag_parts {
-        let equal_sign = match part.find('=') {
-            Some(v) => v,
-            None => {
-                return Err(Error::ParseError(
-                    ParseError::MissingAttributeValue(part.to_owned()),
-                    newlines_in_slice(&string[..closing_del]),
-                ))
-            }
-        };
+impl Node {
+    /// Starts building a new node with the given tag
+    /// See `NodeBuilder` for the available methods
+    pub fn builder<T: ToString>(tag: T) -> NodeBuilder {
+        NodeBuilder::new(tag)
+    }
 
-        // Get key and value from attribute
-        let (k, v) = part.split_at(equal_sign);
+    /// Returns an iterator over all child nodes in document order
+    pub fn children(&self) -> impl Iterator<Item = &Node> {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        self.order.iter().map(move |tag| {
+            let index = counts.entry(tag).or_default();
+            let node = &self.nodes[tag.as_str()][*index];
+            *index += 1;
+            node
+        })
+    }
 
-        // Remove quotes from value
-        let v = if &v[1..2] == "\"" && (&v[v.len() - 1..] == "\"" || v.ends_with("\"/")) {
-            &v[2..v.len() - 1]
-        } else {
-            return Err(Error::ParseError(
-                ParseError::MissingQuotes(part.to_owned()),
-                newlines_in_slice(&string[..closing_del]),
-            ));
-        };
-        attributes.insert(k.to_owned(), v.to_owned());
-    }
-
-    // Empty but valid node
-    if string[opening_del + 1..closing_del].ends_with('/') {
-        return Ok(Payload {
-            prolog,
-            node: Some(Node {
-                tag: tag_name.to_owned(),
-                nodes: HashMap::new(),
-                attributes,
-                content: String::new(),
-            }),
-            remaining: &string[closing_del + 1..],
-        });
-    }
-
-    // Find the closing tag index
-    let closing_tag = match string.find(&format!("</{}>", tag_name)) {
-        Some(v) => v,
-        None => {
-            return Err(Error::ParseError(
-                ParseError::MissingClosingTag(tag_name.to_owned()),
-                newlines_in_slice(&string[..closing_del]),
-            ))
-        }
-    };
-
-    let mut content = String::with_capacity(512);
-    let mut nodes = HashMap::new();
-
-    // Load the inside contents and nodes
-    let mut buf = &string[closing_del + 1..closing_tag];
-    let mut offset = closing_del;
-    while !buf.is_empty() {
-        let payload = load_from_slice(buf).map_err(|e| match e {
-            Error::ParseError(e, ln) => {
-                Error::ParseError(e, ln + newlines_in_slice(&string[..offset]))
-            }
-            e => e,
-        })?;
+    /// Returns the number of child nodes
+    pub fn child_count(&self) -> usize {
+        self.order.len()
+    }
 
-        if let Some(node) = payload.node {
-            let v: &mut Vec<_> = nodes.entry(node.tag.clone()).or_default();
-            v.push(node);
+    /// Checks the tag and attribute names of this node and all child nodes against the xml Name production
+    /// Returns an Err of InvalidName containing the first offending name
+    pub fn validate_names(&self) -> Result<(), Error> {
+        if !is_valid_name(&self.tag) {
+            return Err(Error::InvalidName(self.tag.to_owned()));
         }
 
-        // Nothing was read by node, no more nodes
-        if payload.remaining.as_ptr() == buf.as_ptr() {
-            break;
+        if let Some(key) = self.attributes.keys().find(|key| !is_valid_name(key)) {
+            return Err(Error::InvalidName(key.to_owned()));
         }
 
-        // Put what was before the next tag into the content of the parent tag
-        content.push_str(payload.prolog);
-        offset += buf.len() - payload.remaining.len();
-        buf = payload.remaining;
+        self.children().try_for_each(Node::validate_names)
     }
 
-    // Add the remaining inside content to content after no more nodes where found
-    content.push_str(buf);
-
-    let remaining = &string[closing_tag + tag_name.len() + 3..];
-
-    Ok(Payload {
-        prolog,
-        node: Some(Node {
-            tag: tag_name.to_owned(),
-            attributes,
-            nodes,
-            content: content.trim().into(),
-        }),
-        remaining,
-    })
-}
+    /// Returns the nodes matching a path, see the `path` module for the supported syntax
+    /// Returns an Err of InvalidPath if the path can not be parsed
+    pub fn select(&self, path: &str) -> Result<Vec<&Node>, Error> {
+        Ok(path::Path::parse(path)?.select(self))
+    }
 
-impl Node {
     /// Returns a list of all nodes with the specified tag
     /// If no nodes with the specified tag exists, None is returned
     pub fn get_nodes(&self, tag: &str) -> Option<&Vec<Node>> {
@@ -295,12 +409,317 @@ impl Node {
         }
     }
 
+    /// Gets an attribute by name and parses it into T
+    /// Returns an Err of AttributeNotFound if the attribute doesn't exist
+    /// or an Err of AttributeParse containing the tag, key and parse error if it could not be parsed
+    pub fn attr_as<T>(&self, key: &str) -> Result<T, Error>
+    where
+        T: FromStr,
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        self.try_get_attribute(key)?
+            .parse()
+            .map_err(|e| Error::AttributeParse(self.tag.to_owned(), key.to_owned(), Box::new(e)))
+    }
+
+    /// Parses the content of the node, excluding surrounding whitespace, into T
+    /// Returns an Err of ContentParse containing the tag and parse error if it could not be parsed
+    pub fn content_as<T>(&self) -> Result<T, Error>
+    where
+        T: FromStr,
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        self.content
+            .trim()
+            .parse()
+            .map_err(|e| Error::ContentParse(self.tag.to_owned(), Box::new(e)))
+    }
+
+    /// Parses the content of the node as a whitespace separated list of T
+    /// Useful for arrays such as COLLADA's <float_array>
+    /// Returns an Err of ContentParse containing the tag and parse error of the first item that could not be parsed
+    pub fn content_as_vec<T>(&self) -> Result<Vec<T>, Error>
+    where
+        T: FromStr,
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        self.content
+            .split_whitespace()
+            .map(|item| {
+                item.parse()
+                    .map_err(|e| Error::ContentParse(self.tag.to_owned(), Box::new(e)))
+            })
+            .collect()
+    }
+
     /// Inserts a new node node with the name of the node field
     pub fn add_node(&mut self, node: Node) {
+        self.order.push(node.tag.clone());
         let v = self.nodes.entry(node.tag.clone()).or_default();
         v.push(node);
     }
 
+    /// Returns the position in document order of the n:th child node with the given tag
+    pub(crate) fn position(&self, tag: &str, index: usize) -> Option<usize> {
+        self.order
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| *v == tag)
+            .nth(index)
+            .map(|(i, _)| i)
+    }
+
+    /// Returns the text before each child node in document order, followed by the text after the
+    /// last one
+    pub(crate) fn texts(&self) -> Vec<&str> {
+        let mut texts = Vec::with_capacity(self.order.len() + 1);
+        let mut end = 0;
+        for text in &self.before {
+            match self.content[end..].starts_with(text.as_str()) {
+                true => {
+                    texts.push(&self.content[end..end + text.len()]);
+                    end += text.len();
+                }
+                false => break,
+            }
+        }
+
+        // Text at unknown positions follows the child nodes
+        if texts.len() < self.before.len() || self.before.len() > self.order.len() {
+            texts.clear();
+            end = 0;
+        }
+        texts.resize(self.order.len(), "");
+        texts.push(&self.content[end..]);
+        texts
+    }
+
+    /// Sets the content from the text before each child node in document order, followed by the
+    /// text after the last one
+    pub(crate) fn set_texts<S: AsRef<str>>(&mut self, texts: &[S]) {
+        let (_, before) = texts
+            .split_last()
+            .expect("Missing text after the child nodes");
+        self.content = texts.iter().map(AsRef::as_ref).collect();
+        let len = before.len()
+            - before
+                .iter()
+                .rev()
+                .take_while(|v| v.as_ref().is_empty())
+                .count();
+        self.before = before[..len]
+            .iter()
+            .map(|v| v.as_ref().to_owned())
+            .collect();
+    }
+
+    /// Returns true if there is text before any of the child nodes
+    fn has_mixed_content(&self) -> bool {
+        let texts = self.texts();
+        texts[..texts.len() - 1].iter().any(|v| !v.is_empty())
+    }
+
+    /// Inserts a child node at a position in document order, with no text before it
+    /// Panics if the position is greater than the number of child nodes
+    pub(crate) fn insert_at(&mut self, position: usize, node: Node) {
+        if position < self.before.len() {
+            self.before.insert(position, String::new());
+        }
+        self.attach(position, node);
+    }
+
+    /// Removes and returns the child node at a position in document order
+    /// The text before it joins the text before the next child node
+    /// Panics if the position is out of bounds
+    pub(crate) fn remove_at(&mut self, position: usize) -> Node {
+        if position < self.before.len() {
+            let text = self.before.remove(position);
+            if let Some(next) = self.before.get_mut(position) {
+                next.insert_str(0, &text);
+            }
+        }
+        self.detach(position)
+    }
+
+    /// Adds a child node after all text so far, where `add_node` keeps the text after the child nodes
+    pub(crate) fn push_node(&mut self, node: Node) {
+        if self.content.is_empty() {
+            return self.add_node(node);
+        }
+        let mut texts: Vec<String> = self.texts().into_iter().map(str::to_owned).collect();
+        texts.push(String::new());
+        self.add_node(node);
+        self.set_texts(&texts);
+    }
+
+    /// Replaces the child node at a position in document order with the child nodes and text of
+    /// another node, and returns the replaced child node
+    /// Panics if the position is out of bounds
+    pub(crate) fn splice(&mut self, position: usize, mut replacement: Node) -> Node {
+        let mut texts: Vec<String> = self.texts().into_iter().map(str::to_owned).collect();
+        let mut inner: Vec<String> = replacement.texts().into_iter().map(str::to_owned).collect();
+
+        // The text after the replaced child follows the last text of the replacement
+        let after = texts.remove(position + 1);
+        inner
+            .last_mut()
+            .expect("Texts are never empty")
+            .push_str(&after);
+        texts[position].push_str(&inner[0]);
+        texts.splice(position + 1..position + 1, inner.drain(1..));
+
+        let node = self.detach(position);
+        for (i, child) in replacement.take_children().into_iter().enumerate() {
+            self.attach(position + i, child);
+        }
+        self.set_texts(&texts);
+        node
+    }
+
+    /// Adds a child node at a position in document order, leaving the text in place
+    fn attach(&mut self, position: usize, node: Node) {
+        let index = self.order[..position]
+            .iter()
+            .filter(|v| **v == node.tag)
+            .count();
+        self.order.insert(position, node.tag.clone());
+        self.nodes
+            .entry(node.tag.clone())
+            .or_default()
+            .insert(index, node);
+    }
+
+    /// Removes and returns the child node at a position in document order, leaving the text in place
+    fn detach(&mut self, position: usize) -> Node {
+        let tag = self.order.remove(position);
+        let index = self.order[..position].iter().filter(|v| **v == tag).count();
+        let nodes = self.nodes.get_mut(&tag).expect("Tag index out of sync");
+        let node = nodes.remove(index);
+        // Serialization relies on nodes only containing tags that are present
+        if nodes.is_empty() {
+            self.nodes.remove(&tag);
+        }
+        node
+    }
+
+    /// Removes and returns all child nodes in document order
+    /// The content is kept as a whole
+    pub fn take_children(&mut self) -> Vec<Node> {
+        self.before.clear();
+        let mut nodes: HashMap<String, std::vec::IntoIter<Node>> = std::mem::take(&mut self.nodes)
+            .into_iter()
+            .map(|(tag, nodes)| (tag, nodes.into_iter()))
+            .collect();
+        std::mem::take(&mut self.order)
+            .into_iter()
+            .map(|tag| {
+                nodes
+                    .get_mut(&tag)
+                    .and_then(Iterator::next)
+                    .expect("Tag index out of sync")
+            })
+            .collect()
+    }
+
+    /// Inserts a child node at an index in document order, shifting the following child nodes
+    /// Panics if the index is greater than the number of child nodes
+    pub fn insert_child(&mut self, index: usize, node: Node) {
+        assert!(
+            index <= self.order.len(),
+            "Insertion index {} is out of bounds for {} child nodes",
+            index,
+            self.order.len()
+        );
+        self.insert_at(index, node);
+    }
+
+    /// Removes and returns the n:th child node with the given tag, as indexed by `node[tag][index]`
+    /// Panics if there is no such child node
+    pub fn remove_child(&mut self, tag: &str, index: usize) -> Node {
+        let position = self.expect_position(tag, index);
+        self.remove_at(position)
+    }
+
+    /// Replaces the n:th child node with the given tag, keeping its position in document order
+    /// The replacement can have a different tag. Returns the replaced node
+    /// Panics if there is no such child node
+    pub fn replace_child(&mut self, tag: &str, index: usize, node: Node) -> Node {
+        let position = self.expect_position(tag, index);
+        let replaced = self.detach(position);
+        self.attach(position, node);
+        replaced
+    }
+
+    /// Keeps only the child nodes for which the predicate returns true, in document order
+    /// The text before a removed child node joins the text before the next one
+    pub fn retain_children<F: FnMut(&Node) -> bool>(&mut self, mut predicate: F) {
+        let keep: Vec<bool> = self.children().map(&mut predicate).collect();
+        for (position, _) in keep.iter().enumerate().rev().filter(|(_, keep)| !**keep) {
+            self.remove_at(position);
+        }
+    }
+
+    /// Sorts the child nodes with a comparison function
+    /// The sort is stable, child nodes comparing equal keep their document order
+    /// The text between child nodes stays in place
+    pub fn sort_children_by<F: FnMut(&Node, &Node) -> std::cmp::Ordering>(&mut self, compare: F) {
+        let before = std::mem::take(&mut self.before);
+        let mut children = self.take_children();
+        children.sort_by(compare);
+        for node in children {
+            self.add_node(node);
+        }
+        self.before = before;
+    }
+
+    /// Moves the n:th child node with the given tag into the wrapper, which takes its place
+    /// The child node is added after the existing child nodes of the wrapper
+    /// Panics if there is no such child node
+    pub fn wrap(&mut self, tag: &str, index: usize, mut wrapper: Node) {
+        let position = self.expect_position(tag, index);
+        wrapper.add_node(self.detach(position));
+        self.attach(position, wrapper);
+    }
+
+    /// Replaces the n:th child node with the given tag by its own child nodes and text
+    /// Its attributes are discarded. Returns the number of child nodes moved up
+    /// Panics if there is no such child node
+    pub fn unwrap(&mut self, tag: &str, index: usize) -> usize {
+        let position = self.expect_position(tag, index);
+
+        // The text around the node joins the first and last text inside it
+        let texts = self.texts();
+        let inner = self
+            .children()
+            .nth(position)
+            .map(Node::texts)
+            .unwrap_or_default();
+        let mut merged: Vec<String> = texts[..position].iter().map(|v| v.to_string()).collect();
+        merged.push(format!("{}{}", texts[position], inner[0]));
+        merged.extend(inner[1..].iter().map(|v| v.to_string()));
+        let last = merged.len() - 1;
+        merged[last].push_str(texts[position + 1]);
+        merged.extend(texts[position + 2..].iter().map(|v| v.to_string()));
+
+        let mut node = self.detach(position);
+        let children = node.take_children();
+        let count = children.len();
+        for (i, child) in children.into_iter().enumerate() {
+            self.attach(position + i, child);
+        }
+        self.set_texts(&merged);
+        count
+    }
+
+    fn expect_position(&self, tag: &str, index: usize) -> usize {
+        self.position(tag, index).unwrap_or_else(|| {
+            panic!(
+                "No child node {:?} with index {} inside {:?}",
+                tag, index, self.tag
+            )
+        })
+    }
+
     /// Inserts a new node into the xml structure
     /// Does the same thing as node.add_node(simple_xml::new(tag, content));
     pub fn add_new_node(&mut self, tag: &str, content: String) {
@@ -325,13 +744,107 @@ impl Node {
         Ok(())
     }
 
+    /// Converts an xml structure to bytes in an encoding, starting with an xml declaration naming it
+    /// Characters the encoding can not represent are written as character references
+    pub fn to_bytes(&self, encoding: Encoding) -> Vec<u8> {
+        let declaration = format!(r#"<?xml version="1.0" encoding="{}"?>"#, encoding);
+        encoding.encode(&(declaration + &self.to_string()))
+    }
+
+    /// Converts an xml structure to bytes in an encoding like `to_bytes`
+    /// Uses the pretty to_string_pretty formatting, with the declaration on its own line
+    pub fn to_bytes_pretty(&self, encoding: Encoding) -> Vec<u8> {
+        let declaration = format!("<?xml version=\"1.0\" encoding=\"{}\"?>\n", encoding);
+        encoding.encode(&(declaration + &self.to_string_pretty()))
+    }
+
+    /// This writes an xml structure to a file specified by path in an encoding
+    /// Uses the non-pretty to_string formatting, see `to_bytes`
+    pub fn save_to_file_with_encoding<P: AsRef<Path>>(
+        &self,
+        path: P,
+        encoding: Encoding,
+    ) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        file.write_all(&self.to_bytes(encoding))?;
+
+        Ok(())
+    }
+
+    /// Writes the canonical form of the node and all child nodes, see the `c14n` module
+    /// The options select between inclusive and exclusive canonicalization
+    pub fn write_canonical<W: Write>(
+        &self,
+        writer: &mut W,
+        options: &c14n::CanonicalOptions,
+    ) -> io::Result<()> {
+        c14n::write(self, writer, options)
+    }
+
+    /// Calls the visitor for this node and all child nodes in document order, see the `visit` module
+    pub fn walk<V: Visitor + ?Sized>(&self, visitor: &mut V) {
+        visit::walk(self, visitor)
+    }
+
+    /// Returns an iterator over all descendants in document order, which is a pre-order depth first
+    /// traversal not including this node
+    pub fn descendants(&self) -> visit::Descendants<'_> {
+        visit::Descendants::new(self)
+    }
+
+    /// Returns an iterator over all descendants in breadth first order, the child nodes first, then
+    /// the grandchildren and so on, each level in document order
+    pub fn bfs(&self) -> visit::Bfs<'_> {
+        visit::Bfs::new(self)
+    }
+
+    /// Returns an iterator over all descendants in document order, along with their depth and their
+    /// location below this node
+    /// The child nodes have a depth of 1
+    pub fn descendants_with_depth(&self) -> visit::DescendantsWithDepth<'_> {
+        visit::DescendantsWithDepth::new(self)
+    }
+
+    /// Returns the first descendant in document order matching a predicate
+    pub fn find<P: FnMut(&Node) -> bool>(&self, mut predicate: P) -> Option<&Node> {
+        self.descendants().find(|node| predicate(node))
+    }
+
+    /// Returns all descendants matching a predicate in document order
+    pub fn find_all<P: FnMut(&Node) -> bool>(&self, mut predicate: P) -> Vec<&Node> {
+        self.descendants().filter(|node| predicate(node)).collect()
+    }
+
+    /// Rebuilds the tree bottom up through the folder, see the `visit` module
+    /// Returns None if the folder drops this node
+    pub fn fold<F: Fold + ?Sized>(self, folder: &mut F) -> Option<Node> {
+        visit::fold(self, folder)
+    }
+
     // Converts an xml structure to a string with whitespace formatting
+    // Elements with xml:space="preserve" are written without added whitespace
     pub fn to_string_pretty(&self) -> String {
+        self.to_string_pretty_with(Whitespace::Trim)
+    }
+
+    /// Converts an xml structure to a string formatted for the whitespace mode it was parsed with
+    /// Whitespace is only added where parsing in the same mode removes it again, so nothing is added
+    /// for `Whitespace::Preserve` or inside elements with `xml:space="preserve"`
+    pub fn to_string_pretty_with(&self, whitespace: Whitespace) -> String {
         fn internal(node: &Node, depth: usize) -> String {
             if node.tag.is_empty() {
                 return "".to_owned();
             }
 
+            // Added whitespace would change preserved and mixed content
+            if node.has_mixed_content()
+                || node
+                    .get_attribute("xml:space")
+                    .is_some_and(|v| v == "preserve")
+            {
+                return format!("{}{}\n", " ".repeat(depth * 4), node);
+            }
+
             match node.nodes.len() + node.content.len() {
                 0 => format!(
                     "{indent}<{}{}/>\n",
@@ -344,9 +857,7 @@ impl Node {
                     tag = node.tag,
                     attr = format_attrs(&node.attributes),
                     nodes = node
-                        .nodes
-                        .iter()
-                        .flat_map(|(_, nodes)| nodes.iter())
+                        .children()
                         .map(|node| internal(node, depth + 1))
                         .collect::<String>(),
                     beg = match node.nodes.len() {
@@ -362,7 +873,11 @@ impl Node {
                 ),
             }
         }
-        internal(self, 0)
+
+        match whitespace {
+            Whitespace::Preserve => self.to_string(),
+            Whitespace::Trim | Whitespace::Normalize => internal(self, 0),
+        }
     }
 }
 
@@ -385,23 +900,29 @@ impl std::fmt::Display for Node {
 
         match self.nodes.len() + self.content.len() {
             0 => write!(f, "<{}{}/>", self.tag, format_attrs(&self.attributes)),
-            _ => write!(
-                f,
-                "<{tag}{attr}>{nodes}{content}</{tag}>",
-                tag = self.tag,
-                attr = format_attrs(&self.attributes),
-                nodes = self
-                    .nodes
-                    .iter()
-                    .flat_map(|(_, nodes)| nodes.iter())
-                    .map(|node| node.to_string())
-                    .collect::<String>(),
-                content = self.content,
-            ),
+            _ => {
+                write!(f, "<{}{}>", self.tag, format_attrs(&self.attributes))?;
+                let texts = self.texts();
+                for (text, node) in texts.iter().zip(self.children()) {
+                    write!(f, "{}{}", text, node)?;
+                }
+                write!(f, "{}</{}>", texts[texts.len() - 1], self.tag)
+            }
         }
     }
 }
 
+/// Nodes are equal when their text is equal and at the same positions between the child nodes
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.tag == other.tag
+            && self.attributes == other.attributes
+            && self.order == other.order
+            && self.nodes == other.nodes
+            && self.texts() == other.texts()
+    }
+}
+
 /// Returns a slice of all node nodes with the specified tag
 /// If no nodes with the specified tag exists, an empty slice is returned
 impl ops::Index<&str> for Node {
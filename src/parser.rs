@@ -0,0 +1,732 @@
+//! This is a module providing the parser which loads xml from a string into nodes
+//! The parser keeps the open elements on a stack instead of recursing, so deeply nested input can
+//! not overflow the stack
+//!
+//! In lenient mode the parser recovers from errors instead of stopping at the first one
+//! ```
+//! let (root, diagnostics) = simple_xml::from_string_lenient("<p>Fish & <b>chips</p><p>Peas");
+//! assert_eq!(root["p"].len(), 2);
+//! assert_eq!(root["p"][0]["b"][0].content, "chips");
+//! assert_eq!(root["p"][1].content, "Peas");
+//! assert_eq!(diagnostics.len(), 4);
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+
+use crate::entity::PREDEFINED;
+use crate::error::Limit;
+use crate::name::{is_discouraged_char, is_xml_char};
+use crate::split_unquoted::SplitUnquoted;
+use crate::{dtd, is_valid_name, new, newlines_in_slice, Doctype, Error, Node, ParseError};
+
+/// Options for parsing xml
+/// The limits protect against untrusted input exhausting memory
+/// Exceeding any of the limits returns `ParseError::LimitExceeded`
+/// ```
+/// let options = simple_xml::ParseOptions {
+///     max_depth: 2,
+///     ..Default::default()
+/// };
+///
+/// assert!(simple_xml::from_string_with_options("<a><b/></a>", &options).is_ok());
+/// assert!(simple_xml::from_string_with_options("<a><b><c/></b></a>", &options).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// The maximum nesting depth of elements, where the root is at depth 1
+    pub max_depth: usize,
+    /// The maximum number of attributes on a single element
+    pub max_attributes: usize,
+    /// The maximum length in bytes of tag and attribute names
+    pub max_name_length: usize,
+    /// The maximum size in bytes of the text content of a single element
+    pub max_text_size: usize,
+    /// The maximum number of elements in the document
+    pub max_nodes: usize,
+    /// Reject tag and attribute names not matching the xml Name production
+    pub check_names: bool,
+    /// Reject elements with the same attribute more than once
+    /// When disabled, the last value is kept
+    pub check_duplicate_attributes: bool,
+    /// How whitespace in the content of elements is handled
+    /// Elements with `xml:space="preserve"` and their descendants always keep all whitespace
+    pub whitespace: Whitespace,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            max_depth: 256,
+            max_attributes: 256,
+            max_name_length: 1024,
+            max_text_size: 16 * 1024 * 1024,
+            max_nodes: 1_000_000,
+            check_names: true,
+            check_duplicate_attributes: true,
+            whitespace: Whitespace::default(),
+        }
+    }
+}
+
+/// How the parser handles whitespace in the content of elements
+/// ```
+/// use simple_xml::{ParseOptions, Whitespace};
+///
+/// let xml = "<a>\n  one   two <b/>\n</a>";
+/// let parse = |whitespace| {
+///     let options = ParseOptions { whitespace, ..Default::default() };
+///     simple_xml::from_string_with_options(xml, &options).unwrap().content
+/// };
+///
+/// assert_eq!(parse(Whitespace::Preserve), "\n  one   two \n");
+/// assert_eq!(parse(Whitespace::Trim), "one   two");
+/// assert_eq!(parse(Whitespace::Normalize), "one two");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Whitespace {
+    /// Keep all whitespace, including the whitespace between elements
+    Preserve,
+    /// Remove the whitespace between elements and around the content
+    #[default]
+    Trim,
+    /// Trim the content and collapse every run of whitespace inside it into a single space
+    Normalize,
+}
+
+/// How serious the problem of a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The document is not well-formed
+    Error,
+    /// The document is well-formed, but may not be read as intended
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found while parsing, along with its position in the input
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// The byte offset into the input
+    pub offset: usize,
+    /// The line, starting at 1
+    pub line: usize,
+    /// The column in characters, starting at 1
+    pub column: usize,
+    pub severity: Severity,
+    pub error: ParseError,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line, self.column, self.severity, self.error
+        )
+    }
+}
+
+/// The position of an element in the input, as byte ranges
+/// Used to edit a document in place without writing the rest of it anew
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Span {
+    /// The opening tag, which is the whole element for empty elements
+    pub start_tag: Range<usize>,
+    /// The content between the opening and the closing tag, None for empty elements
+    pub content: Option<Range<usize>>,
+    /// The values of the attributes, without their quotes
+    pub attributes: HashMap<String, Range<usize>>,
+}
+
+/// An element which has been opened but not yet closed
+struct Frame {
+    node: Node,
+    content: String,
+    /// The position of the opening tag
+    start: usize,
+    /// The index of the span of the element
+    span: usize,
+    /// The whitespace mode in effect, which depends on the `xml:space` attributes in scope
+    whitespace: Whitespace,
+    /// The length of the content when each child element was added
+    breaks: Vec<usize>,
+}
+
+struct Parser<'a> {
+    string: &'a str,
+    options: &'a ParseOptions,
+    lenient: bool,
+    pos: usize,
+    stack: Vec<Frame>,
+    /// The top level elements, of which there can only be one unless lenient
+    roots: Vec<Node>,
+    /// The text outside of the top level elements
+    outside: String,
+    node_count: usize,
+    /// The document type declaration, only read when lenient
+    doctype: Option<Doctype>,
+    diagnostics: Vec<Diagnostic>,
+    /// The spans of the elements in the order they were opened
+    spans: Vec<Span>,
+}
+
+/// Parses a string into the root node
+/// A string without any elements results in an empty node without a tag
+pub(crate) fn parse(string: &str, options: &ParseOptions) -> Result<Node, Error> {
+    let mut parser = Parser::new(string, options, false);
+    parser.run()?;
+    Ok(parser.roots.pop().unwrap_or_else(|| new("", String::new())))
+}
+
+/// Parses a string into the root node, along with the spans of the elements in document order
+pub(crate) fn parse_with_spans(
+    string: &str,
+    options: &ParseOptions,
+) -> Result<(Node, Vec<Span>), Error> {
+    let mut parser = Parser::new(string, options, false);
+    parser.run()?;
+    let root = parser.roots.pop().unwrap_or_else(|| new("", String::new()));
+    Ok((root, parser.spans))
+}
+
+/// Parses a string into a tree, recovering from all errors
+/// Parsing stops at the first exceeded limit, and the elements read so far are returned
+pub(crate) fn parse_lenient(string: &str, options: &ParseOptions) -> (Node, Vec<Diagnostic>) {
+    let mut parser = Parser::new(string, options, true);
+    if let Err(Error::ParseError(e, _)) = parser.run() {
+        let diagnostic = parser.diagnostic(e, parser.pos);
+        parser.diagnostics.push(diagnostic);
+
+        // Lenient parsing only stops at limits, which are not checked when closing
+        while let Some(frame) = parser.stack.pop() {
+            let _ = parser.close(frame);
+        }
+    }
+
+    if parser.roots.is_empty() {
+        parser.report_lenient(ParseError::MissingRoot, Severity::Error, string.len());
+    }
+
+    let root = match parser.roots.len() {
+        1 if parser.outside.trim().is_empty() => parser.roots.pop().unwrap(),
+        _ => {
+            let mut root = new("", parser.outside.trim().to_owned());
+            for node in parser.roots {
+                root.add_node(node);
+            }
+            root
+        }
+    };
+
+    (root, parser.diagnostics)
+}
+
+impl<'a> Parser<'a> {
+    fn new(string: &'a str, options: &'a ParseOptions, lenient: bool) -> Self {
+        Parser {
+            string,
+            options,
+            lenient,
+            pos: 0,
+            stack: Vec::new(),
+            roots: Vec::new(),
+            outside: String::new(),
+            node_count: 0,
+            doctype: None,
+            diagnostics: Vec::new(),
+            spans: Vec::new(),
+        }
+    }
+
+    /// Returns the position of a slice of the input
+    fn offset(&self, slice: &str) -> usize {
+        slice.as_ptr() as usize - self.string.as_ptr() as usize
+    }
+
+    fn diagnostic(&self, error: ParseError, pos: usize) -> Diagnostic {
+        let before = &self.string[..pos];
+        let line_start = before.rfind('\n').map_or(0, |v| v + 1);
+        Diagnostic {
+            offset: pos,
+            line: newlines_in_slice(before) + 1,
+            column: before[line_start..].chars().count() + 1,
+            severity: Severity::Error,
+            error,
+        }
+    }
+
+    /// Reports a problem which is only checked when lenient
+    fn report_lenient(&mut self, e: ParseError, severity: Severity, pos: usize) {
+        if self.lenient {
+            let mut diagnostic = self.diagnostic(e, pos);
+            diagnostic.severity = severity;
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Reports a problem at the given position
+    /// Strict parsing stops with an error, while lenient parsing records it and continues
+    fn report(&mut self, e: ParseError, pos: usize) -> Result<(), Error> {
+        if self.lenient {
+            let diagnostic = self.diagnostic(e, pos);
+            self.diagnostics.push(diagnostic);
+            return Ok(());
+        }
+
+        match e {
+            ParseError::ContentOutsideRoot => Err(Error::ContentOutsideRoot),
+            e => Err(Error::ParseError(e, newlines_in_slice(&self.string[..pos]))),
+        }
+    }
+
+    /// Limits stop parsing in both modes
+    fn check_limit(&self, limit: Limit, value: usize, max: usize) -> Result<(), Error> {
+        match value > max {
+            true => {
+                let e = ParseError::LimitExceeded(limit, max);
+                Err(Error::ParseError(
+                    e,
+                    newlines_in_slice(&self.string[..self.pos]),
+                ))
+            }
+            false => Ok(()),
+        }
+    }
+
+    fn run(&mut self) -> Result<(), Error> {
+        let string = self.string;
+        if self.lenient {
+            self.check_chars();
+        }
+
+        while let Some(i) = string[self.pos..].find('<') {
+            let start = self.pos + i;
+            let rest = &string[start..];
+
+            // When trimming, text before a nested element is trimmed, while text before the closing
+            // tag is kept as is
+            let text = &string[self.pos..start];
+            self.check_references(text);
+            let trim = self
+                .stack
+                .last()
+                .map_or(true, |v| v.whitespace == Whitespace::Trim);
+            match trim && !rest.starts_with("</") {
+                true => self.text(text.trim(), self.pos)?,
+                false => self.text(text, self.pos)?,
+            }
+
+            self.pos = start;
+            if rest.starts_with("<!--") {
+                self.skip_past(start, "-->")?;
+            } else if rest.starts_with("<?") {
+                self.skip_past(start, "?>")?;
+            } else if rest.starts_with("<![CDATA[") {
+                // Content is kept as raw text, so the section is kept as is
+                if self.skip_past(start, "]]>")? {
+                    self.text(&string[start..self.pos], start)?;
+                }
+            } else if rest.starts_with("<!DOCTYPE") {
+                // Document type declarations are skipped, use document_from_string to read them
+                match dtd::doctype_len(rest) {
+                    Some(v) => {
+                        self.read_doctype(&rest[..v], start);
+                        self.pos = start + v;
+                    }
+                    None => self.unterminated(start)?,
+                }
+            } else if rest.starts_with("</") {
+                self.close_tag(start)?;
+            } else {
+                self.open_tag(start)?;
+            }
+        }
+
+        self.check_references(&string[self.pos..]);
+        self.text(&string[self.pos..], self.pos)?;
+
+        // Elements still open are missing their closing tags
+        while let Some(frame) = self.stack.pop() {
+            let e = ParseError::MissingClosingTag(frame.node.tag.clone());
+            self.report(e, frame.start)?;
+            self.close(frame)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads the document type declaration when lenient, to know the declared entities
+    fn read_doctype(&mut self, string: &str, start: usize) {
+        if !self.lenient {
+            return;
+        }
+
+        match Doctype::parse(string) {
+            Ok(v) => self.doctype = Some(v),
+            Err(Error::ParseError(e, _)) => self.report_lenient(e, Severity::Error, start),
+            Err(_) => {}
+        }
+    }
+
+    /// Checks that the input only contains characters allowed in xml
+    fn check_chars(&mut self) {
+        for (i, c) in self.string.char_indices() {
+            if !is_xml_char(c) {
+                self.report_lenient(ParseError::IllegalCharacter(c), Severity::Error, i);
+            } else if is_discouraged_char(c) {
+                self.report_lenient(ParseError::DiscouragedCharacter(c), Severity::Warning, i);
+            }
+        }
+    }
+
+    /// Checks the entity and character references in text or an attribute value
+    fn check_references(&mut self, text: &str) {
+        if !self.lenient {
+            return;
+        }
+
+        let start = self.offset(text);
+        for (i, _) in text.match_indices('&') {
+            let rest = &text[i + 1..];
+            let len = rest
+                .find(|c: char| c == ';' || c == '&' || c == '<' || c.is_whitespace())
+                .unwrap_or(rest.len());
+            let name = &rest[..len];
+            let terminated = rest[len..].starts_with(';');
+
+            let valid = terminated
+                && match name.strip_prefix('#') {
+                    Some(code) => {
+                        let (digits, radix) = match code.strip_prefix('x') {
+                            Some(v) => (v, 16),
+                            None => (code, 10),
+                        };
+                        !digits.is_empty()
+                            && digits.chars().all(|c| c.is_digit(radix))
+                            && u32::from_str_radix(digits, radix)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .is_some_and(is_xml_char)
+                    }
+                    None => is_valid_name(name),
+                };
+
+            if !valid {
+                let reference = &text[i..i + 1 + len + terminated as usize];
+                let e = ParseError::InvalidReference(reference.to_owned());
+                self.report_lenient(e, Severity::Error, start + i);
+                continue;
+            }
+
+            if name.starts_with('#') || PREDEFINED.contains(&name) {
+                continue;
+            }
+
+            let severity = match &self.doctype {
+                Some(doctype)
+                    if doctype
+                        .internal_subset
+                        .as_ref()
+                        .is_some_and(|dtd| dtd.entities.contains_key(name)) =>
+                {
+                    continue
+                }
+                // The entity may be declared in the external subset, which is not read
+                Some(doctype) if doctype.external_id.is_some() => Severity::Warning,
+                _ => Severity::Error,
+            };
+            let e = ParseError::UndeclaredEntity(name.to_owned());
+            self.report_lenient(e, severity, start + i);
+        }
+    }
+
+    /// Moves past the end delimiter, returning false if it is missing
+    fn skip_past(&mut self, start: usize, end: &str) -> Result<bool, Error> {
+        match self.string[start..].find(end) {
+            Some(v) => {
+                self.pos = start + v + end.len();
+                Ok(true)
+            }
+            None => self.unterminated(start).map(|_| false),
+        }
+    }
+
+    /// Reports markup missing its end, keeping the rest of the input as text
+    fn unterminated(&mut self, start: usize) -> Result<(), Error> {
+        self.report(ParseError::MissingClosingDelimiter, start)?;
+        self.text(&self.string[start..], start)?;
+        self.pos = self.string.len();
+        Ok(())
+    }
+
+    /// Adds text to the content of the currently open element
+    fn text(&mut self, text: &str, pos: usize) -> Result<(), Error> {
+        match self.stack.last_mut() {
+            Some(frame) => {
+                frame.content.push_str(text);
+                let len = frame.content.len();
+                self.check_limit(Limit::TextSize, len, self.options.max_text_size)
+            }
+            None => {
+                if !text.trim().is_empty() {
+                    self.report(ParseError::ContentOutsideRoot, pos)?;
+                }
+                self.outside.push_str(text);
+                Ok(())
+            }
+        }
+    }
+
+    /// Adds a completed element to its parent, or to the top level elements
+    fn finish(&mut self, node: Node, pos: usize) -> Result<(), Error> {
+        match self.stack.last_mut() {
+            Some(frame) => {
+                frame.breaks.push(frame.content.len());
+                frame.node.add_node(node);
+            }
+            None => {
+                if !self.roots.is_empty() {
+                    self.report(ParseError::ContentOutsideRoot, pos)?;
+                }
+                self.roots.push(node);
+            }
+        }
+        Ok(())
+    }
+
+    /// Completes an element which has been removed from the stack
+    fn close(&mut self, mut frame: Frame) -> Result<(), Error> {
+        let (content, breaks) = apply_whitespace(&frame.content, &frame.breaks, frame.whitespace);
+        let mut texts = Vec::with_capacity(breaks.len() + 1);
+        let mut start = 0;
+        for end in breaks {
+            texts.push(&content[start..end]);
+            start = end;
+        }
+        texts.push(&content[start..]);
+        frame.node.set_texts(&texts);
+        self.finish(frame.node, frame.start)
+    }
+
+    fn close_tag(&mut self, start: usize) -> Result<(), Error> {
+        let end = match self.string[start..].find('>') {
+            Some(v) => start + v,
+            None => return self.unterminated(start),
+        };
+
+        let string = self.string;
+        let name = string[start + 2..end].trim();
+        self.pos = end + 1;
+
+        if !self.stack.iter().any(|frame| frame.node.tag == name) {
+            // A stray closing tag is kept as text
+            self.report(ParseError::UnexpectedClosingTag(name.to_owned()), start)?;
+            return self.text(&string[start..end + 1], start);
+        }
+
+        // Close the elements which are missing their closing tags
+        while let Some(frame) = self.stack.pop() {
+            if frame.node.tag == name {
+                self.spans[frame.span].content = Some(self.spans[frame.span].start_tag.end..start);
+                return self.close(frame);
+            }
+
+            let e = ParseError::MissingClosingTag(frame.node.tag.clone());
+            self.report(e, frame.start)?;
+            self.close(frame)?;
+        }
+        Ok(())
+    }
+
+    /// Finds the end of a tag, skipping delimiters inside attribute values
+    fn tag_end(&self, start: usize) -> Option<usize> {
+        let mut in_quotes = false;
+        for (i, c) in self.string[start..].char_indices() {
+            match c {
+                '"' => in_quotes = !in_quotes,
+                '>' if !in_quotes => return Some(start + i),
+                _ => {}
+            }
+        }
+
+        // Unbalanced quotes are reported when reading the attributes
+        match self.lenient {
+            true => self.string[start..].find('>').map(|v| start + v),
+            false => None,
+        }
+    }
+
+    fn open_tag(&mut self, start: usize) -> Result<(), Error> {
+        let closing_del = match self.tag_end(start) {
+            Some(v) => v,
+            None => return self.unterminated(start),
+        };
+
+        let string = self.string;
+
+        // Do not consider / of empty as a part
+        let empty = string[..closing_del].ends_with('/');
+        let attr_end = if empty { closing_del - 1 } else { closing_del };
+
+        let mut tag_parts =
+            SplitUnquoted::split(&string[start + 1..attr_end], |c| c.is_whitespace());
+
+        let tag_name = match tag_parts.next() {
+            Some(v) if !v.trim().is_empty() => v.trim(),
+            _ => {
+                self.report(ParseError::MissingTagName, start)?;
+                self.pos = closing_del + 1;
+                return self.text(&string[start..closing_del + 1], start);
+            }
+        };
+
+        if self.options.check_names && !is_valid_name(tag_name) {
+            let e = ParseError::InvalidName(tag_name.to_owned());
+            self.report(e, self.offset(tag_name))?;
+        }
+
+        self.check_limit(
+            Limit::NameLength,
+            tag_name.len(),
+            self.options.max_name_length,
+        )?;
+        self.check_limit(Limit::Depth, self.stack.len() + 1, self.options.max_depth)?;
+        self.node_count += 1;
+        self.check_limit(Limit::Nodes, self.node_count, self.options.max_nodes)?;
+
+        let mut attributes = HashMap::new();
+        let mut span = Span {
+            start_tag: start..closing_del + 1,
+            ..Default::default()
+        };
+        for part in tag_parts {
+            // Parts after repeated whitespace start with the extra whitespace, and whitespace
+            // before the end of the tag leaves an empty part
+            let part = part.trim_start();
+            if part.is_empty() {
+                continue;
+            }
+            let part_start = self.offset(part);
+            let equal_sign = match part.find('=') {
+                Some(v) => v,
+                None => {
+                    // Read as an attribute without a value when lenient
+                    let e = ParseError::MissingAttributeValue(part.to_owned());
+                    self.report(e, part_start)?;
+                    attributes.insert(part.to_owned(), String::new());
+                    continue;
+                }
+            };
+
+            // Get key and value from attribute
+            let (k, v) = part.split_at(equal_sign);
+
+            // Remove the equal sign and quotes from value
+            let v = match v[1..]
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"').or_else(|| v.strip_suffix("\"/")))
+            {
+                Some(v) => v,
+                None => {
+                    // Read as an unquoted value when lenient
+                    let e = ParseError::MissingQuotes(part.to_owned());
+                    self.report(e, part_start)?;
+                    v[1..].trim_matches('"')
+                }
+            };
+
+            self.check_limit(Limit::NameLength, k.len(), self.options.max_name_length)?;
+            if self.options.check_names && !is_valid_name(k) {
+                self.report(ParseError::InvalidName(k.to_owned()), part_start)?;
+            }
+
+            self.check_references(v);
+            let value_start = self.offset(v);
+            span.attributes
+                .insert(k.to_owned(), value_start..value_start + v.len());
+            let duplicate = attributes.insert(k.to_owned(), v.to_owned()).is_some();
+            if self.options.check_duplicate_attributes && duplicate {
+                self.report(ParseError::DuplicateAttribute(k.to_owned()), part_start)?;
+            }
+            self.check_limit(
+                Limit::Attributes,
+                attributes.len(),
+                self.options.max_attributes,
+            )?;
+        }
+
+        let mut node = new(tag_name, String::new());
+        node.attributes = attributes;
+        self.pos = closing_del + 1;
+        self.spans.push(span);
+
+        // Empty but valid node
+        if empty {
+            return self.finish(node, start);
+        }
+
+        let whitespace = match node.attributes.get("xml:space").map(String::as_str) {
+            Some("preserve") => Whitespace::Preserve,
+            Some("default") => self.options.whitespace,
+            _ => self
+                .stack
+                .last()
+                .map_or(self.options.whitespace, |v| v.whitespace),
+        };
+        self.stack.push(Frame {
+            node,
+            content: String::new(),
+            start,
+            span: self.spans.len() - 1,
+            whitespace,
+            breaks: Vec::new(),
+        });
+        Ok(())
+    }
+}
+
+/// Applies a whitespace mode to the content of an element, along with the positions of its child
+/// elements in the content
+fn apply_whitespace(
+    content: &str,
+    breaks: &[usize],
+    whitespace: Whitespace,
+) -> (String, Vec<usize>) {
+    if whitespace == Whitespace::Preserve {
+        return (content.to_owned(), breaks.to_vec());
+    }
+
+    let mut output = String::with_capacity(content.len());
+    let mut positions = Vec::with_capacity(breaks.len());
+    let mut breaks = breaks.iter().peekable();
+    let mut space = false;
+    for (i, c) in content.trim_end().char_indices() {
+        while breaks.next_if(|v| **v <= i).is_some() {
+            positions.push(output.len());
+        }
+
+        // Leading whitespace is dropped, and with Normalize runs of whitespace become a single space
+        match (whitespace, c.is_whitespace()) {
+            (_, true) if output.is_empty() => {}
+            (Whitespace::Normalize, true) => space = true,
+            (Whitespace::Normalize, false) if space => {
+                output.push(' ');
+                output.push(c);
+                space = false;
+            }
+            _ => output.push(c),
+        }
+    }
+    positions.extend(breaks.map(|_| output.len()));
+    (output, positions)
+}
@@ -0,0 +1,281 @@
+//! This is a module providing XML patch documents as described in RFC 5261
+//! A patch is an element, usually `<diff>`, containing `<add>`, `<replace>` and `<remove>` operations
+//! which are applied in order. Each operation selects a single element, attribute or text with a path
+//! in its `sel` attribute, see the `path` module for the supported syntax
+//! ```
+//! let mut root = simple_xml::from_string(r#"<config><port>80</port></config>"#).unwrap();
+//! let patch = simple_xml::from_string(
+//!     r#"<diff>
+//!         <replace sel="/config/port/text()">8080</replace>
+//!         <add sel="/config"><host>localhost</host></add>
+//!         <add sel="/config/port" type="@protocol">tcp</add>
+//!     </diff>"#,
+//! )
+//! .unwrap();
+//!
+//! simple_xml::patch::apply(&mut root, &patch).unwrap();
+//! assert_eq!(root["port"][0].content, "8080");
+//! assert_eq!(root["port"][0].get_attribute("protocol").unwrap(), "tcp");
+//! assert_eq!(root["host"][0].content, "localhost");
+//! ```
+
+use crate::diff::Edit;
+use crate::path::{self, Location, Path, Target};
+use crate::{Error, Node};
+
+/// Applies the operations of a patch document to a node tree
+/// The operations are applied atomically, if any of them fail the tree is left unchanged
+/// Returns an Err of SelectorNotFound if a selector matches nothing, or an Err of InvalidPatch if an
+/// operation is malformed, unsupported or its selector matches more than one node
+pub fn apply(root: &mut Node, patch: &Node) -> Result<(), Error> {
+    let mut patched = root.clone();
+    for operation in patch.children() {
+        apply_operation(&mut patched, operation)?;
+    }
+
+    *root = patched;
+    Ok(())
+}
+
+fn apply_operation(root: &mut Node, operation: &Node) -> Result<(), Error> {
+    let invalid = |message: &str| Error::InvalidPatch(format!("<{}> {}", operation.tag, message));
+    let sel = operation
+        .get_attribute("sel")
+        .ok_or_else(|| invalid("is missing the sel attribute"))?;
+    let not_found = || Error::SelectorNotFound(sel.to_owned());
+
+    let path = Path::parse(sel)?;
+    let location = match &path.locate(root)[..] {
+        [] => return Err(not_found()),
+        [location] => location.clone(),
+        _ => {
+            return Err(invalid(&format!(
+                "selector {:?} matches more than one node",
+                sel
+            )))
+        }
+    };
+    // Operations changing the children of the parent are handled before borrowing the node
+    match (operation.tag.as_str(), path.target()) {
+        ("add", Target::Element) if !operation.attributes.contains_key("type") => {
+            return add(root, &location, operation);
+        }
+        ("replace", Target::Element) => {
+            let mut children = operation.children();
+            let replacement = match (children.next(), children.next()) {
+                (Some(child), None) => child.clone(),
+                _ => return Err(invalid("must contain exactly one element")),
+            };
+            // The replacement can have a different tag, so it is reinserted to keep the tag index in sync
+            match parent_position(root, &location) {
+                Some((parent, position)) => {
+                    parent.remove_at(position);
+                    parent.insert_at(position, replacement);
+                }
+                None => *root = replacement,
+            }
+            return Ok(());
+        }
+        ("remove", Target::Element) => {
+            let (parent, position) = parent_position(root, &location)
+                .ok_or_else(|| invalid("can not remove the root"))?;
+            parent.remove_at(position);
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let node = location.get_mut(root).ok_or_else(not_found)?;
+    match (operation.tag.as_str(), path.target()) {
+        ("add", Target::Element) => {
+            let kind = &operation.attributes["type"];
+            let name = kind
+                .strip_prefix('@')
+                .ok_or_else(|| invalid(&format!("has unsupported type {:?}", kind)))?;
+            if node.attributes.contains_key(name) {
+                return Err(invalid(&format!("adds existing attribute {:?}", name)));
+            }
+            node.add_attribute(name, &operation.content);
+        }
+        ("replace", Target::Attribute(name)) => match node.attributes.get_mut(name) {
+            Some(value) => *value = operation.content.clone(),
+            None => return Err(not_found()),
+        },
+        ("replace", Target::Text) => node.content = operation.content.clone(),
+        ("remove", Target::Attribute(name)) => {
+            node.attributes.remove(name).ok_or_else(not_found)?;
+        }
+        ("remove", Target::Text) => node.set_texts(&vec![""; node.child_count() + 1]),
+        ("add", _) => return Err(invalid("must select an element")),
+        _ => return Err(invalid("is not a patch operation")),
+    }
+    Ok(())
+}
+
+/// Inserts the child elements and text of an add operation relative to the selected element
+/// Appending and prepending go after and before all text of the selected element, while siblings
+/// go directly before or after it, leaving the text around it in place
+fn add(root: &mut Node, location: &Location, operation: &Node) -> Result<(), Error> {
+    let invalid = |message: String| Error::InvalidPatch(format!("<add> {}", message));
+
+    // Whether the nodes go after the text at the position, rather than before it
+    let (parent, position, after_text) = match operation.get_attribute("pos").map(String::as_str) {
+        None | Some("append") => {
+            let node = location.get_mut(root).expect("Location was just found");
+            let position = node.child_count();
+            (node, position, true)
+        }
+        Some("prepend") => {
+            let node = location.get_mut(root).expect("Location was just found");
+            (node, 0, false)
+        }
+        Some(pos @ ("before" | "after")) => {
+            let (parent, position) = parent_position(root, location)
+                .ok_or_else(|| invalid("can not add siblings to the root".to_owned()))?;
+            match pos {
+                "before" => (parent, position, true),
+                _ => (parent, position + 1, false),
+            }
+        }
+        Some(pos) => return Err(invalid(format!("has unsupported pos {:?}", pos))),
+    };
+
+    let mut texts: Vec<String> = parent.texts().into_iter().map(str::to_owned).collect();
+    let mut inserted: Vec<String> = operation.texts().into_iter().map(str::to_owned).collect();
+    let text = texts.remove(position);
+    match after_text {
+        true => inserted[0].insert_str(0, &text),
+        false => inserted
+            .last_mut()
+            .expect("Texts are never empty")
+            .push_str(&text),
+    }
+    texts.splice(position..position, inserted);
+
+    for (i, node) in operation.children().enumerate() {
+        parent.insert_at(position + i, node.clone());
+    }
+    parent.set_texts(&texts);
+    Ok(())
+}
+
+/// Returns the parent of the node at a location and the position of the node among its children
+fn parent_position<'a>(root: &'a mut Node, location: &Location) -> Option<(&'a mut Node, usize)> {
+    let (tag, index) = location.0.last()?;
+    let parent = location.parent()?.get_mut(root)?;
+    let position = parent.position(tag, *index)?;
+    Some((parent, position))
+}
+
+/// Creates a patch document turning old into new from the edits returned by `diff`
+/// Attribute and content changes come first, followed by removals in reverse document order so that
+/// earlier selectors stay valid, and finally additions in document order
+/// Moved elements are removed and added again
+/// Where text sits between child elements is not part of the edits, so elements mixing text with
+/// child elements can come out with their text in other places, otherwise applying the patch to old
+/// gives new
+/// ```
+/// let old = simple_xml::from_string(r#"<a><b x="1"/><c/><d/></a>"#).unwrap();
+/// let new = simple_xml::from_string(r#"<a><d/><b x="2"/><e/></a>"#).unwrap();
+///
+/// let patch = simple_xml::patch::from_diff(&old, &new, &simple_xml::diff(&old, &new));
+/// let mut patched = old.clone();
+/// simple_xml::patch::apply(&mut patched, &patch).unwrap();
+/// assert_eq!(patched, new);
+/// ```
+pub fn from_diff(old: &Node, new: &Node, edits: &[Edit]) -> Node {
+    let mut patch = crate::new("diff", String::new());
+    let selector = |location: &Location| format!("/{}{}", old.tag, location);
+    let operation = |tag: &str, sel: String| Node::builder(tag).attr("sel", sel);
+
+    let mut removals = Vec::new();
+    let mut additions = Vec::new();
+    for edit in edits {
+        let node = match edit {
+            Edit::Removed { location, .. } | Edit::Added { location, .. }
+                if location.0.is_empty() =>
+            {
+                // The root element changed, so the whole tree is replaced
+                let replace = operation("replace", format!("/{}", old.tag)).child(new.clone());
+                return Node::builder("diff").child(replace).build();
+            }
+            Edit::Removed { location, .. } => {
+                removals.push(location.clone());
+                continue;
+            }
+            Edit::Added { location, .. } => {
+                additions.push(location.clone());
+                continue;
+            }
+            Edit::Moved { from, to } => {
+                removals.push(from.clone());
+                additions.push(to.clone());
+                continue;
+            }
+            Edit::AttributeAdded {
+                location,
+                name,
+                value,
+            } => operation("add", selector(location))
+                .attr("type", format!("@{}", name))
+                .text(value),
+            Edit::AttributeRemoved { location, name, .. } => {
+                operation("remove", format!("{}/@{}", selector(location), name))
+            }
+            Edit::AttributeChanged {
+                location,
+                name,
+                new,
+                ..
+            } => operation("replace", format!("{}/@{}", selector(location), name)).text(new),
+            Edit::ContentChanged { location, new, .. } => {
+                operation("replace", format!("{}/text()", selector(location))).text(new)
+            }
+        };
+        patch.add_node(node.build());
+    }
+
+    removals.sort_by_cached_key(|location| document_position(old, location));
+    for location in removals.iter().rev() {
+        patch.add_node(operation("remove", selector(location)).build());
+    }
+
+    // Everything before an added element in document order is in place when it is added,
+    // so it can be selected by its location in the new tree
+    additions.sort_by_cached_key(|location| document_position(new, location));
+    for location in additions {
+        let (parent, (tag, index)) = match (location.parent(), location.0.last()) {
+            (Some(parent), Some(last)) => (parent, last),
+            _ => continue,
+        };
+        let (node, siblings) = match (location.get(new), parent.get(new)) {
+            (Some(node), Some(siblings)) => (node, siblings),
+            _ => continue,
+        };
+
+        let operation = match siblings.position(tag, *index) {
+            Some(0) | None => operation("add", selector(&parent)).attr("pos", "prepend"),
+            Some(position) => {
+                let previous = &path::children(siblings, &parent)[position - 1];
+                operation("add", selector(previous)).attr("pos", "after")
+            }
+        };
+        patch.add_node(operation.child(node.clone()).build());
+    }
+
+    patch
+}
+
+/// Returns the positions in document order of the steps of a location, which sort in document order
+fn document_position(root: &Node, location: &Location) -> Vec<usize> {
+    let mut node = root;
+    let mut positions = Vec::new();
+    for (tag, index) in &location.0 {
+        positions.extend(node.position(tag, *index));
+        match node.nodes.get(tag).and_then(|v| v.get(*index)) {
+            Some(child) => node = child,
+            None => break,
+        }
+    }
+    positions
+}
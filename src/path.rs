@@ -0,0 +1,181 @@
+//! This is a module providing selection of elements with XPath 1.0 location paths, evaluated by
+//! the same engine as XSLT stylesheets
+//! Paths can use every axis, node test, predicate and function, but no variables
+//! A final `@attribute` or `text()` step selects the attribute values or text content of the elements
+//! matched by the rest of the path
+//! ```
+//! let root = simple_xml::from_file("./examples/graph.xml").unwrap();
+//!
+//! let edges = root.select("/graph/edge[@from='n3']").unwrap();
+//! assert_eq!(edges.len(), 2);
+//!
+//! let path = simple_xml::path::Path::parse("//node[last()]/@id").unwrap();
+//! assert_eq!(path.values(&root), ["n4"]);
+//!
+//! let targets = root.select("//node[@id = ../edge[@from='n3']/@to]").unwrap();
+//! assert_eq!(targets.len(), 2);
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::xpath::{Context, Expr, Item};
+use crate::{entity, visit, Error, Node};
+
+/// A parsed path
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    /// The expression selecting the elements, without the final attribute or text step
+    expr: Expr,
+    target: Target,
+}
+
+/// What a path selects from the matched elements
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    Element,
+    Attribute(String),
+    Text,
+}
+
+/// The location of a node below a root node
+/// Each step is the tag and the index among the child nodes with that tag
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Location(pub Vec<(String, usize)>);
+
+impl Location {
+    /// Returns the node at the location below root
+    pub fn get<'a>(&self, root: &'a Node) -> Option<&'a Node> {
+        self.0
+            .iter()
+            .try_fold(root, |node, (tag, index)| node.nodes.get(tag)?.get(*index))
+    }
+
+    /// Returns the node at the location below root mutably
+    pub fn get_mut<'a>(&self, root: &'a mut Node) -> Option<&'a mut Node> {
+        self.0.iter().try_fold(root, |node, (tag, index)| {
+            node.nodes.get_mut(tag)?.get_mut(*index)
+        })
+    }
+
+    /// Returns the location of the parent, or None for the root
+    pub fn parent(&self) -> Option<Location> {
+        let (_, parent) = self.0.split_last()?;
+        Some(Location(parent.to_vec()))
+    }
+}
+
+/// Formats the location as a path with 1-based positions, starting below the root
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (tag, index) in &self.0 {
+            write!(f, "/{}[{}]", tag, index + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Path {
+    /// Parses a path
+    /// Returns an Err of InvalidPath if the path is malformed, is not a location path selecting
+    /// elements, or calls unknown functions
+    pub fn parse(path: &str) -> Result<Path, Error> {
+        let invalid = |_| Error::InvalidPath(path.to_owned());
+        let mut expr = Expr::parse(path).map_err(invalid)?;
+        expr.check().map_err(invalid)?;
+        let target = expr.split_target();
+        match expr.selects_elements() {
+            true => Ok(Path { expr, target }),
+            false => Err(Error::InvalidPath(path.to_owned())),
+        }
+    }
+
+    /// Returns what the path selects from the matched elements
+    pub fn target(&self) -> &Target {
+        &self.target
+    }
+
+    /// Returns the locations of the matched elements in document order
+    /// Absolute paths start above the root, so the first step matches the root itself
+    /// Relative paths start at the root
+    pub fn locate(&self, root: &Node) -> Vec<Location> {
+        let item = Item::Element(Location::default());
+        let context = Context {
+            root,
+            item: item.clone(),
+            position: 1,
+            size: 1,
+            current: item,
+            variables: &[],
+        };
+        let items = self
+            .expr
+            .select(&context)
+            .expect("Paths are checked when parsed");
+
+        let mut locations: Vec<Location> = items
+            .into_iter()
+            .filter_map(|item| match item {
+                // A single slash selects the document, which stands for the root
+                Item::Document => Some(Location::default()),
+                Item::Element(location) => Some(location),
+                _ => None,
+            })
+            .collect();
+        // The document comes right before the root in document order
+        locations.dedup();
+        locations
+    }
+
+    /// Returns the matched elements in document order
+    pub fn select<'a>(&self, root: &'a Node) -> Vec<&'a Node> {
+        self.locate(root)
+            .iter()
+            .filter_map(|location| location.get(root))
+            .collect()
+    }
+
+    /// Returns the selected values
+    /// These are the attribute values or text content of the matched elements, depending on the target
+    /// For paths selecting elements, the content of the elements is returned
+    pub fn values<'a>(&self, root: &'a Node) -> Vec<&'a str> {
+        let nodes = self.select(root).into_iter();
+        match &self.target {
+            Target::Attribute(name) => nodes
+                .filter_map(|node| node.get_attribute(name).map(String::as_str))
+                .collect(),
+            Target::Element | Target::Text => nodes.map(|node| node.content.as_str()).collect(),
+        }
+    }
+}
+
+/// Returns the locations of the children of a node in document order
+pub(crate) fn children(node: &Node, location: &Location) -> Vec<Location> {
+    let mut counts = HashMap::new();
+    node.children()
+        .map(|child| {
+            let index = counts.entry(child.tag.as_str()).or_insert(0);
+            let mut steps = location.0.clone();
+            steps.push((child.tag.clone(), *index));
+            *index += 1;
+            Location(steps)
+        })
+        .collect()
+}
+
+/// Returns the locations of the elements with one of the given attributes equal to id, in document
+/// order
+pub(crate) fn find_ids<'a>(
+    root: &'a Node,
+    keys: &'a [&str],
+    id: &'a str,
+) -> impl Iterator<Item = Location> + 'a {
+    visit::with_locations(root)
+        .filter(move |(_, node)| {
+            keys.iter().any(|key| {
+                node.get_attribute(key)
+                    .is_some_and(|v| entity::decode(v) == id)
+            })
+        })
+        .map(|(location, _)| location)
+}
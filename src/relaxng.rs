@@ -0,0 +1,1450 @@
+//! This is a module providing validation of xml structures against RELAX NG schemas
+//! Schemas can be loaded in both the xml and the compact syntax
+//!
+//! Validation uses the derivative algorithm by James Clark, which supports interleave without
+//! backtracking
+//! Namespaces are not resolved; elements and attributes are matched by their local name
+//! External references, includes, and nested grammars are not supported
+//! The XML Schema datatype library is available with the `xsd` feature, which is enabled by default
+//! ```
+//! let schema = simple_xml::relaxng::Schema::from_compact(
+//!     r#"element note {
+//!         attribute lang { "en" | "sv" }?,
+//!         (element to { text }+ & element from { text })
+//!     }"#,
+//! )
+//! .unwrap();
+//!
+//! let note = simple_xml::from_string("<note><from>Jani</from><to>Tove</to></note>").unwrap();
+//! assert_eq!(schema.validate(&note), []);
+//!
+//! let note = simple_xml::from_string("<note lang=\"de\"><to>Tove</to></note>").unwrap();
+//! assert_eq!(schema.validate(&note).len(), 2);
+//! ```
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+#[cfg(feature = "xsd")]
+use regex::Regex;
+use thiserror::Error;
+
+#[cfg(feature = "xsd")]
+use crate::datatype::Builtin;
+use crate::{Error, Node, NodeBuilder};
+
+/// The namespace of the XML Schema datatype library
+pub const XSD_DATATYPES: &str = "http://www.w3.org/2001/XMLSchema-datatypes";
+
+/// A library of datatypes which can be referenced by data and value patterns
+/// Libraries are registered on a schema by their namespace URI
+pub trait DatatypeLibrary {
+    /// Returns true if the datatype exists in the library
+    fn contains(&self, datatype: &str) -> bool;
+    /// Returns true if the value is valid for the datatype with the given parameters
+    fn allows(&self, datatype: &str, params: &[(String, String)], value: &str) -> bool;
+    /// Returns true if the two values are equal in the value space of the datatype
+    fn equal(&self, datatype: &str, a: &str, b: &str) -> bool;
+}
+
+/// The built in library containing string and token
+struct BuiltinLibrary;
+
+impl DatatypeLibrary for BuiltinLibrary {
+    fn contains(&self, datatype: &str) -> bool {
+        matches!(datatype, "string" | "token")
+    }
+
+    fn allows(&self, _: &str, _: &[(String, String)], _: &str) -> bool {
+        true
+    }
+
+    fn equal(&self, datatype: &str, a: &str, b: &str) -> bool {
+        match datatype {
+            "string" => a == b,
+            _ => a.split_whitespace().eq(b.split_whitespace()),
+        }
+    }
+}
+
+/// The XML Schema datatype library
+/// Supports the pattern, length, and range parameters
+#[cfg(feature = "xsd")]
+struct XsdLibrary;
+
+#[cfg(feature = "xsd")]
+impl DatatypeLibrary for XsdLibrary {
+    fn contains(&self, datatype: &str) -> bool {
+        Builtin::from_name(datatype).is_some()
+    }
+
+    fn allows(&self, datatype: &str, params: &[(String, String)], value: &str) -> bool {
+        let builtin = match Builtin::from_name(datatype) {
+            Some(v) => v,
+            None => return false,
+        };
+
+        if !builtin.accepts(value) {
+            return false;
+        }
+
+        let value = match builtin {
+            Builtin::String => value,
+            _ => value.trim(),
+        };
+
+        let len = value.chars().count();
+        let number = value.parse::<f64>().unwrap_or(f64::NAN);
+        params.iter().all(|(k, v)| {
+            let count = || v.parse::<usize>().unwrap_or_default();
+            let bound = || v.parse::<f64>().unwrap_or(f64::NAN);
+            match k.as_str() {
+                "pattern" => Regex::new(&format!("^(?:{})$", v)).is_ok_and(|r| r.is_match(value)),
+                "length" => len == count(),
+                "minLength" => len >= count(),
+                "maxLength" => len <= count(),
+                "minInclusive" => number >= bound(),
+                "maxInclusive" => number <= bound(),
+                "minExclusive" => number > bound(),
+                "maxExclusive" => number < bound(),
+                _ => true,
+            }
+        })
+    }
+
+    fn equal(&self, datatype: &str, a: &str, b: &str) -> bool {
+        let (a, b) = (a.trim(), b.trim());
+        match Builtin::from_name(datatype) {
+            Some(Builtin::String) => a == b,
+            Some(Builtin::Boolean) => {
+                let truthy = |v| matches!(v, "true" | "1");
+                truthy(a) == truthy(b)
+            }
+            Some(v) if v.is_numeric() => match (a.parse::<f64>(), b.parse::<f64>()) {
+                (Ok(a), Ok(b)) => a == b,
+                _ => false,
+            },
+            _ => a == b,
+        }
+    }
+}
+
+/// A loaded RELAX NG schema
+pub struct Schema {
+    start: Rc<Pattern>,
+    defines: Vec<Rc<Pattern>>,
+    libraries: HashMap<String, Box<dyn DatatypeLibrary>>,
+}
+
+impl std::fmt::Debug for Schema {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Schema")
+            .field("start", &self.start)
+            .field("defines", &self.defines)
+            .field("libraries", &self.libraries.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// A single problem found when validating a node against a schema
+#[derive(Debug, Error, Clone, PartialEq)]
+#[error("{path}: {kind}")]
+pub struct Violation {
+    /// The path to the offending node, e.g; /note/to[2]
+    pub path: String,
+    pub kind: ViolationKind,
+}
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum ViolationKind {
+    #[error("Element {found:?} is not allowed here, expected one of {expected:?}")]
+    UnexpectedElement {
+        found: String,
+        expected: Vec<String>,
+    },
+    #[error("Attribute {0:?} is not allowed")]
+    UnexpectedAttribute(String),
+    #[error("Invalid value {value:?} for attribute {name:?}")]
+    InvalidAttributeValue { name: String, value: String },
+    #[error("Missing required attribute {0:?}")]
+    MissingAttribute(String),
+    #[error("Text {0:?} is not allowed here")]
+    InvalidText(String),
+    #[error("Element is incomplete, expected one of {0:?}")]
+    IncompleteContent(Vec<String>),
+    #[error("Datatype library {0:?} is not registered")]
+    UnknownDatatypeLibrary(String),
+    #[error("Datatype {name:?} does not exist in library {library:?}")]
+    UnknownDatatype { library: String, name: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum NameClass {
+    Name(String),
+    AnyName(Option<Box<NameClass>>),
+    Choice(Box<NameClass>, Box<NameClass>),
+}
+
+impl NameClass {
+    fn contains(&self, name: &str) -> bool {
+        match self {
+            NameClass::Name(v) => v == local(name),
+            NameClass::AnyName(except) => !except.as_ref().is_some_and(|v| v.contains(name)),
+            NameClass::Choice(a, b) => a.contains(name) || b.contains(name),
+        }
+    }
+
+    fn describe(&self, names: &mut Vec<String>) {
+        let name = match self {
+            NameClass::Name(v) => v.clone(),
+            NameClass::AnyName(_) => "*".into(),
+            NameClass::Choice(a, b) => {
+                a.describe(names);
+                return b.describe(names);
+            }
+        };
+
+        if !names.contains(&name) {
+            names.push(name)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Pattern {
+    Empty,
+    NotAllowed,
+    Text,
+    Choice(Rc<Pattern>, Rc<Pattern>),
+    Interleave(Rc<Pattern>, Rc<Pattern>),
+    Group(Rc<Pattern>, Rc<Pattern>),
+    OneOrMore(Rc<Pattern>),
+    List(Rc<Pattern>),
+    Data {
+        library: String,
+        name: String,
+        params: Vec<(String, String)>,
+        except: Option<Rc<Pattern>>,
+    },
+    Value {
+        library: String,
+        name: String,
+        value: String,
+    },
+    Attribute(NameClass, Rc<Pattern>),
+    Element(NameClass, Rc<Pattern>),
+    /// A reference to a named definition
+    Ref(usize),
+    /// Content of an element which is followed by the remaining content of the parent
+    After(Rc<Pattern>, Rc<Pattern>),
+}
+
+use Pattern::*;
+
+thread_local! {
+    static EMPTY: Rc<Pattern> = Rc::new(Empty);
+    static NOT_ALLOWED: Rc<Pattern> = Rc::new(NotAllowed);
+}
+
+fn empty() -> Rc<Pattern> {
+    EMPTY.with(Rc::clone)
+}
+
+fn not_allowed() -> Rc<Pattern> {
+    NOT_ALLOWED.with(Rc::clone)
+}
+
+fn choice(a: Rc<Pattern>, b: Rc<Pattern>) -> Rc<Pattern> {
+    match (&*a, &*b) {
+        (NotAllowed, _) => b,
+        (_, NotAllowed) => a,
+        _ if Rc::ptr_eq(&a, &b) || a == b => a,
+        _ => Rc::new(Choice(a, b)),
+    }
+}
+
+fn group(a: Rc<Pattern>, b: Rc<Pattern>) -> Rc<Pattern> {
+    match (&*a, &*b) {
+        (NotAllowed, _) | (_, NotAllowed) => not_allowed(),
+        (Empty, _) => b,
+        (_, Empty) => a,
+        _ => Rc::new(Group(a, b)),
+    }
+}
+
+fn interleave(a: Rc<Pattern>, b: Rc<Pattern>) -> Rc<Pattern> {
+    match (&*a, &*b) {
+        (NotAllowed, _) | (_, NotAllowed) => not_allowed(),
+        (Empty, _) => b,
+        (_, Empty) => a,
+        _ => Rc::new(Interleave(a, b)),
+    }
+}
+
+fn after(a: Rc<Pattern>, b: Rc<Pattern>) -> Rc<Pattern> {
+    match (&*a, &*b) {
+        (NotAllowed, _) | (_, NotAllowed) => not_allowed(),
+        _ => Rc::new(After(a, b)),
+    }
+}
+
+fn one_or_more(p: Rc<Pattern>) -> Rc<Pattern> {
+    match &*p {
+        NotAllowed => p,
+        _ => Rc::new(OneOrMore(p)),
+    }
+}
+
+/// Returns the local part of a qualified name
+fn local(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+fn invalid(msg: String) -> Error {
+    Error::InvalidSchema(msg)
+}
+
+/// Loads a schema from a file
+/// Files ending in .rnc are read as compact syntax, all others as xml syntax
+pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Schema, Error> {
+    let path = path.as_ref();
+    match path.extension().and_then(|v| v.to_str()) {
+        Some("rnc") => Schema::from_compact(&std::fs::read_to_string(path)?),
+        _ => Schema::from_node(&crate::from_file(path)?),
+    }
+}
+
+impl Schema {
+    /// Loads a schema in the xml syntax from its root node
+    pub fn from_node(root: &Node) -> Result<Schema, Error> {
+        let mut loader = Loader {
+            names: HashMap::new(),
+            defines: Vec::new(),
+        };
+
+        let start = match local(&root.tag) {
+            "grammar" => {
+                let mut start = None;
+                loader.grammar(root, "", &mut start)?;
+                start.ok_or_else(|| invalid("Missing start pattern".into()))?
+            }
+            _ => loader.pattern(root, "")?,
+        };
+
+        let mut names = vec![String::new(); loader.defines.len()];
+        for (name, i) in loader.names {
+            names[i] = name;
+        }
+
+        let defines = loader
+            .defines
+            .into_iter()
+            .zip(names)
+            .map(|(v, name)| v.ok_or_else(|| invalid(format!("Undefined reference {:?}", name))))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut libraries: HashMap<String, Box<dyn DatatypeLibrary>> = HashMap::new();
+        libraries.insert(String::new(), Box::new(BuiltinLibrary));
+        #[cfg(feature = "xsd")]
+        libraries.insert(XSD_DATATYPES.into(), Box::new(XsdLibrary));
+
+        let schema = Schema {
+            start,
+            defines,
+            libraries,
+        };
+
+        // References must pass through an element to not recurse indefinitely
+        for i in 0..schema.defines.len() {
+            if schema.refers_to(
+                &schema.defines[i],
+                i,
+                &mut vec![false; schema.defines.len()],
+            ) {
+                return Err(invalid("Recursive reference outside of an element".into()));
+            }
+        }
+
+        Ok(schema)
+    }
+
+    /// Loads a schema in the compact syntax
+    pub fn from_compact(string: &str) -> Result<Schema, Error> {
+        Schema::from_node(&compact::parse(string)?)
+    }
+
+    /// Registers a datatype library for data and value patterns with the given datatypeLibrary URI
+    /// The built in library is always available, and the XML Schema datatypes with the `xsd` feature
+    pub fn add_library<L: DatatypeLibrary + 'static>(&mut self, uri: &str, library: L) {
+        self.libraries.insert(uri.to_owned(), Box::new(library));
+    }
+
+    /// Validates a node and all its children against the schema
+    /// An empty list means the node is valid
+    pub fn validate(&self, node: &Node) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let path = format!("/{}", node.tag);
+
+        if let Some(kind) = self.unknown_datatype() {
+            violations.push(Violation { path, kind });
+            return violations;
+        }
+
+        let p = self.element_deriv(&self.start, node, &path, &mut violations);
+        // The root is matched as the only child of the document
+        if !matches!(*p, NotAllowed) && !self.nullable(&p) {
+            violations.push(Violation {
+                path,
+                kind: ViolationKind::IncompleteContent(self.expected(&p)),
+            });
+        }
+        violations
+    }
+
+    /// Returns the first datatype which is not provided by the registered libraries
+    fn unknown_datatype(&self) -> Option<ViolationKind> {
+        fn walk(schema: &Schema, p: &Pattern) -> Option<ViolationKind> {
+            let (library, name) = match p {
+                Data { library, name, .. } | Value { library, name, .. } => (library, name),
+                Choice(a, b) | Interleave(a, b) | Group(a, b) | After(a, b) => {
+                    return walk(schema, a).or_else(|| walk(schema, b))
+                }
+                OneOrMore(p) | List(p) | Attribute(_, p) | Element(_, p) => return walk(schema, p),
+                _ => return None,
+            };
+
+            match schema.libraries.get(library) {
+                None => Some(ViolationKind::UnknownDatatypeLibrary(library.clone())),
+                Some(v) if !v.contains(name) => Some(ViolationKind::UnknownDatatype {
+                    library: library.clone(),
+                    name: name.clone(),
+                }),
+                Some(_) => match p {
+                    Data {
+                        except: Some(except),
+                        ..
+                    } => walk(schema, except),
+                    _ => None,
+                },
+            }
+        }
+
+        std::iter::once(&self.start)
+            .chain(&self.defines)
+            .find_map(|p| walk(self, p))
+    }
+
+    /// Returns true if the pattern references the definition without passing through an element
+    fn refers_to(&self, p: &Pattern, target: usize, visited: &mut Vec<bool>) -> bool {
+        match p {
+            Ref(i) if *i == target => true,
+            Ref(i) if visited[*i] => false,
+            Ref(i) => {
+                visited[*i] = true;
+                self.refers_to(&self.defines[*i], target, visited)
+            }
+            Choice(a, b) | Interleave(a, b) | Group(a, b) | After(a, b) => {
+                self.refers_to(a, target, visited) || self.refers_to(b, target, visited)
+            }
+            OneOrMore(p) | List(p) | Attribute(_, p) => self.refers_to(p, target, visited),
+            _ => false,
+        }
+    }
+
+    fn deref<'a>(&'a self, p: &'a Rc<Pattern>) -> &'a Rc<Pattern> {
+        match **p {
+            Ref(i) => self.deref(&self.defines[i]),
+            _ => p,
+        }
+    }
+
+    fn nullable(&self, p: &Rc<Pattern>) -> bool {
+        match &**self.deref(p) {
+            Empty | Text => true,
+            Group(a, b) | Interleave(a, b) => self.nullable(a) && self.nullable(b),
+            Choice(a, b) => self.nullable(a) || self.nullable(b),
+            OneOrMore(p) => self.nullable(p),
+            _ => false,
+        }
+    }
+
+    fn text_deriv(&self, p: &Rc<Pattern>, s: &str) -> Rc<Pattern> {
+        match &**self.deref(p) {
+            Choice(a, b) => choice(self.text_deriv(a, s), self.text_deriv(b, s)),
+            Interleave(a, b) => choice(
+                interleave(self.text_deriv(a, s), b.clone()),
+                interleave(a.clone(), self.text_deriv(b, s)),
+            ),
+            Group(a, b) => {
+                let p = group(self.text_deriv(a, s), b.clone());
+                match self.nullable(a) {
+                    true => choice(p, self.text_deriv(b, s)),
+                    false => p,
+                }
+            }
+            After(a, b) => after(self.text_deriv(a, s), b.clone()),
+            OneOrMore(p) => group(
+                self.text_deriv(p, s),
+                choice(one_or_more(p.clone()), empty()),
+            ),
+            Text => self.deref(p).clone(),
+            Value {
+                library,
+                name,
+                value,
+            } => match self.libraries[library].equal(name, value, s) {
+                true => empty(),
+                false => not_allowed(),
+            },
+            Data {
+                library,
+                name,
+                params,
+                except,
+            } => {
+                let allowed = self.libraries[library].allows(name, params, s)
+                    && !except
+                        .as_ref()
+                        .is_some_and(|v| self.nullable(&self.text_deriv(v, s)));
+                match allowed {
+                    true => empty(),
+                    false => not_allowed(),
+                }
+            }
+            List(p) => {
+                let p = s
+                    .split_whitespace()
+                    .fold(p.clone(), |p, word| self.text_deriv(&p, word));
+                match self.nullable(&p) {
+                    true => empty(),
+                    false => not_allowed(),
+                }
+            }
+            _ => not_allowed(),
+        }
+    }
+
+    fn apply_after(&self, p: &Rc<Pattern>, f: &dyn Fn(Rc<Pattern>) -> Rc<Pattern>) -> Rc<Pattern> {
+        match &**p {
+            After(a, b) => after(a.clone(), f(b.clone())),
+            Choice(a, b) => choice(self.apply_after(a, f), self.apply_after(b, f)),
+            _ => not_allowed(),
+        }
+    }
+
+    fn start_tag_open_deriv(&self, p: &Rc<Pattern>, name: &str) -> Rc<Pattern> {
+        match &**self.deref(p) {
+            Choice(a, b) => choice(
+                self.start_tag_open_deriv(a, name),
+                self.start_tag_open_deriv(b, name),
+            ),
+            Element(nc, p) if nc.contains(name) => after(p.clone(), empty()),
+            Interleave(a, b) => choice(
+                self.apply_after(&self.start_tag_open_deriv(a, name), &|v| {
+                    interleave(v, b.clone())
+                }),
+                self.apply_after(&self.start_tag_open_deriv(b, name), &|v| {
+                    interleave(a.clone(), v)
+                }),
+            ),
+            OneOrMore(p) => self.apply_after(&self.start_tag_open_deriv(p, name), &|v| {
+                group(v, choice(one_or_more(p.clone()), empty()))
+            }),
+            Group(a, b) => {
+                let x = self.apply_after(&self.start_tag_open_deriv(a, name), &|v| {
+                    group(v, b.clone())
+                });
+                match self.nullable(a) {
+                    true => choice(x, self.start_tag_open_deriv(b, name)),
+                    false => x,
+                }
+            }
+            After(a, b) => self.apply_after(&self.start_tag_open_deriv(a, name), &|v| {
+                after(v, b.clone())
+            }),
+            _ => not_allowed(),
+        }
+    }
+
+    fn att_deriv(&self, p: &Rc<Pattern>, name: &str, value: &str) -> Rc<Pattern> {
+        match &**self.deref(p) {
+            After(a, b) => after(self.att_deriv(a, name, value), b.clone()),
+            Choice(a, b) => choice(
+                self.att_deriv(a, name, value),
+                self.att_deriv(b, name, value),
+            ),
+            Group(a, b) => choice(
+                group(self.att_deriv(a, name, value), b.clone()),
+                group(a.clone(), self.att_deriv(b, name, value)),
+            ),
+            Interleave(a, b) => choice(
+                interleave(self.att_deriv(a, name, value), b.clone()),
+                interleave(a.clone(), self.att_deriv(b, name, value)),
+            ),
+            OneOrMore(p) => group(
+                self.att_deriv(p, name, value),
+                choice(one_or_more(p.clone()), empty()),
+            ),
+            Attribute(nc, p) if nc.contains(name) && self.value_match(p, value) => empty(),
+            _ => not_allowed(),
+        }
+    }
+
+    fn value_match(&self, p: &Rc<Pattern>, value: &str) -> bool {
+        (self.nullable(p) && value.trim().is_empty()) || self.nullable(&self.text_deriv(p, value))
+    }
+
+    /// Returns true if an attribute pattern with the name is allowed by the pattern
+    fn accepts_attribute(&self, p: &Rc<Pattern>, name: &str) -> bool {
+        match &**self.deref(p) {
+            After(a, _) | OneOrMore(a) => self.accepts_attribute(a, name),
+            Choice(a, b) | Group(a, b) | Interleave(a, b) => {
+                self.accepts_attribute(a, name) || self.accepts_attribute(b, name)
+            }
+            Attribute(nc, _) => nc.contains(name),
+            _ => false,
+        }
+    }
+
+    /// Collects the names of all attributes which are left unmatched in the pattern
+    fn attributes(&self, p: &Rc<Pattern>, names: &mut Vec<String>) {
+        match &**self.deref(p) {
+            After(a, _) | OneOrMore(a) => self.attributes(a, names),
+            Choice(a, b) | Group(a, b) | Interleave(a, b) => {
+                self.attributes(a, names);
+                self.attributes(b, names);
+            }
+            Attribute(nc, _) => nc.describe(names),
+            _ => {}
+        }
+    }
+
+    /// Closes the start tag, which disallows any remaining attributes
+    /// When recovering, missing attributes are treated as present instead
+    fn start_tag_close_deriv(&self, p: &Rc<Pattern>, recover: bool) -> Rc<Pattern> {
+        let close = |p| self.start_tag_close_deriv(p, recover);
+        match &**self.deref(p) {
+            After(a, b) => after(close(a), b.clone()),
+            Choice(a, b) => choice(close(a), close(b)),
+            Group(a, b) => group(close(a), close(b)),
+            Interleave(a, b) => interleave(close(a), close(b)),
+            OneOrMore(p) => one_or_more(close(p)),
+            Attribute(_, _) if recover => empty(),
+            Attribute(_, _) => not_allowed(),
+            _ => self.deref(p).clone(),
+        }
+    }
+
+    fn end_tag_deriv(&self, p: &Rc<Pattern>) -> Rc<Pattern> {
+        match &**p {
+            Choice(a, b) => choice(self.end_tag_deriv(a), self.end_tag_deriv(b)),
+            After(a, b) if self.nullable(a) => b.clone(),
+            _ => not_allowed(),
+        }
+    }
+
+    /// Assumes the content of the current element is valid and returns what follows it
+    fn skip_content(&self, p: &Rc<Pattern>) -> Rc<Pattern> {
+        match &**p {
+            Choice(a, b) => choice(self.skip_content(a), self.skip_content(b)),
+            After(_, b) => b.clone(),
+            _ => not_allowed(),
+        }
+    }
+
+    /// Returns the names of the elements which can start next
+    fn expected(&self, p: &Rc<Pattern>) -> Vec<String> {
+        fn walk(schema: &Schema, p: &Rc<Pattern>, names: &mut Vec<String>) {
+            match &**schema.deref(p) {
+                Choice(a, b) | Interleave(a, b) => {
+                    walk(schema, a, names);
+                    walk(schema, b, names);
+                }
+                Group(a, b) => {
+                    walk(schema, a, names);
+                    if schema.nullable(a) {
+                        walk(schema, b, names);
+                    }
+                }
+                OneOrMore(p) | After(p, _) => walk(schema, p, names),
+                Element(nc, _) => nc.describe(names),
+                _ => {}
+            }
+        }
+
+        let mut names = Vec::new();
+        walk(self, p, &mut names);
+        names
+    }
+
+    /// Derives the pattern by a whole element and reports all violations inside it
+    /// Returns NotAllowed if the element itself is not allowed
+    fn element_deriv(
+        &self,
+        p: &Rc<Pattern>,
+        node: &Node,
+        path: &str,
+        violations: &mut Vec<Violation>,
+    ) -> Rc<Pattern> {
+        let mut report = |kind| {
+            violations.push(Violation {
+                path: path.to_owned(),
+                kind,
+            })
+        };
+
+        let opened = self.start_tag_open_deriv(p, &node.tag);
+        if matches!(*opened, NotAllowed) {
+            report(ViolationKind::UnexpectedElement {
+                found: node.tag.clone(),
+                expected: self.expected(p),
+            });
+            return opened;
+        }
+
+        let mut attributes = node
+            .attributes
+            .iter()
+            .filter(|(k, _)| *k != "xmlns" && !k.starts_with("xmlns:"))
+            .collect::<Vec<_>>();
+        attributes.sort();
+
+        let mut d = opened.clone();
+        for (name, value) in attributes {
+            let next = self.att_deriv(&d, name, value);
+            match *next {
+                NotAllowed if self.accepts_attribute(&d, name) => {
+                    report(ViolationKind::InvalidAttributeValue {
+                        name: name.clone(),
+                        value: value.clone(),
+                    })
+                }
+                NotAllowed => report(ViolationKind::UnexpectedAttribute(name.clone())),
+                _ => d = next,
+            }
+        }
+
+        let closed = self.start_tag_close_deriv(&d, false);
+        let mut d = match *closed {
+            NotAllowed => {
+                let mut missing = Vec::new();
+                self.attributes(&d, &mut missing);
+                for name in missing {
+                    report(ViolationKind::MissingAttribute(name));
+                }
+                self.start_tag_close_deriv(&d, true)
+            }
+            _ => closed,
+        };
+
+        let text = node.content.trim();
+        let has_text = !text.is_empty();
+        // Invalid text is already reported and would otherwise leave the content incomplete
+        let mut invalid_text = false;
+        if node.child_count() == 0 {
+            // A single text node must match exactly, including the empty string
+            let next = self.text_deriv(&d, &node.content);
+            d = match *next {
+                NotAllowed if has_text => {
+                    report(ViolationKind::InvalidText(node.content.clone()));
+                    invalid_text = true;
+                    d
+                }
+                NotAllowed => d,
+                _ if has_text => next,
+                _ => choice(d, next),
+            };
+        } else if has_text {
+            let next = self.text_deriv(&d, &node.content);
+            d = match *next {
+                NotAllowed => {
+                    report(ViolationKind::InvalidText(node.content.clone()));
+                    invalid_text = true;
+                    d
+                }
+                _ => next,
+            };
+        }
+
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for child in node.children() {
+            let index = counts.entry(&child.tag).or_default();
+            *index += 1;
+            let child_path = format!("{}/{}[{}]", path, child.tag, index);
+
+            let next = self.element_deriv(&d, child, &child_path, violations);
+            // Skip invalid elements and continue validating the siblings
+            if !matches!(*next, NotAllowed) {
+                d = next;
+            }
+        }
+
+        let ended = self.end_tag_deriv(&d);
+        match *ended {
+            NotAllowed if invalid_text => self.skip_content(&opened),
+            NotAllowed => {
+                violations.push(Violation {
+                    path: path.to_owned(),
+                    kind: ViolationKind::IncompleteContent(self.expected(&d)),
+                });
+                self.skip_content(&opened)
+            }
+            _ => ended,
+        }
+    }
+}
+
+/// Converts the xml syntax into patterns
+struct Loader {
+    /// Maps names of definitions to their index
+    names: HashMap<String, usize>,
+    defines: Vec<Option<Rc<Pattern>>>,
+}
+
+impl Loader {
+    fn reference(&mut self, name: &str) -> usize {
+        let len = self.names.len();
+        let index = *self.names.entry(name.to_owned()).or_insert(len);
+        if index == self.defines.len() {
+            self.defines.push(None);
+        }
+        index
+    }
+
+    fn grammar(
+        &mut self,
+        node: &Node,
+        library: &str,
+        start: &mut Option<Rc<Pattern>>,
+    ) -> Result<(), Error> {
+        let library = node
+            .attributes
+            .get("datatypeLibrary")
+            .map(|v| v.as_str())
+            .unwrap_or(library);
+
+        for child in node.children() {
+            match local(&child.tag) {
+                "start" => {
+                    let p = self.group(child, library)?;
+                    *start = Some(combine(start.take(), p, child)?);
+                }
+                "define" => {
+                    let name = child
+                        .attributes
+                        .get("name")
+                        .ok_or_else(|| invalid("Missing name of define".into()))?;
+                    let index = self.reference(name);
+                    let p = self.group(child, library)?;
+                    self.defines[index] = Some(combine(self.defines[index].take(), p, child)?);
+                }
+                "div" => self.grammar(child, library, start)?,
+                tag => return Err(invalid(format!("Unsupported grammar content {:?}", tag))),
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads all pattern children of a node as a group
+    fn group(&mut self, node: &Node, library: &str) -> Result<Rc<Pattern>, Error> {
+        self.children(node, library, group)
+    }
+
+    fn children(
+        &mut self,
+        node: &Node,
+        library: &str,
+        f: fn(Rc<Pattern>, Rc<Pattern>) -> Rc<Pattern>,
+    ) -> Result<Rc<Pattern>, Error> {
+        let library = node
+            .attributes
+            .get("datatypeLibrary")
+            .map(|v| v.as_str())
+            .unwrap_or(library);
+
+        let mut result: Option<Rc<Pattern>> = None;
+        for child in node.children() {
+            let p = self.pattern(child, library)?;
+            result = Some(match result {
+                Some(v) => f(v, p),
+                None => p,
+            });
+        }
+        result.ok_or_else(|| invalid(format!("Missing pattern in {:?}", node.tag)))
+    }
+
+    fn name_class(&self, node: &Node) -> Result<NameClass, Error> {
+        let except = |node: &Node| -> Result<Option<Box<NameClass>>, Error> {
+            match node.children().find(|v| local(&v.tag) == "except") {
+                Some(except) => Ok(Some(Box::new(self.name_classes(except)?))),
+                None => Ok(None),
+            }
+        };
+
+        match local(&node.tag) {
+            "name" => Ok(NameClass::Name(local(node.content.trim()).to_owned())),
+            // Namespaces are not resolved so nsName matches any name
+            "anyName" | "nsName" => Ok(NameClass::AnyName(except(node)?)),
+            "choice" => self.name_classes(node),
+            tag => Err(invalid(format!("Invalid name class {:?}", tag))),
+        }
+    }
+
+    fn name_classes(&self, node: &Node) -> Result<NameClass, Error> {
+        node.children()
+            .map(|v| self.name_class(v))
+            .reduce(|a, b| Ok(NameClass::Choice(Box::new(a?), Box::new(b?))))
+            .unwrap_or_else(|| Err(invalid(format!("Missing name class in {:?}", node.tag))))
+    }
+
+    /// Reads the name class of an element or attribute and the remaining pattern children
+    fn named(
+        &mut self,
+        node: &Node,
+        library: &str,
+        default: Rc<Pattern>,
+    ) -> Result<(NameClass, Rc<Pattern>), Error> {
+        let library = node
+            .attributes
+            .get("datatypeLibrary")
+            .map(|v| v.as_str())
+            .unwrap_or(library);
+
+        let mut children = node.children();
+        let nc = match node.attributes.get("name") {
+            Some(name) => NameClass::Name(local(name).to_owned()),
+            None => {
+                let first = children
+                    .next()
+                    .ok_or_else(|| invalid(format!("Missing name of {:?}", node.tag)))?;
+                self.name_class(first)?
+            }
+        };
+
+        let mut p: Option<Rc<Pattern>> = None;
+        for child in children {
+            let v = self.pattern(child, library)?;
+            p = Some(match p {
+                Some(p) => group(p, v),
+                None => v,
+            });
+        }
+
+        Ok((nc, p.unwrap_or(default)))
+    }
+
+    fn pattern(&mut self, node: &Node, library: &str) -> Result<Rc<Pattern>, Error> {
+        let library = node
+            .attributes
+            .get("datatypeLibrary")
+            .map(|v| v.as_str())
+            .unwrap_or(library);
+
+        let p = match local(&node.tag) {
+            "element" => {
+                let (nc, p) = self.named(node, library, empty())?;
+                Rc::new(Element(nc, p))
+            }
+            "attribute" => {
+                let (nc, p) = self.named(node, library, Rc::new(Text))?;
+                Rc::new(Attribute(nc, p))
+            }
+            "group" => self.group(node, library)?,
+            "interleave" => self.children(node, library, interleave)?,
+            "choice" => self.children(node, library, choice)?,
+            "optional" => choice(self.group(node, library)?, empty()),
+            "zeroOrMore" => choice(one_or_more(self.group(node, library)?), empty()),
+            "oneOrMore" => one_or_more(self.group(node, library)?),
+            "mixed" => interleave(self.group(node, library)?, Rc::new(Text)),
+            "list" => Rc::new(List(self.group(node, library)?)),
+            "empty" => empty(),
+            "text" => Rc::new(Text),
+            "notAllowed" => not_allowed(),
+            "ref" => {
+                let name = node
+                    .attributes
+                    .get("name")
+                    .ok_or_else(|| invalid("Missing name of ref".into()))?;
+                Rc::new(Ref(self.reference(name)))
+            }
+            "data" => {
+                let name = node
+                    .attributes
+                    .get("type")
+                    .ok_or_else(|| invalid("Missing type of data".into()))?;
+
+                let mut params = Vec::new();
+                let mut except = None;
+                for child in node.children() {
+                    match local(&child.tag) {
+                        "param" => params.push((
+                            child.attributes.get("name").cloned().unwrap_or_default(),
+                            child.content.clone(),
+                        )),
+                        "except" => except = Some(self.children(child, library, choice)?),
+                        tag => return Err(invalid(format!("Invalid data content {:?}", tag))),
+                    }
+                }
+
+                Rc::new(Data {
+                    library: library.to_owned(),
+                    name: local(name).to_owned(),
+                    params,
+                    except,
+                })
+            }
+            "value" => {
+                // Values without a type use the built in token type
+                let (library, name) = match node.attributes.get("type") {
+                    Some(v) => (library, local(v)),
+                    None => ("", "token"),
+                };
+                Rc::new(Value {
+                    library: library.to_owned(),
+                    name: name.to_owned(),
+                    value: node.content.clone(),
+                })
+            }
+            tag => return Err(invalid(format!("Unsupported pattern {:?}", tag))),
+        };
+
+        Ok(p)
+    }
+}
+
+/// Combines multiple definitions of the same name
+fn combine(
+    existing: Option<Rc<Pattern>>,
+    p: Rc<Pattern>,
+    node: &Node,
+) -> Result<Rc<Pattern>, Error> {
+    let existing = match existing {
+        Some(v) => v,
+        None => return Ok(p),
+    };
+
+    match node.attributes.get("combine").map(|v| v.as_str()) {
+        Some("choice") => Ok(choice(existing, p)),
+        Some("interleave") => Ok(interleave(existing, p)),
+        _ => Err(invalid(format!(
+            "Duplicate definition without combine in {:?}",
+            node.tag
+        ))),
+    }
+}
+
+/// Translation of the compact syntax into the xml syntax
+mod compact {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Token {
+        Ident(String),
+        /// prefix:local
+        CName(String, String),
+        /// prefix:*
+        NsName(String),
+        Literal(String),
+        Punct(&'static str),
+    }
+
+    const PUNCT: [&str; 17] = [
+        "|=", "&=", "=", "{", "}", "(", ")", "[", "]", ",", "|", "&", "?", "*", "+", "-", "~",
+    ];
+
+    fn tokenize(string: &str) -> Result<Vec<Token>, Error> {
+        let mut tokens = Vec::new();
+        let mut rest = string;
+        loop {
+            rest = rest.trim_start();
+            if rest.starts_with('#') {
+                rest = rest.find('\n').map(|i| &rest[i..]).unwrap_or("");
+                continue;
+            }
+
+            let c = match rest.chars().next() {
+                Some(c) => c,
+                None => return Ok(tokens),
+            };
+
+            if let Some(p) = PUNCT.iter().find(|p| rest.starts_with(**p)) {
+                tokens.push(Token::Punct(p));
+                rest = &rest[p.len()..];
+            } else if c == '"' || c == '\'' {
+                let (quote, len) = match rest.starts_with(&c.to_string().repeat(3)) {
+                    true => (c.to_string().repeat(3), 3),
+                    false => (c.to_string(), 1),
+                };
+                let end = rest[len..]
+                    .find(&quote)
+                    .ok_or_else(|| invalid("Unterminated literal".into()))?;
+                tokens.push(Token::Literal(rest[len..len + end].to_owned()));
+                rest = &rest[len + end + len..];
+            } else if crate::name::is_name_start_char(c) || c == '\\' {
+                let rest_name = rest.strip_prefix('\\').unwrap_or(rest);
+                let len = rest_name
+                    .find(|c| !crate::name::is_name_char(c) || c == ':')
+                    .unwrap_or(rest_name.len());
+                let name = rest_name[..len].to_owned();
+                rest = &rest_name[len..];
+
+                if let Some(after) = rest.strip_prefix(":*") {
+                    tokens.push(Token::NsName(name));
+                    rest = after;
+                } else if let Some(after) = rest.strip_prefix(':') {
+                    let len = after
+                        .find(|c| !crate::name::is_name_char(c) || c == ':')
+                        .unwrap_or(after.len());
+                    tokens.push(Token::CName(name, after[..len].to_owned()));
+                    rest = &after[len..];
+                } else {
+                    tokens.push(Token::Ident(name));
+                }
+            } else {
+                return Err(invalid(format!("Unexpected character {:?}", c)));
+            }
+        }
+    }
+
+    struct Parser {
+        tokens: Vec<Token>,
+        pos: usize,
+        datatypes: HashMap<String, String>,
+    }
+
+    /// Parses a schema in compact syntax into the equivalent xml syntax
+    pub(super) fn parse(string: &str) -> Result<Node, Error> {
+        let mut parser = Parser {
+            tokens: tokenize(string)?,
+            pos: 0,
+            datatypes: HashMap::new(),
+        };
+        parser.datatypes.insert("xsd".into(), XSD_DATATYPES.into());
+
+        parser.declarations()?;
+
+        let is_grammar = match (parser.peek(), parser.tokens.get(parser.pos + 1)) {
+            (Some(Token::Ident(v)), _) if v == "start" || v == "div" || v == "include" => true,
+            (Some(Token::Ident(_)), Some(Token::Punct(p))) => matches!(*p, "=" | "|=" | "&="),
+            (None, _) => true,
+            _ => false,
+        };
+
+        let root = match is_grammar {
+            true => parser.grammar(NodeBuilder::new("grammar"))?.build(),
+            false => parser.pattern()?,
+        };
+
+        match parser.peek() {
+            None => Ok(root),
+            Some(v) => Err(invalid(format!("Unexpected {:?}", v))),
+        }
+    }
+
+    impl Parser {
+        fn peek(&self) -> Option<&Token> {
+            self.tokens.get(self.pos)
+        }
+
+        fn next(&mut self) -> Option<Token> {
+            let token = self.tokens.get(self.pos).cloned();
+            self.pos += 1;
+            token
+        }
+
+        fn eat(&mut self, punct: &str) -> bool {
+            match self.peek() {
+                Some(Token::Punct(p)) if *p == punct => {
+                    self.pos += 1;
+                    true
+                }
+                _ => false,
+            }
+        }
+
+        fn expect(&mut self, punct: &str) -> Result<(), Error> {
+            match self.eat(punct) {
+                true => Ok(()),
+                false => Err(invalid(format!(
+                    "Expected {:?}, found {:?}",
+                    punct,
+                    self.peek()
+                ))),
+            }
+        }
+
+        fn is_keyword(&self, keyword: &str) -> bool {
+            matches!(self.peek(), Some(Token::Ident(v)) if v == keyword)
+        }
+
+        fn literal(&mut self) -> Result<String, Error> {
+            let mut value = match self.next() {
+                Some(Token::Literal(v)) => v,
+                v => return Err(invalid(format!("Expected literal, found {:?}", v))),
+            };
+            while self.eat("~") {
+                match self.next() {
+                    Some(Token::Literal(v)) => value.push_str(&v),
+                    v => return Err(invalid(format!("Expected literal, found {:?}", v))),
+                }
+            }
+            Ok(value)
+        }
+
+        /// Skips annotations in square brackets
+        fn annotations(&mut self) {
+            while self.eat("[") {
+                let mut depth = 1;
+                while depth > 0 {
+                    match self.next() {
+                        Some(Token::Punct("[")) => depth += 1,
+                        Some(Token::Punct("]")) => depth -= 1,
+                        None => return,
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        /// Reads namespace and datatypes declarations
+        fn declarations(&mut self) -> Result<(), Error> {
+            loop {
+                self.annotations();
+                if self.is_keyword("default") {
+                    self.pos += 1;
+                }
+
+                if self.is_keyword("namespace") {
+                    self.pos += 1;
+                    // Namespaces are not resolved
+                    if let Some(Token::Ident(_)) = self.peek() {
+                        self.pos += 1;
+                    }
+                    self.expect("=")?;
+                    if !self.is_keyword("inherit") {
+                        self.literal()?;
+                    } else {
+                        self.pos += 1;
+                    }
+                } else if self.is_keyword("datatypes") {
+                    self.pos += 1;
+                    let prefix = match self.next() {
+                        Some(Token::Ident(v)) => v,
+                        v => return Err(invalid(format!("Expected prefix, found {:?}", v))),
+                    };
+                    self.expect("=")?;
+                    let uri = self.literal()?;
+                    self.datatypes.insert(prefix, uri);
+                } else {
+                    return Ok(());
+                }
+            }
+        }
+
+        fn grammar(&mut self, mut grammar: NodeBuilder) -> Result<NodeBuilder, Error> {
+            loop {
+                self.annotations();
+                let name = match self.peek() {
+                    Some(Token::Ident(v)) => v.clone(),
+                    _ => return Ok(grammar),
+                };
+                self.pos += 1;
+
+                match name.as_str() {
+                    "div" => {
+                        self.expect("{")?;
+                        let div = self.grammar(NodeBuilder::new("div"))?;
+                        self.expect("}")?;
+                        grammar = grammar.child(div);
+                    }
+                    "include" => return Err(invalid("Includes are not supported".into())),
+                    _ => {
+                        let combine = if self.eat("|=") {
+                            Some("choice")
+                        } else if self.eat("&=") {
+                            Some("interleave")
+                        } else {
+                            self.expect("=")?;
+                            None
+                        };
+
+                        let mut define = match name.as_str() {
+                            "start" => NodeBuilder::new("start"),
+                            _ => NodeBuilder::new("define").attr("name", &name),
+                        };
+                        if let Some(combine) = combine {
+                            define = define.attr("combine", combine);
+                        }
+                        grammar = grammar.child(define.child(self.pattern()?));
+                    }
+                }
+            }
+        }
+
+        fn pattern(&mut self) -> Result<Node, Error> {
+            let first = self.particle()?;
+            let op = match self.peek() {
+                Some(Token::Punct(p)) if matches!(*p, "," | "|" | "&") => *p,
+                _ => return Ok(first),
+            };
+
+            let mut items = vec![first];
+            while self.eat(op) {
+                items.push(self.particle()?);
+            }
+
+            if let Some(Token::Punct(p)) = self.peek() {
+                if matches!(*p, "," | "|" | "&") {
+                    return Err(invalid("Mixed operators require parentheses".into()));
+                }
+            }
+
+            let tag = match op {
+                "," => "group",
+                "|" => "choice",
+                _ => "interleave",
+            };
+            Ok(NodeBuilder::new(tag).children(items).build())
+        }
+
+        fn particle(&mut self) -> Result<Node, Error> {
+            let p = self.primary()?;
+            let tag = if self.eat("?") {
+                "optional"
+            } else if self.eat("*") {
+                "zeroOrMore"
+            } else if self.eat("+") {
+                "oneOrMore"
+            } else {
+                return Ok(p);
+            };
+            Ok(NodeBuilder::new(tag).child(p).build())
+        }
+
+        fn block(&mut self) -> Result<Node, Error> {
+            self.expect("{")?;
+            let p = self.pattern()?;
+            self.expect("}")?;
+            Ok(p)
+        }
+
+        fn primary(&mut self) -> Result<Node, Error> {
+            self.annotations();
+            let token = self
+                .next()
+                .ok_or_else(|| invalid("Unexpected end of schema".into()))?;
+
+            let node = match token {
+                Token::Punct("(") => {
+                    let p = self.pattern()?;
+                    self.expect(")")?;
+                    p
+                }
+                Token::Literal(_) => {
+                    self.pos -= 1;
+                    NodeBuilder::new("value").text(self.literal()?).build()
+                }
+                Token::Ident(v) => match v.as_str() {
+                    "element" | "attribute" => {
+                        let nc = self.name_class()?;
+                        NodeBuilder::new(&v).child(nc).child(self.block()?).build()
+                    }
+                    "mixed" | "list" => NodeBuilder::new(&v).child(self.block()?).build(),
+                    "empty" | "text" | "notAllowed" => NodeBuilder::new(&v).build(),
+                    "string" | "token" => self.datatype("", &v)?,
+                    "parent" | "grammar" | "external" => {
+                        return Err(invalid(format!("{:?} is not supported", v)))
+                    }
+                    _ => NodeBuilder::new("ref").attr("name", v).build(),
+                },
+                Token::CName(prefix, local) => {
+                    let library = self.datatypes.get(&prefix).cloned().ok_or_else(|| {
+                        invalid(format!("Undeclared datatypes prefix {:?}", prefix))
+                    })?;
+                    self.datatype(&library, &local)?
+                }
+                v => return Err(invalid(format!("Unexpected {:?}", v))),
+            };
+
+            Ok(node)
+        }
+
+        /// Reads a data or value pattern after the datatype name
+        fn datatype(&mut self, library: &str, name: &str) -> Result<Node, Error> {
+            if let Some(Token::Literal(_)) = self.peek() {
+                return Ok(NodeBuilder::new("value")
+                    .attr("datatypeLibrary", library)
+                    .attr("type", name)
+                    .text(self.literal()?)
+                    .build());
+            }
+
+            let mut data = NodeBuilder::new("data")
+                .attr("datatypeLibrary", library)
+                .attr("type", name);
+
+            if self.eat("{") {
+                while !self.eat("}") {
+                    let param = match self.next() {
+                        Some(Token::Ident(v)) => v,
+                        v => return Err(invalid(format!("Expected parameter, found {:?}", v))),
+                    };
+                    self.expect("=")?;
+                    data = data.child(
+                        NodeBuilder::new("param")
+                            .attr("name", param)
+                            .text(self.literal()?),
+                    );
+                }
+            }
+
+            if self.eat("-") {
+                data = data.child(NodeBuilder::new("except").child(self.primary()?));
+            }
+
+            Ok(data.build())
+        }
+
+        fn name_class(&mut self) -> Result<Node, Error> {
+            let first = self.name_class_primary()?;
+            if !matches!(self.peek(), Some(Token::Punct("|"))) {
+                return Ok(first);
+            }
+
+            let mut choice = NodeBuilder::new("choice").child(first);
+            while self.eat("|") {
+                choice = choice.child(self.name_class_primary()?);
+            }
+            Ok(choice.build())
+        }
+
+        fn name_class_primary(&mut self) -> Result<Node, Error> {
+            let token = self
+                .next()
+                .ok_or_else(|| invalid("Unexpected end of schema".into()))?;
+
+            let (tag, name) = match token {
+                Token::Punct("(") => {
+                    let nc = self.name_class()?;
+                    self.expect(")")?;
+                    return Ok(nc);
+                }
+                Token::Ident(v) => return Ok(NodeBuilder::new("name").text(v).build()),
+                Token::CName(_, local) => return Ok(NodeBuilder::new("name").text(local).build()),
+                Token::Punct("*") => ("anyName", None::<String>),
+                Token::NsName(prefix) => ("nsName", Some(prefix)),
+                v => return Err(invalid(format!("Expected name class, found {:?}", v))),
+            };
+
+            let mut nc = NodeBuilder::new(tag);
+            if let Some(prefix) = name {
+                nc = nc.attr("ns", prefix);
+            }
+            if self.eat("-") {
+                nc = nc.child(NodeBuilder::new("except").child(self.name_class_primary()?));
+            }
+            Ok(nc.build())
+        }
+    }
+}
@@ -39,7 +39,7 @@ where
                 if non_del && !self.in_quotes {
                     let end = &self.data[..i];
                     // println!("Iter end: '{}', i: {}", end, i);
-                    self.data = &self.data[i + 1..];
+                    self.data = &self.data[i + c.len_utf8()..];
                     // println!("data: '{}'",&self.data[i..]);
                     return Some(end);
                 }
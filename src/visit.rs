@@ -0,0 +1,305 @@
+//! This is a module providing traversal of whole trees, with `Visitor` for reading a tree through
+//! `Node::walk`, `Fold` for rebuilding a tree with nodes replaced, dropped or rewritten through
+//! `Node::fold`, and the iterators returned by `Node::descendants`, `Node::bfs` and
+//! `Node::descendants_with_depth`
+//! All of them traverse with an explicit stack or queue, so deeply nested trees do not overflow the
+//! stack. Text is visited between the children of an element where its position is known, and after
+//! them otherwise, matching how nodes are serialized
+//! Comments are not kept by the parser and have no place in the tree, so there is no comment callback
+//! ```
+//! use simple_xml::{Fold, Node};
+//!
+//! /// Moves every texture into the assets directory
+//! struct Assets;
+//!
+//! impl Fold for Assets {
+//!     fn fold_attribute(&mut self, tag: &str, name: &str, value: String) -> Option<String> {
+//!         match (tag, name) {
+//!             ("texture", "src") => Some(format!("assets/{}", value)),
+//!             _ => Some(value),
+//!         }
+//!     }
+//!
+//!     fn fold_element(&mut self, node: Node) -> Option<Node> {
+//!         // Drop unused textures
+//!         match node.get_attribute("unused") {
+//!             Some(_) => None,
+//!             None => Some(node),
+//!         }
+//!     }
+//! }
+//!
+//! let root = simple_xml::from_string(
+//!     r#"<model><texture src="wood.png"/><texture src="old.png" unused="1"/></model>"#,
+//! )
+//! .unwrap();
+//! let root = root.fold(&mut Assets).unwrap();
+//! assert_eq!(root.to_string(), r#"<model><texture src="assets/wood.png"/></model>"#);
+//! ```
+
+use std::collections::VecDeque;
+
+use crate::path::{self, Location};
+use crate::Node;
+
+/// Controls how a walk continues after entering an element
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Walk {
+    /// Visit the children and content of the element
+    #[default]
+    Continue,
+    /// Skip the children and content of the element, its leave callback is still called
+    SkipChildren,
+    /// End the walk without calling any further callbacks
+    Stop,
+}
+
+/// Callbacks for the parts of a tree, called by `Node::walk` in document order
+/// All methods do nothing by default, so only the needed ones have to be implemented
+pub trait Visitor {
+    /// Called for an element before its children and content
+    fn enter_element(&mut self, _node: &Node) -> Walk {
+        Walk::Continue
+    }
+
+    /// Called for an element after its children and content
+    fn leave_element(&mut self, _node: &Node) {}
+
+    /// Called with each non-empty run of text of an element in document order, as raw text with
+    /// references unexpanded
+    fn text(&mut self, _node: &Node, _text: &str) {}
+}
+
+/// Rewrites a tree from the bottom up, called by `Node::fold`
+/// The attributes and text of an element are folded after its children, followed by the element
+/// itself. All methods keep their input by default
+pub trait Fold {
+    /// Returns the element replacing a folded element, or None to drop it
+    fn fold_element(&mut self, node: Node) -> Option<Node> {
+        Some(node)
+    }
+
+    /// Returns the new value of an attribute, or None to remove it
+    fn fold_attribute(&mut self, _tag: &str, _name: &str, value: String) -> Option<String> {
+        Some(value)
+    }
+
+    /// Returns the new raw text of a non-empty run of text of an element
+    /// The text before a dropped child element is joined with the text after it into a single run
+    fn fold_text(&mut self, _tag: &str, text: String) -> String {
+        text
+    }
+}
+
+enum Step<'a> {
+    Enter(&'a Node),
+    Text(&'a Node, &'a str),
+    Leave(&'a Node),
+}
+
+pub(crate) fn walk<V: Visitor + ?Sized>(root: &Node, visitor: &mut V) {
+    let mut stack = vec![Step::Enter(root)];
+    while let Some(step) = stack.pop() {
+        let node = match step {
+            Step::Enter(node) => node,
+            Step::Text(node, text) => {
+                visitor.text(node, text);
+                continue;
+            }
+            Step::Leave(node) => {
+                visitor.leave_element(node);
+                continue;
+            }
+        };
+
+        match visitor.enter_element(node) {
+            Walk::Continue => {
+                stack.push(Step::Leave(node));
+                let start = stack.len();
+                let texts = node.texts();
+                for (text, child) in texts.iter().zip(node.children()) {
+                    if !text.is_empty() {
+                        stack.push(Step::Text(node, text));
+                    }
+                    stack.push(Step::Enter(child));
+                }
+                if let Some(text) = texts.last().filter(|v| !v.is_empty()) {
+                    stack.push(Step::Text(node, text));
+                }
+                stack[start..].reverse();
+            }
+            Walk::SkipChildren => visitor.leave_element(node),
+            Walk::Stop => return,
+        }
+    }
+}
+
+/// An element being rebuilt by `fold`
+struct Folding {
+    node: Node,
+    children: std::vec::IntoIter<Node>,
+    /// The text before each child and after the last one
+    texts: std::vec::IntoIter<String>,
+    /// The text runs around the folded children kept so far
+    runs: Vec<String>,
+}
+
+impl Folding {
+    fn new(mut node: Node) -> Self {
+        let mut texts: Vec<String> = node.texts().into_iter().map(str::to_owned).collect();
+        let first = texts.remove(0);
+        let children = node.take_children().into_iter();
+        Folding {
+            node,
+            children,
+            texts: texts.into_iter(),
+            runs: vec![first],
+        }
+    }
+
+    /// Adds a folded child, where the text before a dropped child joins the text after it
+    fn add(&mut self, folded: Option<Node>) {
+        let text = self.texts.next().unwrap_or_default();
+        match folded {
+            Some(folded) => {
+                self.node.add_node(folded);
+                self.runs.push(text);
+            }
+            None => self
+                .runs
+                .last_mut()
+                .expect("Missing text run")
+                .push_str(&text),
+        }
+    }
+}
+
+pub(crate) fn fold<F: Fold + ?Sized>(root: Node, folder: &mut F) -> Option<Node> {
+    // Elements are rebuilt on a stack, each with the children still to be folded
+    let mut stack = vec![Folding::new(root)];
+    loop {
+        let folding = stack.last_mut()?;
+        if let Some(child) = folding.children.next() {
+            stack.push(Folding::new(child));
+            continue;
+        }
+
+        let Folding { mut node, runs, .. } = stack.pop()?;
+        let attributes = std::mem::take(&mut node.attributes);
+        node.attributes = attributes
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let value = folder.fold_attribute(&node.tag, &name, value)?;
+                Some((name, value))
+            })
+            .collect();
+        let runs: Vec<String> = runs
+            .into_iter()
+            .map(|run| match run.is_empty() {
+                true => run,
+                false => folder.fold_text(&node.tag, run),
+            })
+            .collect();
+        node.set_texts(&runs);
+
+        let folded = folder.fold_element(node);
+        match stack.last_mut() {
+            Some(parent) => parent.add(folded),
+            None => return folded,
+        }
+    }
+}
+
+/// An iterator over the descendants of a node in document order, see `Node::descendants`
+#[derive(Debug, Clone)]
+pub struct Descendants<'a> {
+    stack: Vec<&'a Node>,
+}
+
+impl<'a> Descendants<'a> {
+    pub(crate) fn new(root: &'a Node) -> Self {
+        let mut stack: Vec<_> = root.children().collect();
+        stack.reverse();
+        Descendants { stack }
+    }
+}
+
+impl<'a> Iterator for Descendants<'a> {
+    type Item = &'a Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        let start = self.stack.len();
+        self.stack.extend(node.children());
+        self.stack[start..].reverse();
+        Some(node)
+    }
+}
+
+/// An iterator over the descendants of a node level by level, see `Node::bfs`
+#[derive(Debug, Clone)]
+pub struct Bfs<'a> {
+    queue: VecDeque<&'a Node>,
+}
+
+impl<'a> Bfs<'a> {
+    pub(crate) fn new(root: &'a Node) -> Self {
+        Bfs {
+            queue: root.children().collect(),
+        }
+    }
+}
+
+impl<'a> Iterator for Bfs<'a> {
+    type Item = &'a Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.queue.pop_front()?;
+        self.queue.extend(node.children());
+        Some(node)
+    }
+}
+
+/// An iterator over the descendants of a node in document order along with their depth and location,
+/// see `Node::descendants_with_depth`
+#[derive(Debug, Clone)]
+pub struct DescendantsWithDepth<'a> {
+    stack: Vec<(Location, &'a Node)>,
+}
+
+impl<'a> DescendantsWithDepth<'a> {
+    pub(crate) fn new(root: &'a Node) -> Self {
+        let mut stack = Vec::new();
+        push_children(&mut stack, root, &Location::default());
+        DescendantsWithDepth { stack }
+    }
+}
+
+impl<'a> Iterator for DescendantsWithDepth<'a> {
+    type Item = (usize, Location, &'a Node);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (location, node) = self.stack.pop()?;
+        push_children(&mut self.stack, node, &location);
+        Some((location.0.len(), location, node))
+    }
+}
+
+/// Returns a node and its descendants in document order along with their location below the node
+pub(crate) fn with_locations(root: &Node) -> impl Iterator<Item = (Location, &Node)> {
+    std::iter::once((Location::default(), root)).chain(
+        root.descendants_with_depth()
+            .map(|(_, location, node)| (location, node)),
+    )
+}
+
+/// Pushes the children of a node in reverse document order, so they are popped in document order
+fn push_children<'a>(stack: &mut Vec<(Location, &'a Node)>, node: &'a Node, location: &Location) {
+    let start = stack.len();
+    stack.extend(
+        path::children(node, location)
+            .into_iter()
+            .zip(node.children()),
+    );
+    stack[start..].reverse();
+}
@@ -0,0 +1,416 @@
+//! This is a module providing XInclude 1.0 processing, merging documents split across files with
+//! `<xi:include href="..."/>` elements into a single tree
+//! Relative references are resolved against the path of the including file. Included documents can
+//! be narrowed down with an `xpointer` attribute using the `element()` scheme or a shorthand id, and
+//! `parse="text"` includes a file as text. If a resource can not be read or the pointer selects
+//! nothing, the children of an `<xi:fallback>` element are used instead
+//! Files are read through the `Resolver` trait, so documents can be included from memory as well
+//! Nodes keep their text as a single content after their child elements, so included text is
+//! appended to the content of the parent. The base uri fixup adding `xml:base` attributes is not done
+//! ```
+//! use std::collections::HashMap;
+//! use std::path::PathBuf;
+//!
+//! let mut files = HashMap::new();
+//! files.insert(
+//!     PathBuf::from("config/main.xml"),
+//!     r#"<config xmlns:xi="http://www.w3.org/2001/XInclude">
+//!         <xi:include href="parts/server.xml"/>
+//!         <motd><xi:include href="parts/motd.txt" parse="text"/></motd>
+//!     </config>"#
+//!         .to_owned(),
+//! );
+//! files.insert(PathBuf::from("config/parts/server.xml"), r#"<server port="80"/>"#.to_owned());
+//! files.insert(PathBuf::from("config/parts/motd.txt"), "Hello & welcome".to_owned());
+//!
+//! let root = simple_xml::xinclude::load("config/main.xml", &files).unwrap();
+//! assert_eq!(root["server"][0].get_attribute("port").unwrap(), "80");
+//! assert_eq!(root["motd"][0].content, "Hello &amp; welcome");
+//! ```
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::path::{self, Location};
+use crate::{encoding, entity, Error, Node};
+
+/// The namespace of XInclude elements
+pub const XINCLUDE_NAMESPACE: &str = "http://www.w3.org/2001/XInclude";
+
+/// A source of included files
+pub trait Resolver {
+    /// Returns the contents of the file at a path
+    fn read(&self, path: &Path) -> io::Result<String>;
+}
+
+/// Reads included files from the file system
+/// Files are transcoded to UTF-8 from the encoding detected by `encoding::decode`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileResolver;
+
+impl Resolver for FileResolver {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        encoding::decode(&std::fs::read(path)?)
+            .map(|(string, _)| string)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// Reads included files from memory, keyed by their path
+impl Resolver for HashMap<PathBuf, String> {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        self.get(path).cloned().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} does not exist", path.display()),
+            )
+        })
+    }
+}
+
+/// Loads a file and replaces its include elements with the resources they reference
+/// Returns an Err of InclusionLoop if a document includes itself, directly or through other documents,
+/// or an Err of InvalidInclude if an include element is malformed or fails without a fallback
+pub fn load<P: AsRef<Path>, R: Resolver>(path: P, resolver: &R) -> Result<Node, Error> {
+    let path = normalize(path.as_ref());
+    let root = crate::from_string(&resolver.read(&path)?)?;
+    process(&root, &path, resolver)
+}
+
+/// Replaces the include elements of a tree with the resources they reference
+/// Relative references are resolved against the directory of path, the file the tree was loaded from
+pub fn process<R: Resolver>(root: &Node, path: &Path, resolver: &R) -> Result<Node, Error> {
+    let mut processor = Processor {
+        resolver,
+        including: Vec::new(),
+    };
+    let path = normalize(path);
+    processor.including.push((path.clone(), None));
+    processor.document(root, root, &path, &[])
+}
+
+/// An include element, with its attributes decoded
+struct Include {
+    href: String,
+    parse: String,
+    xpointer: Option<String>,
+    /// The fallback element and the XInclude prefixes in its scope
+    fallback: Option<(Node, Vec<String>)>,
+}
+
+struct Processor<'a, R> {
+    resolver: &'a R,
+    /// The resources being included, as pairs of path and pointer, to detect loops
+    including: Vec<(PathBuf, Option<String>)>,
+}
+
+impl<R: Resolver> Processor<'_, R> {
+    /// Returns an element of a document with its includes replaced, the document is kept unchanged
+    /// for references into itself
+    fn document(
+        &mut self,
+        element: &Node,
+        document: &Node,
+        path: &Path,
+        prefixes: &[String],
+    ) -> Result<Node, Error> {
+        // The element is wrapped as it can be an include element itself
+        let mut wrapper = crate::new("", String::new());
+        wrapper.add_node(element.clone());
+        self.expand(&mut wrapper, document, path, prefixes)?;
+        match (wrapper.child_count(), wrapper.content.trim().is_empty()) {
+            (1, true) => Ok(wrapper.remove_at(0)),
+            _ => Err(Error::InvalidInclude(format!(
+                "the root element of {} must be replaced by exactly one element",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Replaces the include elements below a node, which is in the scope of the given XInclude prefixes
+    fn expand(
+        &mut self,
+        node: &mut Node,
+        document: &Node,
+        path: &Path,
+        prefixes: &[String],
+    ) -> Result<(), Error> {
+        // The tree changes while includes are replaced, so the elements to expand are kept by location
+        let mut stack = vec![(Location(Vec::new()), prefixes.to_vec())];
+        while let Some((location, prefixes)) = stack.pop() {
+            let parent = location.get_mut(node).expect("Location was just found");
+            let mut descend = Vec::new();
+            let mut position = 0;
+            while position < parent.child_count() {
+                let child = child_at(parent, position);
+                let scope = scope(&prefixes, child);
+                let include = match include(child, &prefixes)? {
+                    Some(v) => v,
+                    None => {
+                        descend.push((position, scope));
+                        position += 1;
+                        continue;
+                    }
+                };
+
+                let replacement = self.include(&include, document, path)?;
+                let count = replacement.child_count();
+                parent.splice(position, replacement);
+                position += count;
+            }
+
+            // Children are visited once the positions of their siblings no longer change
+            let children = path::children(parent, &location);
+            for (position, scope) in descend.into_iter().rev() {
+                stack.push((children[position].clone(), scope));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a node with the processed nodes and text replacing an include element
+    fn include(&mut self, include: &Include, document: &Node, path: &Path) -> Result<Node, Error> {
+        let result = match include.parse.as_str() {
+            "xml" => self.include_xml(include, document, path),
+            "text" if include.xpointer.is_some() => {
+                return Err(Error::InvalidInclude(format!(
+                    "xpointer is not allowed when including {:?} as text",
+                    include.href
+                )))
+            }
+            "text" => self.include_text(include, path),
+            parse => {
+                return Err(Error::InvalidInclude(format!(
+                    "unsupported parse value {:?}",
+                    parse
+                )))
+            }
+        };
+
+        match (result, &include.fallback) {
+            // Resource errors are recovered from with the fallback
+            (Err(Error::IOError(_) | Error::InvalidInclude(_)), Some((fallback, scope))) => {
+                let mut fallback = fallback.clone();
+                self.expand(&mut fallback, document, path, scope)?;
+                Ok(fallback)
+            }
+            (result, _) => result,
+        }
+    }
+
+    fn include_text(&mut self, include: &Include, path: &Path) -> Result<Node, Error> {
+        let target = resolve(path, &include.href);
+        let text = self.resolver.read(&target)?;
+        Ok(crate::new("", entity::escape_text(&text)))
+    }
+
+    fn include_xml(
+        &mut self,
+        include: &Include,
+        document: &Node,
+        path: &Path,
+    ) -> Result<Node, Error> {
+        let target = match include.href.is_empty() {
+            true => path.to_path_buf(),
+            false => resolve(path, &include.href),
+        };
+        let key = (target.clone(), include.xpointer.clone());
+        if self.including.contains(&key) {
+            return Err(Error::InclusionLoop(match &include.xpointer {
+                Some(xpointer) => format!("{}#{}", target.display(), xpointer),
+                None => target.display().to_string(),
+            }));
+        }
+
+        let parsed;
+        let source = match include.href.is_empty() {
+            true => document,
+            false => {
+                parsed = crate::from_string(&self.resolver.read(&target)?)?;
+                &parsed
+            }
+        };
+        let location = match &include.xpointer {
+            Some(xpointer) => point(source, xpointer).ok_or_else(|| {
+                Error::InvalidInclude(format!(
+                    "xpointer {:?} selects nothing in {}",
+                    xpointer,
+                    target.display()
+                ))
+            })?,
+            None => Location(Vec::new()),
+        };
+        let selected = location.get(source).expect("Location was just found");
+
+        self.including.push(key);
+        let prefixes = ancestor_scope(source, &location);
+        let result = self.document(selected, source, &target, &prefixes);
+        self.including.pop();
+        let mut replacement = crate::new("", String::new());
+        replacement.add_node(result?);
+        Ok(replacement)
+    }
+}
+
+/// Returns the child at a position in document order
+fn child_at(node: &Node, position: usize) -> &Node {
+    let tag = &node.order[position];
+    let index = node.order[..position].iter().filter(|v| *v == tag).count();
+    &node.nodes[tag][index]
+}
+
+/// Returns the XInclude prefixes in scope of a node, where the default namespace is an empty prefix
+fn scope(prefixes: &[String], node: &Node) -> Vec<String> {
+    let mut scope = prefixes.to_vec();
+    for (key, value) in &node.attributes {
+        let prefix = match (key.as_str(), key.strip_prefix("xmlns:")) {
+            ("xmlns", _) => "",
+            (_, Some(prefix)) => prefix,
+            _ => continue,
+        };
+        scope.retain(|v| v != prefix);
+        if value == XINCLUDE_NAMESPACE {
+            scope.push(prefix.to_owned());
+        }
+    }
+    scope
+}
+
+/// Returns the local name of a node in the XInclude namespace
+fn local_name<'a>(node: &'a Node, prefixes: &[String]) -> Option<&'a str> {
+    let (prefix, name) = node.tag.split_once(':').unwrap_or(("", &node.tag));
+    prefixes.iter().any(|v| v == prefix).then_some(name)
+}
+
+/// Reads an include element, returning None for other elements
+fn include(node: &Node, prefixes: &[String]) -> Result<Option<Include>, Error> {
+    let prefixes = scope(prefixes, node);
+    if local_name(node, &prefixes) != Some("include") {
+        return Ok(None);
+    }
+
+    let attribute = |key: &str| node.get_attribute(key).map(|v| entity::decode(v));
+    let href = attribute("href").unwrap_or_default();
+    let xpointer = attribute("xpointer");
+    if href.is_empty() && xpointer.is_none() {
+        return Err(Error::InvalidInclude(
+            "an include needs an href or xpointer attribute".to_owned(),
+        ));
+    }
+    if href.contains('#') {
+        return Err(Error::InvalidInclude(format!(
+            "href {:?} must not contain a fragment identifier",
+            href
+        )));
+    }
+
+    let mut fallback = None;
+    for child in node.children() {
+        let scope = scope(&prefixes, child);
+        match local_name(child, &scope) {
+            Some("fallback") if fallback.is_none() => fallback = Some((child.clone(), scope)),
+            Some(name) => {
+                return Err(Error::InvalidInclude(format!(
+                    "unexpected {:?} element in an include",
+                    name
+                )))
+            }
+            None => {}
+        }
+    }
+
+    Ok(Some(Include {
+        href,
+        parse: attribute("parse").unwrap_or_else(|| "xml".to_owned()),
+        xpointer,
+        fallback,
+    }))
+}
+
+/// Resolves a reference relative to the directory of the including file
+fn resolve(path: &Path, href: &str) -> PathBuf {
+    let href = href.strip_prefix("file://").unwrap_or(href);
+    normalize(&path.parent().unwrap_or(Path::new("")).join(href))
+}
+
+/// Removes `.` and `..` components so that paths to the same file compare equal
+fn normalize(path: &Path) -> PathBuf {
+    let mut output = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match output.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    output.pop();
+                }
+                _ => output.push(".."),
+            },
+            component => output.push(component),
+        }
+    }
+    output
+}
+
+/// Returns the XInclude prefixes in scope of the parent of the node at a location
+fn ancestor_scope(root: &Node, location: &Location) -> Vec<String> {
+    let mut prefixes = Vec::new();
+    let mut node = root;
+    for (tag, index) in &location.0 {
+        prefixes = scope(&prefixes, node);
+        node = &node.nodes[tag][*index];
+    }
+    prefixes
+}
+
+/// Evaluates an XPointer, which is a shorthand id or a sequence of `element()` schemes tried in order
+fn point(root: &Node, xpointer: &str) -> Option<Location> {
+    let xpointer = xpointer.trim();
+    if !xpointer.contains('(') {
+        return find_id(root, xpointer);
+    }
+
+    let mut rest = xpointer;
+    while let Some(start) = rest.find('(') {
+        let scheme = rest[..start].trim();
+        let end = rest[start..].find(')')? + start;
+        let data = &rest[start + 1..end];
+        rest = &rest[end + 1..];
+
+        // Other schemes are skipped, as they may be handled by other processors
+        if scheme != "element" {
+            continue;
+        }
+        if let Some(location) = element_scheme(root, data) {
+            return Some(location);
+        }
+    }
+    None
+}
+
+/// Evaluates the data of an `element()` scheme, an optional id followed by a child sequence like `/1/2`
+fn element_scheme(root: &Node, data: &str) -> Option<Location> {
+    let mut steps = data.split('/');
+    let mut location = match steps.next()? {
+        "" => {
+            // The first step of an absolute sequence selects the root element
+            if steps.next()?.parse::<usize>().ok()? != 1 {
+                return None;
+            }
+            Location(Vec::new())
+        }
+        id => find_id(root, id)?,
+    };
+
+    for step in steps {
+        let index = step.parse::<usize>().ok()?.checked_sub(1)?;
+        let node = location.get(root)?;
+        location = path::children(node, &location).into_iter().nth(index)?;
+    }
+    Some(location)
+}
+
+/// Finds the first element with an `xml:id` or `id` attribute, as there are no DTD declared ids
+fn find_id(root: &Node, id: &str) -> Option<Location> {
+    path::find_ids(root, &["xml:id", "id"], id).next()
+}
@@ -0,0 +1,1266 @@
+//! This is a module providing evaluation of XPath 1.0 expressions, used by the `xslt` and `path`
+//! modules
+//! Nodes are referred to by their location below the root, as the tree has no parent links
+//! The text of an element is a single text node following its child elements, matching how nodes
+//! are serialized
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+
+use crate::path::{self, Location, Target};
+use crate::{entity, Error, Node};
+
+/// A node of the source tree
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum Item {
+    /// The document node above the root element
+    Document,
+    Element(Location),
+    Attribute(Location, String),
+    /// The content of an element
+    Text(Location),
+}
+
+/// The value of an expression
+#[derive(Debug, Clone)]
+pub(crate) enum Value {
+    /// Nodes in document order
+    Nodes(Vec<Item>),
+    String(String),
+    Number(f64),
+    Boolean(bool),
+    /// A tree fragment built by a variable, held as the children and content of a node without a tag
+    Fragment(Node),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Axis {
+    Child,
+    Descendant,
+    DescendantOrSelf,
+    Parent,
+    Ancestor,
+    AncestorOrSelf,
+    Current,
+    Attribute,
+    FollowingSibling,
+    PrecedingSibling,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Test {
+    Name(String),
+    /// All names with a prefix, `prefix:*`
+    Prefix(String),
+    Any,
+    Text,
+    Node,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Step {
+    axis: Axis,
+    test: Test,
+    predicates: Vec<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Start {
+    Document,
+    Context,
+    Filter(Box<Expr>),
+}
+
+/// A parsed expression
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Compare(&'static str, Box<Expr>, Box<Expr>),
+    Arithmetic(&'static str, Box<Expr>, Box<Expr>),
+    Negate(Box<Expr>),
+    Union(Box<Expr>, Box<Expr>),
+    Path(Start, Vec<Step>),
+    Filter(Box<Expr>, Vec<Expr>),
+    Variable(String),
+    Literal(String),
+    Number(f64),
+    Function(String, Vec<Expr>),
+}
+
+/// What an expression is evaluated against
+pub(crate) struct Context<'a> {
+    pub root: &'a Node,
+    pub item: Item,
+    /// The position of the item in the current node list, starting at 1
+    pub position: usize,
+    pub size: usize,
+    /// The item being processed by the template or for-each, returned by `current()`
+    pub current: Item,
+    /// The variables in scope, with later bindings shadowing earlier ones
+    pub variables: &'a [(String, Value)],
+}
+
+impl Context<'_> {
+    fn with(&self, item: Item, position: usize, size: usize) -> Context<'_> {
+        Context {
+            root: self.root,
+            item,
+            position,
+            size,
+            current: self.current.clone(),
+            variables: self.variables,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Symbol(&'static str),
+    Operator(&'static str),
+    Name(String),
+    Literal(String),
+    Number(f64),
+    Variable(String),
+}
+
+const SYMBOLS: [&str; 20] = [
+    "//", "..", "::", "!=", "<=", ">=", "/", "(", ")", "[", "]", ".", "@", ",", "|", "+", "-", "=",
+    "<", ">",
+];
+
+/// The deepest nesting of operators, parentheses and predicates, as expressions are parsed and
+/// evaluated recursively
+const MAX_NESTING: usize = 64;
+
+fn invalid(expression: &str) -> Error {
+    Error::InvalidStylesheet(format!("invalid expression {:?}", expression))
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = Vec::new();
+    let mut rest = expression.trim_start();
+    while let Some(c) = rest.chars().next() {
+        // Names and * are operators when following a value, see section 3.7 of XPath 1.0
+        let after_value = match tokens.last() {
+            None | Some(Token::Operator(_)) => false,
+            Some(Token::Symbol(s)) => [")", "]", ".", ".."].contains(s),
+            Some(_) => true,
+        };
+
+        let (token, length) = if c == '"' || c == '\'' {
+            let end = rest[1..].find(c).ok_or_else(|| invalid(expression))?;
+            (Token::Literal(rest[1..end + 1].to_owned()), end + 2)
+        } else if c.is_ascii_digit()
+            || (c == '.' && rest[1..].starts_with(|c: char| c.is_ascii_digit()))
+        {
+            let length = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(rest.len());
+            let number = rest[..length].parse().map_err(|_| invalid(expression))?;
+            (Token::Number(number), length)
+        } else if c == '*' {
+            match after_value {
+                true => (Token::Operator("*"), 1),
+                false => (Token::Name("*".to_owned()), 1),
+            }
+        } else if let Some(name) = rest.strip_prefix('$') {
+            let length = name_length(name);
+            if length == 0 {
+                return Err(invalid(expression));
+            }
+            (Token::Variable(name[..length].to_owned()), length + 1)
+        } else if let Some(symbol) = SYMBOLS.iter().find(|v| rest.starts_with(**v)) {
+            (Token::Symbol(symbol), symbol.len())
+        } else {
+            let mut length = name_length(rest);
+            if length == 0 {
+                return Err(invalid(expression));
+            }
+            // A prefixed wildcard such as xsl:*
+            if rest[length..].starts_with(":*") {
+                length += 2;
+            }
+
+            let name = &rest[..length];
+            match (after_value, name) {
+                (true, "and") => (Token::Operator("and"), length),
+                (true, "or") => (Token::Operator("or"), length),
+                (true, "div") => (Token::Operator("div"), length),
+                (true, "mod") => (Token::Operator("mod"), length),
+                _ => (Token::Name(name.to_owned()), length),
+            }
+        };
+
+        tokens.push(token);
+        rest = rest[length..].trim_start();
+    }
+    Ok(tokens)
+}
+
+/// Returns the length of the qualified name at the start of text
+fn name_length(text: &str) -> usize {
+    let mut length = 0;
+    for (i, c) in text.char_indices() {
+        let valid = c.is_alphanumeric() || c == '_' || (i > 0 && matches!(c, '-' | '.'));
+        // A single colon separates the prefix, a double colon an axis
+        let colon = c == ':' && !text[i + 1..].starts_with([':', '*']) && i > 0;
+        if !valid && !colon {
+            break;
+        }
+        length = i + c.len_utf8();
+    }
+    length
+}
+
+struct Parser<'a> {
+    expression: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Expr {
+    /// Parses an expression
+    /// Returns an Err of InvalidStylesheet if the expression is malformed
+    pub(crate) fn parse(expression: &str) -> Result<Expr, Error> {
+        let mut parser = Parser {
+            expression,
+            tokens: tokenize(expression)?,
+            pos: 0,
+            depth: 0,
+        };
+        let expr = parser.or()?;
+        match parser.pos == parser.tokens.len() {
+            true => Ok(expr),
+            false => Err(invalid(expression)),
+        }
+    }
+
+    /// Returns the default priority of a template pattern, see section 5.5 of XSLT 1.0
+    pub(crate) fn default_priority(&self) -> f64 {
+        match self {
+            Expr::Path(Start::Context, steps)
+                if steps.len() == 1 && steps[0].predicates.is_empty() =>
+            {
+                match steps[0].test {
+                    Test::Name(_) => 0.0,
+                    Test::Prefix(_) => -0.25,
+                    Test::Any | Test::Text | Test::Node => -0.5,
+                }
+            }
+            _ => 0.5,
+        }
+    }
+
+    /// Splits a union pattern into its alternatives
+    pub(crate) fn alternatives(self) -> Vec<Expr> {
+        match self {
+            Expr::Union(a, b) => {
+                let mut alternatives = a.alternatives();
+                alternatives.extend(b.alternatives());
+                alternatives
+            }
+            expr => vec![expr],
+        }
+    }
+
+    /// Removes a final attribute or `text()` step without predicates from a location path, and
+    /// returns what it selects from the elements matched by the rest of the path
+    pub(crate) fn split_target(&mut self) -> Target {
+        let steps = match self {
+            Expr::Path(_, steps) => steps,
+            _ => return Target::Element,
+        };
+        let target = match steps.last() {
+            Some(Step {
+                axis: Axis::Attribute,
+                test: Test::Name(name),
+                predicates,
+            }) if predicates.is_empty() => Target::Attribute(name.clone()),
+            Some(Step {
+                axis: Axis::Child,
+                test: Test::Text,
+                predicates,
+            }) if predicates.is_empty() => Target::Text,
+            _ => return Target::Element,
+        };
+        steps.pop();
+        target
+    }
+
+    /// Checks if the expression is a location path, or a union or filter of them, with no attribute
+    /// or text steps outside predicates
+    pub(crate) fn selects_elements(&self) -> bool {
+        match self {
+            Expr::Path(start, steps) => {
+                let elements = match start {
+                    Start::Filter(expr) => expr.selects_elements(),
+                    _ => true,
+                };
+                elements
+                    && steps
+                        .iter()
+                        .all(|v| v.axis != Axis::Attribute && v.test != Test::Text)
+            }
+            Expr::Union(a, b) => a.selects_elements() && b.selects_elements(),
+            Expr::Filter(expr, _) => expr.selects_elements(),
+            _ => false,
+        }
+    }
+
+    /// Checks that evaluating the expression without variables can not fail, so that only known
+    /// functions are called with the right arguments and node sets are used where required
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        let nodes = |expr: &Expr| {
+            expr.check()?;
+            match expr {
+                Expr::Path(..) | Expr::Union(..) | Expr::Filter(..) => Ok(()),
+                Expr::Function(name, _) if name == "current" => Ok(()),
+                _ => Err(Error::InvalidStylesheet(
+                    "expected an expression selecting nodes".to_owned(),
+                )),
+            }
+        };
+        let predicates = |predicates: &[Expr]| predicates.iter().try_for_each(Expr::check);
+
+        match self {
+            Expr::Or(a, b)
+            | Expr::And(a, b)
+            | Expr::Compare(_, a, b)
+            | Expr::Arithmetic(_, a, b) => {
+                a.check()?;
+                b.check()
+            }
+            Expr::Negate(a) => a.check(),
+            Expr::Union(a, b) => {
+                nodes(a)?;
+                nodes(b)
+            }
+            Expr::Path(start, steps) => {
+                if let Start::Filter(expr) = start {
+                    nodes(expr)?;
+                }
+                steps.iter().try_for_each(|v| predicates(&v.predicates))
+            }
+            Expr::Filter(expr, filters) => {
+                nodes(expr)?;
+                predicates(filters)
+            }
+            Expr::Variable(name) => Err(Error::InvalidStylesheet(format!(
+                "undefined variable ${}",
+                name
+            ))),
+            Expr::Literal(_) | Expr::Number(_) => Ok(()),
+            Expr::Function(name, arguments) => {
+                check_arguments(name, arguments)?;
+                match name.as_str() {
+                    "count" | "sum" | "name" | "local-name" => arguments.iter().try_for_each(nodes),
+                    _ => arguments.iter().try_for_each(Expr::check),
+                }
+            }
+        }
+    }
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, token: Token) -> bool {
+        match self.peek() == Some(&token) {
+            true => {
+                self.pos += 1;
+                true
+            }
+            false => false,
+        }
+    }
+
+    fn expect(&mut self, symbol: &'static str) -> Result<(), Error> {
+        match self.eat(Token::Symbol(symbol)) {
+            true => Ok(()),
+            false => Err(invalid(self.expression)),
+        }
+    }
+
+    /// Enters a nested expression, failing if the nesting is too deep
+    fn enter(&mut self) -> Result<(), Error> {
+        self.depth += 1;
+        match self.depth > MAX_NESTING {
+            true => Err(invalid(self.expression)),
+            false => Ok(()),
+        }
+    }
+
+    fn binary(
+        &mut self,
+        operators: &[&'static str],
+        next: fn(&mut Self) -> Result<Expr, Error>,
+        build: fn(&'static str, Expr, Expr) -> Expr,
+    ) -> Result<Expr, Error> {
+        let mut expr = next(self)?;
+        // Each operator nests the expression so far one level deeper
+        let mut chained = 0;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Symbol(v)) | Some(Token::Operator(v)) if operators.contains(v) => *v,
+                _ => {
+                    self.depth -= chained;
+                    return Ok(expr);
+                }
+            };
+            self.pos += 1;
+            self.enter()?;
+            chained += 1;
+            expr = build(operator, expr, next(self)?);
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, Error> {
+        self.binary(&["or"], Self::and, |_, a, b| {
+            Expr::Or(Box::new(a), Box::new(b))
+        })
+    }
+
+    fn and(&mut self) -> Result<Expr, Error> {
+        self.binary(&["and"], Self::equality, |_, a, b| {
+            Expr::And(Box::new(a), Box::new(b))
+        })
+    }
+
+    fn equality(&mut self) -> Result<Expr, Error> {
+        self.binary(&["=", "!="], Self::relational, |op, a, b| {
+            Expr::Compare(op, Box::new(a), Box::new(b))
+        })
+    }
+
+    fn relational(&mut self) -> Result<Expr, Error> {
+        self.binary(&["<", "<=", ">", ">="], Self::additive, |op, a, b| {
+            Expr::Compare(op, Box::new(a), Box::new(b))
+        })
+    }
+
+    fn additive(&mut self) -> Result<Expr, Error> {
+        self.binary(&["+", "-"], Self::multiplicative, |op, a, b| {
+            Expr::Arithmetic(op, Box::new(a), Box::new(b))
+        })
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, Error> {
+        self.binary(&["*", "div", "mod"], Self::unary, |op, a, b| {
+            Expr::Arithmetic(op, Box::new(a), Box::new(b))
+        })
+    }
+
+    fn unary(&mut self) -> Result<Expr, Error> {
+        self.enter()?;
+        let expr = match self.eat(Token::Symbol("-")) {
+            true => Expr::Negate(Box::new(self.unary()?)),
+            false => self.binary(&["|"], Self::path, |_, a, b| {
+                Expr::Union(Box::new(a), Box::new(b))
+            })?,
+        };
+        self.depth -= 1;
+        Ok(expr)
+    }
+
+    fn path(&mut self) -> Result<Expr, Error> {
+        let start = match self.peek() {
+            Some(Token::Symbol("/")) => {
+                self.pos += 1;
+                // A single slash selects the document
+                if !self.starts_step() {
+                    return Ok(Expr::Path(Start::Document, Vec::new()));
+                }
+                Start::Document
+            }
+            Some(Token::Symbol("//")) => Start::Document,
+            Some(
+                Token::Variable(_) | Token::Literal(_) | Token::Number(_) | Token::Symbol("("),
+            ) => {
+                let primary = self.primary()?;
+                Start::Filter(Box::new(primary))
+            }
+            Some(Token::Name(name))
+                if name != "text"
+                    && name != "node"
+                    && self.tokens.get(self.pos + 1) == Some(&Token::Symbol("(")) =>
+            {
+                Start::Filter(Box::new(self.primary()?))
+            }
+            _ => Start::Context,
+        };
+
+        let mut steps = Vec::new();
+        let primary = match start {
+            Start::Filter(primary) => primary,
+            start => {
+                if start == Start::Document && self.peek() == Some(&Token::Symbol("//")) {
+                    self.separator(&mut steps)?;
+                }
+                self.relative(&mut steps)?;
+                return Ok(Expr::Path(start, steps));
+            }
+        };
+
+        let mut predicates = Vec::new();
+        while self.peek() == Some(&Token::Symbol("[")) {
+            predicates.push(self.predicate()?);
+        }
+        let primary = match predicates.is_empty() {
+            true => *primary,
+            false => Expr::Filter(primary, predicates),
+        };
+
+        if !matches!(self.peek(), Some(Token::Symbol("/" | "//"))) {
+            return Ok(primary);
+        }
+        self.separator(&mut steps)?;
+        self.relative(&mut steps)?;
+        Ok(Expr::Path(Start::Filter(Box::new(primary)), steps))
+    }
+
+    fn starts_step(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(Token::Name(_) | Token::Symbol("." | ".." | "@"))
+        )
+    }
+
+    /// Reads a slash, adding a descendant-or-self step for a double slash
+    fn separator(&mut self, steps: &mut Vec<Step>) -> Result<(), Error> {
+        if self.eat(Token::Symbol("//")) {
+            steps.push(Step {
+                axis: Axis::DescendantOrSelf,
+                test: Test::Node,
+                predicates: Vec::new(),
+            });
+            Ok(())
+        } else {
+            self.expect("/")
+        }
+    }
+
+    fn relative(&mut self, steps: &mut Vec<Step>) -> Result<(), Error> {
+        steps.push(self.step()?);
+        while matches!(self.peek(), Some(Token::Symbol("/" | "//"))) {
+            self.separator(steps)?;
+            steps.push(self.step()?);
+        }
+        Ok(())
+    }
+
+    fn step(&mut self) -> Result<Step, Error> {
+        let abbreviated = |axis| Step {
+            axis,
+            test: Test::Node,
+            predicates: Vec::new(),
+        };
+        if self.eat(Token::Symbol(".")) {
+            return Ok(abbreviated(Axis::Current));
+        }
+        if self.eat(Token::Symbol("..")) {
+            return Ok(abbreviated(Axis::Parent));
+        }
+
+        let mut axis = match self.eat(Token::Symbol("@")) {
+            true => Axis::Attribute,
+            false => Axis::Child,
+        };
+
+        let name = match self.peek() {
+            Some(Token::Name(name)) => name.clone(),
+            _ => return Err(invalid(self.expression)),
+        };
+        self.pos += 1;
+
+        let name = match self.eat(Token::Symbol("::")) {
+            true if axis == Axis::Child => {
+                axis = match name.as_str() {
+                    "child" => Axis::Child,
+                    "descendant" => Axis::Descendant,
+                    "descendant-or-self" => Axis::DescendantOrSelf,
+                    "parent" => Axis::Parent,
+                    "ancestor" => Axis::Ancestor,
+                    "ancestor-or-self" => Axis::AncestorOrSelf,
+                    "self" => Axis::Current,
+                    "attribute" => Axis::Attribute,
+                    "following-sibling" => Axis::FollowingSibling,
+                    "preceding-sibling" => Axis::PrecedingSibling,
+                    _ => return Err(invalid(self.expression)),
+                };
+                match self.peek() {
+                    Some(Token::Name(name)) => {
+                        let name = name.clone();
+                        self.pos += 1;
+                        name
+                    }
+                    _ => return Err(invalid(self.expression)),
+                }
+            }
+            true => return Err(invalid(self.expression)),
+            false => name,
+        };
+
+        let test = match name.as_str() {
+            "text" | "node" if self.eat(Token::Symbol("(")) => {
+                self.expect(")")?;
+                match name.as_str() {
+                    "text" => Test::Text,
+                    _ => Test::Node,
+                }
+            }
+            "*" => Test::Any,
+            name => match name.strip_suffix(":*") {
+                Some(prefix) => Test::Prefix(prefix.to_owned()),
+                None => Test::Name(name.to_owned()),
+            },
+        };
+
+        let mut predicates = Vec::new();
+        while self.peek() == Some(&Token::Symbol("[")) {
+            predicates.push(self.predicate()?);
+        }
+
+        Ok(Step {
+            axis,
+            test,
+            predicates,
+        })
+    }
+
+    fn predicate(&mut self) -> Result<Expr, Error> {
+        self.expect("[")?;
+        let expr = self.or()?;
+        self.expect("]")?;
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr, Error> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| invalid(self.expression))?;
+        self.pos += 1;
+        match token {
+            Token::Variable(name) => Ok(Expr::Variable(name)),
+            Token::Literal(v) => Ok(Expr::Literal(v)),
+            Token::Number(v) => Ok(Expr::Number(v)),
+            Token::Symbol("(") => {
+                let expr = self.or()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Token::Name(name) => {
+                self.expect("(")?;
+                let mut arguments = Vec::new();
+                if !self.eat(Token::Symbol(")")) {
+                    loop {
+                        arguments.push(self.or()?);
+                        if self.eat(Token::Symbol(")")) {
+                            break;
+                        }
+                        self.expect(",")?;
+                    }
+                }
+                Ok(Expr::Function(name, arguments))
+            }
+            _ => Err(invalid(self.expression)),
+        }
+    }
+}
+
+impl Expr {
+    /// Evaluates the expression
+    /// Returns an Err of InvalidStylesheet for unknown variables and functions, or wrongly typed arguments
+    pub(crate) fn evaluate(&self, context: &Context) -> Result<Value, Error> {
+        Ok(match self {
+            Expr::Or(a, b) => {
+                Value::Boolean(a.evaluate(context)?.boolean() || b.evaluate(context)?.boolean())
+            }
+            Expr::And(a, b) => {
+                Value::Boolean(a.evaluate(context)?.boolean() && b.evaluate(context)?.boolean())
+            }
+            Expr::Compare(op, a, b) => {
+                let (a, b) = (a.evaluate(context)?, b.evaluate(context)?);
+                Value::Boolean(compare(context.root, op, &a, &b))
+            }
+            Expr::Arithmetic(op, a, b) => {
+                let a = a.evaluate(context)?.number(context.root);
+                let b = b.evaluate(context)?.number(context.root);
+                Value::Number(match *op {
+                    "+" => a + b,
+                    "-" => a - b,
+                    "*" => a * b,
+                    "div" => a / b,
+                    _ => a % b,
+                })
+            }
+            Expr::Negate(a) => Value::Number(-a.evaluate(context)?.number(context.root)),
+            Expr::Union(a, b) => {
+                let mut items = a.evaluate(context)?.into_nodes()?;
+                items.extend(b.evaluate(context)?.into_nodes()?);
+                Value::Nodes(sort(context.root, items))
+            }
+            Expr::Path(start, steps) => {
+                let mut items = match start {
+                    Start::Document => vec![Item::Document],
+                    Start::Context => vec![context.item.clone()],
+                    Start::Filter(expr) => expr.evaluate(context)?.into_nodes()?,
+                };
+                for step in steps {
+                    items = step.evaluate(context, &items)?;
+                }
+                Value::Nodes(items)
+            }
+            Expr::Filter(expr, predicates) => {
+                let items = expr.evaluate(context)?.into_nodes()?;
+                Value::Nodes(filter(context, items, predicates)?)
+            }
+            Expr::Variable(name) => context
+                .variables
+                .iter()
+                .rev()
+                .find(|(v, _)| v == name)
+                .map(|(_, value)| value.clone())
+                .ok_or_else(|| Error::InvalidStylesheet(format!("undefined variable ${}", name)))?,
+            Expr::Literal(v) => Value::String(v.clone()),
+            Expr::Number(v) => Value::Number(*v),
+            Expr::Function(name, arguments) => function(context, name, arguments)?,
+        })
+    }
+
+    /// Evaluates the expression as a node set
+    pub(crate) fn select(&self, context: &Context) -> Result<Vec<Item>, Error> {
+        self.evaluate(context)?.into_nodes()
+    }
+
+    /// Checks if an item matches the expression as a template pattern, which is the case if the
+    /// expression selects the item from the item itself or one of its ancestors
+    pub(crate) fn matches(&self, context: &Context, item: &Item) -> Result<bool, Error> {
+        let mut ancestor = Some(item.clone());
+        while let Some(current) = ancestor {
+            let context = context.with(current.clone(), 1, 1);
+            if self.select(&context)?.contains(item) {
+                return Ok(true);
+            }
+            ancestor = parent(&current);
+        }
+        Ok(false)
+    }
+}
+
+impl Step {
+    fn evaluate(&self, context: &Context, items: &[Item]) -> Result<Vec<Item>, Error> {
+        let mut output = Vec::new();
+        let mut seen = HashSet::new();
+        for item in items {
+            let candidates: Vec<Item> = axis(context.root, item, self.axis)
+                .into_iter()
+                .filter(|v| self.test(context.root, v))
+                .collect();
+            for item in filter(context, candidates, &self.predicates)? {
+                if seen.insert(item.clone()) {
+                    output.push(item);
+                }
+            }
+        }
+        Ok(sort(context.root, output))
+    }
+
+    fn test(&self, root: &Node, item: &Item) -> bool {
+        let name = item_name(root, item);
+        // The principal node type is attribute on the attribute axis and element otherwise
+        let principal = matches!(item, Item::Attribute(..)) == (self.axis == Axis::Attribute)
+            && !matches!(item, Item::Document);
+
+        match &self.test {
+            Test::Node => true,
+            Test::Text => matches!(item, Item::Text(_)),
+            Test::Any => principal && name.is_some(),
+            Test::Name(test) => principal && name == Some(test),
+            Test::Prefix(prefix) => {
+                principal && name.is_some_and(|v| v.split_once(':').is_some_and(|v| v.0 == prefix))
+            }
+        }
+    }
+}
+
+/// Returns the name of an element or attribute
+pub(crate) fn item_name<'a>(root: &'a Node, item: &'a Item) -> Option<&'a str> {
+    match item {
+        Item::Element(location) => location.get(root).map(|node| node.tag.as_str()),
+        Item::Attribute(_, name) => Some(name.as_str()),
+        _ => None,
+    }
+}
+
+/// Filters items by predicates, where positions follow the order of the items
+fn filter(
+    context: &Context,
+    mut items: Vec<Item>,
+    predicates: &[Expr],
+) -> Result<Vec<Item>, Error> {
+    for predicate in predicates {
+        let size = items.len();
+        let mut kept = Vec::new();
+        for (i, item) in items.into_iter().enumerate() {
+            let keep = match predicate.evaluate(&context.with(item.clone(), i + 1, size))? {
+                Value::Number(n) => n == (i + 1) as f64,
+                value => value.boolean(),
+            };
+            if keep {
+                kept.push(item);
+            }
+        }
+        items = kept;
+    }
+    Ok(items)
+}
+
+/// Returns the parent of an item
+pub(crate) fn parent(item: &Item) -> Option<Item> {
+    match item {
+        Item::Document => None,
+        Item::Element(location) => Some(match location.parent() {
+            Some(parent) => Item::Element(parent),
+            None => Item::Document,
+        }),
+        Item::Attribute(location, _) | Item::Text(location) => {
+            Some(Item::Element(location.clone()))
+        }
+    }
+}
+
+/// Returns the child elements and text of an item in document order
+pub(crate) fn children(root: &Node, item: &Item) -> Vec<Item> {
+    let location = match item {
+        Item::Document => return vec![Item::Element(Location::default())],
+        Item::Element(location) => location,
+        _ => return Vec::new(),
+    };
+
+    let node = match location.get(root) {
+        Some(v) => v,
+        None => return Vec::new(),
+    };
+    let mut children: Vec<Item> = path::children(node, location)
+        .into_iter()
+        .map(Item::Element)
+        .collect();
+    if !node.content.is_empty() {
+        children.push(Item::Text(location.clone()));
+    }
+    children
+}
+
+/// Returns the items on an axis, with reverse axes ordered from the nearest item
+fn axis(root: &Node, item: &Item, axis: Axis) -> Vec<Item> {
+    match axis {
+        Axis::Current => vec![item.clone()],
+        Axis::Child => children(root, item),
+        Axis::Parent => parent(item).into_iter().collect(),
+        Axis::Ancestor | Axis::AncestorOrSelf => {
+            let mut items = match axis {
+                Axis::AncestorOrSelf => vec![item.clone()],
+                _ => Vec::new(),
+            };
+            let mut current = parent(item);
+            while let Some(v) = current {
+                current = parent(&v);
+                items.push(v);
+            }
+            items
+        }
+        Axis::Descendant | Axis::DescendantOrSelf => {
+            let mut items = Vec::new();
+            let mut stack = vec![item.clone()];
+            while let Some(v) = stack.pop() {
+                stack.extend(children(root, &v).into_iter().rev());
+                items.push(v);
+            }
+            if axis == Axis::Descendant {
+                items.remove(0);
+            }
+            items
+        }
+        Axis::Attribute => match item {
+            Item::Element(location) => {
+                let mut names: Vec<&String> = location
+                    .get(root)
+                    .map(|node| node.attributes.keys().collect())
+                    .unwrap_or_default();
+                names.sort();
+                names
+                    .into_iter()
+                    .filter(|v| *v != "xmlns" && !v.starts_with("xmlns:"))
+                    .map(|name| Item::Attribute(location.clone(), name.clone()))
+                    .collect()
+            }
+            _ => Vec::new(),
+        },
+        Axis::FollowingSibling | Axis::PrecedingSibling => {
+            if matches!(item, Item::Attribute(..)) {
+                return Vec::new();
+            }
+            let siblings = match parent(item) {
+                Some(parent) => children(root, &parent),
+                None => return Vec::new(),
+            };
+            let index = siblings.iter().position(|v| v == item).unwrap_or(0);
+            match axis {
+                Axis::FollowingSibling => siblings[index + 1..].to_vec(),
+                _ => siblings[..index].iter().rev().cloned().collect(),
+            }
+        }
+    }
+}
+
+/// Returns a key which orders items in document order
+fn order_key(root: &Node, item: &Item) -> (Vec<usize>, String) {
+    let location = match item {
+        Item::Document => return (Vec::new(), String::new()),
+        Item::Element(v) | Item::Attribute(v, _) | Item::Text(v) => v,
+    };
+
+    // Attributes come before child elements, which come before the text
+    let mut key = vec![0];
+    let mut node = root;
+    for (tag, index) in &location.0 {
+        key.push(node.position(tag, *index).map_or(0, |v| v + 2));
+        match node.get_nodes(tag).and_then(|v| v.get(*index)) {
+            Some(v) => node = v,
+            None => break,
+        }
+    }
+
+    match item {
+        Item::Attribute(_, name) => {
+            key.push(1);
+            (key, name.clone())
+        }
+        Item::Text(_) => {
+            key.push(usize::MAX);
+            (key, String::new())
+        }
+        _ => (key, String::new()),
+    }
+}
+
+/// Sorts items in document order and removes duplicates
+fn sort(root: &Node, items: Vec<Item>) -> Vec<Item> {
+    let mut keyed: Vec<_> = items
+        .into_iter()
+        .map(|item| (order_key(root, &item), item))
+        .collect();
+    keyed.sort_by(|a, b| a.0.cmp(&b.0));
+    keyed.dedup_by(|a, b| a.1 == b.1);
+    keyed.into_iter().map(|(_, item)| item).collect()
+}
+
+/// Returns the string value of an item
+pub(crate) fn string_value(root: &Node, item: &Item) -> String {
+    match item {
+        Item::Document => string_value(root, &Item::Element(Location::default())),
+        Item::Element(_) => {
+            let mut output = String::new();
+            let mut stack = vec![item.clone()];
+            while let Some(v) = stack.pop() {
+                match v {
+                    Item::Text(ref location) => {
+                        if let Some(node) = location.get(root) {
+                            output.push_str(&entity::decode(&node.content));
+                        }
+                    }
+                    v => stack.extend(children(root, &v).into_iter().rev()),
+                }
+            }
+            output
+        }
+        Item::Attribute(location, name) => location
+            .get(root)
+            .and_then(|node| node.get_attribute(name))
+            .map(|v| entity::decode(v))
+            .unwrap_or_default(),
+        Item::Text(location) => location
+            .get(root)
+            .map(|node| entity::decode(&node.content))
+            .unwrap_or_default(),
+    }
+}
+
+/// Returns the text of a fragment
+fn fragment_value(node: &Node) -> String {
+    let mut output = String::new();
+    for child in node.children() {
+        output.push_str(&fragment_value(child));
+    }
+    output.push_str(&entity::decode(&node.content));
+    output
+}
+
+/// Converts a number to a string as done by the XPath string function
+pub(crate) fn number_to_string(n: f64) -> String {
+    if n.is_nan() {
+        "NaN".to_owned()
+    } else if n.is_infinite() {
+        match n > 0.0 {
+            true => "Infinity".to_owned(),
+            false => "-Infinity".to_owned(),
+        }
+    } else if n == n.trunc() && n.abs() < 1e17 {
+        format!("{}", n as i64)
+    } else {
+        n.to_string()
+    }
+}
+
+/// Converts a string to a number as done by the XPath number function
+fn string_to_number(s: &str) -> f64 {
+    let s = s.trim();
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    let valid = !digits.is_empty()
+        && digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && digits.chars().filter(|c| *c == '.').count() <= 1
+        && digits != ".";
+    match valid {
+        true => s.parse().unwrap_or(f64::NAN),
+        false => f64::NAN,
+    }
+}
+
+impl Value {
+    pub(crate) fn into_nodes(self) -> Result<Vec<Item>, Error> {
+        match self {
+            Value::Nodes(v) => Ok(v),
+            _ => Err(Error::InvalidStylesheet(
+                "expected an expression selecting nodes".to_owned(),
+            )),
+        }
+    }
+
+    pub(crate) fn boolean(&self) -> bool {
+        match self {
+            Value::Nodes(v) => !v.is_empty(),
+            Value::String(v) => !v.is_empty(),
+            Value::Number(v) => *v != 0.0 && !v.is_nan(),
+            Value::Boolean(v) => *v,
+            Value::Fragment(_) => true,
+        }
+    }
+
+    pub(crate) fn string(&self, root: &Node) -> String {
+        match self {
+            Value::Nodes(v) => v.first().map(|v| string_value(root, v)).unwrap_or_default(),
+            Value::String(v) => v.clone(),
+            Value::Number(v) => number_to_string(*v),
+            Value::Boolean(v) => v.to_string(),
+            Value::Fragment(v) => fragment_value(v),
+        }
+    }
+
+    pub(crate) fn number(&self, root: &Node) -> f64 {
+        match self {
+            Value::Number(v) => *v,
+            Value::Boolean(v) => f64::from(u8::from(*v)),
+            v => string_to_number(&v.string(root)),
+        }
+    }
+}
+
+/// Compares two values with an equality or relational operator, see section 3.4 of XPath 1.0
+fn compare(root: &Node, op: &str, a: &Value, b: &Value) -> bool {
+    let string = |item: &Item| Value::String(string_value(root, item));
+    match (a, b) {
+        (Value::Nodes(x), Value::Nodes(y)) => x
+            .iter()
+            .any(|x| y.iter().any(|y| compare(root, op, &string(x), &string(y)))),
+        (Value::Nodes(_), Value::Boolean(_)) | (Value::Boolean(_), Value::Nodes(_)) => compare(
+            root,
+            op,
+            &Value::Boolean(a.boolean()),
+            &Value::Boolean(b.boolean()),
+        ),
+        (Value::Nodes(x), _) => x.iter().any(|x| compare(root, op, &string(x), b)),
+        (_, Value::Nodes(y)) => y.iter().any(|y| compare(root, op, a, &string(y))),
+        _ if op == "=" || op == "!=" => {
+            let equal = match (a, b) {
+                (Value::Boolean(_), _) | (_, Value::Boolean(_)) => a.boolean() == b.boolean(),
+                (Value::Number(_), _) | (_, Value::Number(_)) => a.number(root) == b.number(root),
+                _ => a.string(root) == b.string(root),
+            };
+            equal == (op == "=")
+        }
+        _ => {
+            let (a, b) = (a.number(root), b.number(root));
+            match op {
+                "<" => a < b,
+                "<=" => a <= b,
+                ">" => a > b,
+                _ => a >= b,
+            }
+        }
+    }
+}
+
+fn function(context: &Context, name: &str, arguments: &[Expr]) -> Result<Value, Error> {
+    let root = context.root;
+    check_arguments(name, arguments)?;
+    let values = arguments
+        .iter()
+        .map(|v| v.evaluate(context))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let string = |i: usize| -> Result<String, Error> {
+        match values.get(i) {
+            Some(v) => Ok(v.string(root)),
+            None => Ok(string_value(root, &context.item)),
+        }
+    };
+
+    Ok(match name {
+        "last" => Value::Number(context.size as f64),
+        "position" => Value::Number(context.position as f64),
+        "current" => Value::Nodes(vec![context.current.clone()]),
+        "count" => Value::Number(values[0].clone().into_nodes()?.len() as f64),
+        "sum" => {
+            let nodes = values[0].clone().into_nodes()?;
+            Value::Number(
+                nodes
+                    .iter()
+                    .map(|v| string_to_number(&string_value(root, v)))
+                    .sum(),
+            )
+        }
+        "name" | "local-name" => {
+            let item = match values.first() {
+                Some(v) => v.clone().into_nodes()?.into_iter().next(),
+                None => Some(context.item.clone()),
+            };
+            let qualified = item.as_ref().and_then(|v| item_name(root, v)).unwrap_or("");
+            Value::String(match qualified.split_once(':') {
+                Some((_, local)) if name == "local-name" => local.to_owned(),
+                _ => qualified.to_owned(),
+            })
+        }
+        "string" => Value::String(string(0)?),
+        "concat" => Value::String(values.iter().map(|v| v.string(root)).collect()),
+        "contains" | "starts-with" | "substring-before" | "substring-after" => {
+            let (a, b) = (string(0)?, string(1)?);
+            match name {
+                "contains" => Value::Boolean(a.contains(&b)),
+                "starts-with" => Value::Boolean(a.starts_with(&b)),
+                "substring-before" => {
+                    Value::String(a.find(&b).map(|i| a[..i].to_owned()).unwrap_or_default())
+                }
+                _ => Value::String(
+                    a.find(&b)
+                        .map(|i| a[i + b.len()..].to_owned())
+                        .unwrap_or_default(),
+                ),
+            }
+        }
+        "substring" => {
+            let chars: Vec<char> = string(0)?.chars().collect();
+            // Positions start at 1 and are rounded, see section 4.2 of XPath 1.0
+            let start = values[1].number(root).round();
+            let end = match values.get(2) {
+                Some(v) => start + v.number(root).round(),
+                None => f64::INFINITY,
+            };
+            Value::String(
+                chars
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| {
+                        let position = (*i + 1) as f64;
+                        position >= start && position < end
+                    })
+                    .map(|(_, c)| c)
+                    .collect(),
+            )
+        }
+        "string-length" => Value::Number(string(0)?.chars().count() as f64),
+        "normalize-space" => {
+            Value::String(string(0)?.split_whitespace().collect::<Vec<_>>().join(" "))
+        }
+        "translate" => {
+            let (from, to): (Vec<char>, Vec<char>) =
+                (string(1)?.chars().collect(), string(2)?.chars().collect());
+            Value::String(
+                string(0)?
+                    .chars()
+                    .filter_map(|c| match from.iter().position(|v| *v == c) {
+                        Some(i) => to.get(i).copied(),
+                        None => Some(c),
+                    })
+                    .collect(),
+            )
+        }
+        "not" => Value::Boolean(!values[0].boolean()),
+        "boolean" => Value::Boolean(values[0].boolean()),
+        "true" => Value::Boolean(true),
+        "false" => Value::Boolean(false),
+        "number" => match values.first() {
+            Some(v) => Value::Number(v.number(root)),
+            None => Value::Number(string_to_number(&string(0)?)),
+        },
+        "floor" | "ceiling" | "round" => {
+            let n = values[0].number(root);
+            Value::Number(match name {
+                "floor" => n.floor(),
+                "ceiling" => n.ceil(),
+                // Halves round towards positive infinity
+                _ => (n + 0.5).floor(),
+            })
+        }
+        _ => unreachable!("Every function has an arity"),
+    })
+}
+
+/// Returns the numbers of arguments a function takes, or None if the function is unknown
+fn arity(name: &str) -> Option<RangeInclusive<usize>> {
+    Some(match name {
+        "last" | "position" | "current" | "true" | "false" => 0..=0,
+        "count" | "sum" | "not" | "boolean" | "floor" | "ceiling" | "round" => 1..=1,
+        "name" | "local-name" | "string" | "string-length" | "normalize-space" | "number" => 0..=1,
+        "contains" | "starts-with" | "substring-before" | "substring-after" => 2..=2,
+        "substring" => 2..=3,
+        "translate" => 3..=3,
+        "concat" => 2..=usize::MAX,
+        _ => return None,
+    })
+}
+
+/// Checks that a function is known and called with the right number of arguments
+fn check_arguments(name: &str, arguments: &[Expr]) -> Result<(), Error> {
+    match arity(name) {
+        Some(range) if range.contains(&arguments.len()) => Ok(()),
+        Some(_) => Err(Error::InvalidStylesheet(format!(
+            "wrong number of arguments to {}()",
+            name
+        ))),
+        None => Err(Error::InvalidStylesheet(format!(
+            "unknown function {}()",
+            name
+        ))),
+    }
+}
+
+/// Orders two strings or numbers for sorting, with NaN first
+pub(crate) fn compare_keys(a: &Value, b: &Value, root: &Node, numeric: bool) -> Ordering {
+    match numeric {
+        true => {
+            let (a, b) = (a.number(root), b.number(root));
+            match (a.is_nan(), b.is_nan()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Less,
+                (false, true) => Ordering::Greater,
+                _ => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+            }
+        }
+        false => a.string(root).cmp(&b.string(root)),
+    }
+}
@@ -0,0 +1,921 @@
+//! This is a module providing validation of xml structures against a subset of XML Schema
+//! The schema is itself loaded with this crate
+//!
+//! Supported are global and local element declarations, named and anonymous complex and simple
+//! types, sequence, choice, and all groups with minOccurs and maxOccurs, simple and complex content
+//! extensions, attributes, and restrictions of the built in simple types with the pattern,
+//! enumeration, length, and range facets
+//!
+//! Namespaces are not resolved; elements and types are matched by their local name
+//! ```
+//! let schema = simple_xml::xsd::Schema::from_node(&simple_xml::from_string(
+//!     r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+//!         <xs:element name="age" type="xs:int"/>
+//!     </xs:schema>"#,
+//! ).unwrap()).unwrap();
+//!
+//! let violations = schema.validate(&simple_xml::from_string("<age>old</age>").unwrap());
+//! assert_eq!(violations[0].to_string(), "/age: Value \"old\" is not a valid int");
+//! ```
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use regex::Regex;
+use thiserror::Error;
+
+use crate::datatype::{is_reserved_attribute, Builtin};
+use crate::{Error, Node};
+
+/// A loaded schema which can validate xml structures
+#[derive(Debug, Clone)]
+pub struct Schema {
+    elements: HashMap<String, ElementDecl>,
+    types: HashMap<String, TypeDef>,
+}
+
+/// A single problem found when validating a node against a schema
+#[derive(Debug, Error, Clone, PartialEq)]
+#[error("{path}: {kind}")]
+pub struct Violation {
+    /// The path to the offending node, e.g; /note/to[2]
+    pub path: String,
+    pub kind: ViolationKind,
+}
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum ViolationKind {
+    #[error("No global element declaration for {0:?}")]
+    UnknownRoot(String),
+    #[error("Children {found:?} do not match the content model {expected}")]
+    InvalidContent {
+        expected: String,
+        found: Vec<String>,
+    },
+    #[error("Text is not allowed in element only content")]
+    UnexpectedText,
+    #[error("Child elements are not allowed in simple content")]
+    UnexpectedChildren,
+    #[error("Value {value:?} {reason}")]
+    InvalidValue { value: String, reason: String },
+    #[error("Missing required attribute {0:?}")]
+    MissingAttribute(String),
+    #[error("Attribute {0:?} is not declared")]
+    UndeclaredAttribute(String),
+    #[error("Value {value:?} of attribute {name:?} {reason}")]
+    InvalidAttributeValue {
+        name: String,
+        value: String,
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct ElementDecl {
+    name: String,
+    ty: TypeRef,
+}
+
+#[derive(Debug, Clone)]
+enum TypeRef {
+    Builtin(Builtin),
+    Named(String),
+    Complex(Box<ComplexType>),
+    Simple(Box<SimpleType>),
+    /// No type was given, anything is allowed
+    Any,
+}
+
+#[derive(Debug, Clone)]
+enum TypeDef {
+    Complex(ComplexType),
+    Simple(SimpleType),
+}
+
+#[derive(Debug, Clone)]
+struct ComplexType {
+    /// The type extended through complexContent or simpleContent
+    base: Option<TypeRef>,
+    particle: Option<Particle>,
+    attributes: Vec<AttributeDecl>,
+    mixed: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Particle {
+    kind: ParticleKind,
+    min: usize,
+    /// None for unbounded
+    max: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+enum ParticleKind {
+    Element(ElementDecl),
+    /// A reference to a global element declaration
+    Ref(String),
+    Sequence(Vec<Particle>),
+    Choice(Vec<Particle>),
+    All(Vec<Particle>),
+    Any,
+}
+
+#[derive(Debug, Clone)]
+struct AttributeDecl {
+    name: String,
+    ty: TypeRef,
+    required: bool,
+    fixed: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct SimpleType {
+    base: TypeRef,
+    facets: Facets,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Facets {
+    patterns: Vec<Regex>,
+    enumeration: Vec<String>,
+    length: Option<usize>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    min_inclusive: Option<f64>,
+    max_inclusive: Option<f64>,
+    min_exclusive: Option<f64>,
+    max_exclusive: Option<f64>,
+}
+
+/// Loads a schema from an xsd file
+pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Schema, Error> {
+    Schema::from_node(&crate::from_file(path)?)
+}
+
+/// Returns the local part of a qualified name
+fn local(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Returns the prefix of a qualified name
+fn prefix(name: &str) -> Option<&str> {
+    name.find(':').map(|i| &name[..i])
+}
+
+fn invalid(msg: String) -> Error {
+    Error::InvalidSchema(msg)
+}
+
+impl TypeDef {
+    /// Returns the named type this type is derived from, looking through anonymous base types
+    fn base_name(&self) -> Option<&str> {
+        match self {
+            TypeDef::Complex(v) => v.base.as_ref()?.name(),
+            TypeDef::Simple(v) => v.base.name(),
+        }
+    }
+}
+
+impl TypeRef {
+    /// Returns the name of a named type, or of the named type an anonymous type is derived from
+    fn name(&self) -> Option<&str> {
+        match self {
+            TypeRef::Named(v) => Some(v),
+            TypeRef::Complex(v) => v.base.as_ref()?.name(),
+            TypeRef::Simple(v) => v.base.name(),
+            TypeRef::Builtin(_) | TypeRef::Any => None,
+        }
+    }
+}
+
+/// Reads a schema document
+struct Loader {
+    /// The prefix bound to the XML Schema namespace
+    xs: Option<String>,
+    /// Named types which are referenced, checked once all types are loaded
+    type_refs: Vec<String>,
+    element_refs: Vec<String>,
+}
+
+impl Schema {
+    /// Loads a schema from the root <xs:schema> node of a schema document
+    /// Returns an Err of InvalidSchema if the schema uses unsupported or inconsistent constructs
+    pub fn from_node(root: &Node) -> Result<Schema, Error> {
+        if local(&root.tag) != "schema" {
+            return Err(invalid(format!(
+                "Expected schema root, found {:?}",
+                root.tag
+            )));
+        }
+
+        let mut loader = Loader {
+            xs: prefix(&root.tag).map(str::to_owned),
+            type_refs: Vec::new(),
+            element_refs: Vec::new(),
+        };
+
+        let mut schema = Schema {
+            elements: HashMap::new(),
+            types: HashMap::new(),
+        };
+
+        for child in root.children() {
+            match local(&child.tag) {
+                "element" => {
+                    let decl = loader.element(child)?;
+                    schema.elements.insert(decl.name.clone(), decl);
+                }
+                "complexType" => {
+                    let name = loader.name(child)?;
+                    let ty = loader.complex_type(child)?;
+                    schema.types.insert(name, TypeDef::Complex(ty));
+                }
+                "simpleType" => {
+                    let name = loader.name(child)?;
+                    let ty = loader.simple_type(child)?;
+                    schema.types.insert(name, TypeDef::Simple(ty));
+                }
+                "annotation" | "import" | "include" => {}
+                tag => return Err(invalid(format!("Unsupported schema component {:?}", tag))),
+            }
+        }
+
+        if let Some(name) = loader
+            .type_refs
+            .iter()
+            .find(|v| !schema.types.contains_key(*v))
+        {
+            return Err(invalid(format!("Unknown type {:?}", name)));
+        }
+        if let Some(name) = loader
+            .element_refs
+            .iter()
+            .find(|v| !schema.elements.contains_key(*v))
+        {
+            return Err(invalid(format!("Unknown element {:?}", name)));
+        }
+
+        // Values are checked against each base in turn, which would not end for circular derivations
+        for name in schema.types.keys() {
+            let mut seen = vec![name.as_str()];
+            while let Some(base) = schema.types[seen[seen.len() - 1]].base_name() {
+                if seen.contains(&base) {
+                    return Err(invalid(format!("Circular derivation of type {:?}", name)));
+                }
+                seen.push(base);
+            }
+        }
+
+        Ok(schema)
+    }
+
+    /// Validates a node and all its children against the global element declarations
+    /// An empty list means the node is valid
+    pub fn validate(&self, node: &Node) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let path = format!("/{}", node.tag);
+        match self.elements.get(local(&node.tag)) {
+            Some(decl) => self.validate_element(node, &decl.ty, &path, &mut violations),
+            None => violations.push(Violation {
+                path,
+                kind: ViolationKind::UnknownRoot(node.tag.clone()),
+            }),
+        }
+        violations
+    }
+}
+
+impl Loader {
+    fn name(&self, node: &Node) -> Result<String, Error> {
+        node.attributes
+            .get("name")
+            .map(|v| v.to_owned())
+            .ok_or_else(|| invalid(format!("Missing name on {:?}", node.tag)))
+    }
+
+    fn type_ref(&mut self, name: &str) -> Result<TypeRef, Error> {
+        if prefix(name) == self.xs.as_deref() {
+            if let Some(builtin) = Builtin::from_name(local(name)) {
+                return Ok(TypeRef::Builtin(builtin));
+            }
+            if self.xs.is_some() {
+                return Err(invalid(format!("Unsupported built in type {:?}", name)));
+            }
+        }
+
+        self.type_refs.push(local(name).to_owned());
+        Ok(TypeRef::Named(local(name).to_owned()))
+    }
+
+    fn occurs(&self, node: &Node) -> Result<(usize, Option<usize>), Error> {
+        let parse = |key, default| match node.attributes.get(key) {
+            Some(v) if v == "unbounded" => Ok(None),
+            Some(v) => v
+                .parse()
+                .map(Some)
+                .map_err(|_| invalid(format!("Invalid {} {:?}", key, v))),
+            None => Ok(Some(default)),
+        };
+
+        let min = parse("minOccurs", 1)?.ok_or_else(|| invalid("Unbounded minOccurs".into()))?;
+        let max = parse("maxOccurs", 1)?;
+        Ok((min, max))
+    }
+
+    /// Reads the type of an element or attribute from either the type attribute or an anonymous type
+    fn declared_type(&mut self, node: &Node) -> Result<TypeRef, Error> {
+        if let Some(ty) = node.attributes.get("type") {
+            return self.type_ref(ty);
+        }
+
+        for child in node.children() {
+            match local(&child.tag) {
+                "complexType" => return Ok(TypeRef::Complex(Box::new(self.complex_type(child)?))),
+                "simpleType" => return Ok(TypeRef::Simple(Box::new(self.simple_type(child)?))),
+                _ => {}
+            }
+        }
+
+        Ok(TypeRef::Any)
+    }
+
+    fn element(&mut self, node: &Node) -> Result<ElementDecl, Error> {
+        Ok(ElementDecl {
+            name: self.name(node)?,
+            ty: self.declared_type(node)?,
+        })
+    }
+
+    fn particle(&mut self, node: &Node) -> Result<Option<Particle>, Error> {
+        let group = |loader: &mut Self| -> Result<Vec<Particle>, Error> {
+            let mut items = Vec::new();
+            for child in node.children() {
+                if let Some(v) = loader.particle(child)? {
+                    items.push(v);
+                }
+            }
+            Ok(items)
+        };
+
+        let kind = match local(&node.tag) {
+            "element" => match node.attributes.get("ref") {
+                Some(name) => {
+                    self.element_refs.push(local(name).to_owned());
+                    ParticleKind::Ref(local(name).to_owned())
+                }
+                None => ParticleKind::Element(self.element(node)?),
+            },
+            "sequence" => ParticleKind::Sequence(group(self)?),
+            "choice" => ParticleKind::Choice(group(self)?),
+            "all" => ParticleKind::All(group(self)?),
+            "any" => ParticleKind::Any,
+            "annotation" => return Ok(None),
+            tag => return Err(invalid(format!("Unsupported particle {:?}", tag))),
+        };
+
+        let (min, max) = self.occurs(node)?;
+        Ok(Some(Particle { kind, min, max }))
+    }
+
+    fn attribute(&mut self, node: &Node) -> Result<AttributeDecl, Error> {
+        let name = match node.attributes.get("ref") {
+            Some(v) => local(v).to_owned(),
+            None => self.name(node)?,
+        };
+
+        let ty = match self.declared_type(node)? {
+            TypeRef::Complex(_) => {
+                return Err(invalid(format!("Attribute {:?} has complex type", name)))
+            }
+            v => v,
+        };
+
+        Ok(AttributeDecl {
+            name,
+            ty,
+            required: node.attributes.get("use").map(|v| v.as_str()) == Some("required"),
+            fixed: node.attributes.get("fixed").cloned(),
+        })
+    }
+
+    /// Reads the particle and attributes from the children of a complexType or an extension
+    fn complex_content(&mut self, node: &Node, ty: &mut ComplexType) -> Result<(), Error> {
+        for child in node.children() {
+            match local(&child.tag) {
+                "attribute" => ty.attributes.push(self.attribute(child)?),
+                "sequence" | "choice" | "all" => ty.particle = self.particle(child)?,
+                "complexContent" | "simpleContent" => {
+                    let derivation = child
+                        .children()
+                        .find(|v| matches!(local(&v.tag), "extension" | "restriction"))
+                        .ok_or_else(|| invalid(format!("Missing derivation in {:?}", child.tag)))?;
+
+                    let base = derivation
+                        .attributes
+                        .get("base")
+                        .ok_or_else(|| invalid("Missing base of derivation".into()))?;
+
+                    // Restrictions of complex types repeat the content model, so the base is only kept
+                    // for extensions and simple content
+                    if local(&derivation.tag) == "extension" || local(&child.tag) == "simpleContent"
+                    {
+                        ty.base = Some(self.type_ref(base)?);
+                    }
+                    self.complex_content(derivation, ty)?;
+                }
+                "annotation" | "anyAttribute" | "attributeGroup" => {}
+                tag => {
+                    return Err(invalid(format!(
+                        "Unsupported complexType content {:?}",
+                        tag
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn complex_type(&mut self, node: &Node) -> Result<ComplexType, Error> {
+        let mut ty = ComplexType {
+            base: None,
+            particle: None,
+            attributes: Vec::new(),
+            mixed: node.attributes.get("mixed").map(|v| v.as_str()) == Some("true"),
+        };
+        self.complex_content(node, &mut ty)?;
+        Ok(ty)
+    }
+
+    fn simple_type(&mut self, node: &Node) -> Result<SimpleType, Error> {
+        let restriction = node
+            .children()
+            .find(|v| local(&v.tag) == "restriction")
+            .ok_or_else(|| invalid("Only simpleType restrictions are supported".into()))?;
+
+        let base = match restriction.attributes.get("base") {
+            Some(v) => self.type_ref(v)?,
+            None => match restriction
+                .children()
+                .find(|v| local(&v.tag) == "simpleType")
+            {
+                Some(v) => TypeRef::Simple(Box::new(self.simple_type(v)?)),
+                None => return Err(invalid("Missing base of restriction".into())),
+            },
+        };
+
+        let mut facets = Facets::default();
+        for facet in restriction.children() {
+            let value = match facet.attributes.get("value") {
+                Some(v) => v,
+                None => continue,
+            };
+
+            let count = || {
+                value
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| invalid(format!("Invalid facet value {:?}", value)))
+            };
+            let bound = || {
+                value
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| invalid(format!("Invalid facet value {:?}", value)))
+            };
+
+            match local(&facet.tag) {
+                "pattern" => facets.patterns.push(
+                    Regex::new(&format!("^(?:{})$", value))
+                        .map_err(|e| invalid(format!("Invalid pattern {:?}: {}", value, e)))?,
+                ),
+                "enumeration" => facets.enumeration.push(value.to_owned()),
+                "length" => facets.length = count()?,
+                "minLength" => facets.min_length = count()?,
+                "maxLength" => facets.max_length = count()?,
+                "minInclusive" => facets.min_inclusive = bound()?,
+                "maxInclusive" => facets.max_inclusive = bound()?,
+                "minExclusive" => facets.min_exclusive = bound()?,
+                "maxExclusive" => facets.max_exclusive = bound()?,
+                // Facets which don't restrict the supported types are ignored
+                _ => {}
+            }
+        }
+
+        Ok(SimpleType { base, facets })
+    }
+}
+
+/// The effective content of a complex type after resolving extensions
+struct Effective<'a> {
+    particles: Vec<&'a Particle>,
+    attributes: Vec<&'a AttributeDecl>,
+    simple: Option<&'a TypeRef>,
+    mixed: bool,
+}
+
+impl Schema {
+    fn complex<'a>(&'a self, ty: &'a ComplexType, effective: &mut Effective<'a>) {
+        if let Some(base) = &ty.base {
+            match base {
+                TypeRef::Named(name) => match self.types.get(name) {
+                    Some(TypeDef::Complex(v)) => self.complex(v, effective),
+                    _ => effective.simple = Some(base),
+                },
+                TypeRef::Complex(v) => self.complex(v, effective),
+                _ => effective.simple = Some(base),
+            }
+        }
+
+        effective.particles.extend(&ty.particle);
+        effective.attributes.extend(&ty.attributes);
+        effective.mixed |= ty.mixed;
+    }
+
+    fn validate_element(
+        &self,
+        node: &Node,
+        ty: &TypeRef,
+        path: &str,
+        violations: &mut Vec<Violation>,
+    ) {
+        let complex = match ty {
+            TypeRef::Any | TypeRef::Builtin(Builtin::AnyType) => return,
+            TypeRef::Complex(v) => v,
+            TypeRef::Named(name) => match &self.types[name] {
+                TypeDef::Complex(v) => v,
+                TypeDef::Simple(_) => {
+                    return self.validate_simple_element(node, ty, path, violations)
+                }
+            },
+            TypeRef::Builtin(_) | TypeRef::Simple(_) => {
+                return self.validate_simple_element(node, ty, path, violations)
+            }
+        };
+
+        let mut effective = Effective {
+            particles: Vec::new(),
+            attributes: Vec::new(),
+            simple: None,
+            mixed: false,
+        };
+        self.complex(complex, &mut effective);
+
+        let mut report = |kind| {
+            violations.push(Violation {
+                path: path.to_owned(),
+                kind,
+            })
+        };
+
+        // Attributes
+        for decl in &effective.attributes {
+            match node.attributes.get(&decl.name) {
+                None if decl.required => report(ViolationKind::MissingAttribute(decl.name.clone())),
+                None => {}
+                Some(value) => {
+                    let reason = match &decl.fixed {
+                        Some(fixed) if fixed != value => Some(format!("must be {:?}", fixed)),
+                        _ => self.check_value(&decl.ty, value).err(),
+                    };
+                    if let Some(reason) = reason {
+                        report(ViolationKind::InvalidAttributeValue {
+                            name: decl.name.clone(),
+                            value: value.clone(),
+                            reason,
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut undeclared = node
+            .attributes
+            .keys()
+            .filter(|k| !is_reserved_attribute(k))
+            .filter(|k| !effective.attributes.iter().any(|d| &d.name == *k))
+            .collect::<Vec<_>>();
+        undeclared.sort();
+        for key in undeclared {
+            report(ViolationKind::UndeclaredAttribute(key.clone()));
+        }
+
+        // Simple content
+        if let Some(simple) = effective.simple {
+            if node.child_count() > 0 {
+                report(ViolationKind::UnexpectedChildren);
+            } else if let Err(reason) = self.check_value(simple, &node.content) {
+                report(ViolationKind::InvalidValue {
+                    value: node.content.clone(),
+                    reason,
+                });
+            }
+            return;
+        }
+
+        if !effective.mixed && !node.content.trim().is_empty() {
+            report(ViolationKind::UnexpectedText);
+        }
+
+        // Element content
+        let names = node.children().map(|v| local(&v.tag)).collect::<Vec<_>>();
+        let particle = match effective.particles[..] {
+            [particle] => particle.clone(),
+            _ => Particle {
+                kind: ParticleKind::Sequence(
+                    effective.particles.iter().map(|v| (*v).clone()).collect(),
+                ),
+                min: 1,
+                max: Some(1),
+            },
+        };
+
+        if !self.match_at(&particle, &names, 0).contains(&names.len()) {
+            report(ViolationKind::InvalidContent {
+                expected: self.describe(&particle),
+                found: names.iter().map(|v| v.to_string()).collect(),
+            });
+            return;
+        }
+
+        let mut decls = HashMap::new();
+        self.collect_decls(&particle, &mut decls);
+
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for child in node.children() {
+            let index = counts.entry(&child.tag).or_default();
+            *index += 1;
+            let path = format!("{}/{}[{}]", path, child.tag, index);
+            // Children matched by a wildcard are not validated
+            if let Some(ty) = decls.get(local(&child.tag)) {
+                self.validate_element(child, ty, &path, violations);
+            }
+        }
+    }
+
+    fn validate_simple_element(
+        &self,
+        node: &Node,
+        ty: &TypeRef,
+        path: &str,
+        violations: &mut Vec<Violation>,
+    ) {
+        let mut report = |kind| {
+            violations.push(Violation {
+                path: path.to_owned(),
+                kind,
+            })
+        };
+
+        let mut undeclared = node
+            .attributes
+            .keys()
+            .filter(|k| !is_reserved_attribute(k))
+            .collect::<Vec<_>>();
+        undeclared.sort();
+        for key in undeclared {
+            report(ViolationKind::UndeclaredAttribute(key.clone()));
+        }
+
+        if node.child_count() > 0 {
+            report(ViolationKind::UnexpectedChildren);
+        } else if let Err(reason) = self.check_value(ty, &node.content) {
+            report(ViolationKind::InvalidValue {
+                value: node.content.clone(),
+                reason,
+            });
+        }
+    }
+
+    /// Checks a value against a simple type and returns the reason if it is invalid
+    fn check_value(&self, ty: &TypeRef, value: &str) -> Result<(), String> {
+        match ty {
+            TypeRef::Any => Ok(()),
+            TypeRef::Builtin(builtin) => match builtin.accepts(value) {
+                true => Ok(()),
+                false => Err(format!("is not a valid {}", builtin.name())),
+            },
+            TypeRef::Named(name) => match &self.types[name] {
+                TypeDef::Simple(v) => self.check_simple(v, value),
+                TypeDef::Complex(_) => Err(format!("can not be of complex type {:?}", name)),
+            },
+            TypeRef::Simple(v) => self.check_simple(v, value),
+            TypeRef::Complex(_) => Err("can not be of complex type".into()),
+        }
+    }
+
+    fn check_simple(&self, ty: &SimpleType, value: &str) -> Result<(), String> {
+        self.check_value(&ty.base, value)?;
+
+        let builtin = self.builtin_of(&ty.base);
+        let value = match builtin {
+            Builtin::String => value,
+            _ => value.trim(),
+        };
+
+        let facets = &ty.facets;
+        if !facets.patterns.is_empty() && !facets.patterns.iter().any(|p| p.is_match(value)) {
+            let patterns = facets
+                .patterns
+                .iter()
+                .map(|p| p.as_str())
+                .collect::<Vec<_>>();
+            return Err(format!("does not match {}", patterns.join(" or ")));
+        }
+
+        if !facets.enumeration.is_empty() && !facets.enumeration.iter().any(|v| v == value) {
+            return Err(format!("is not one of {:?}", facets.enumeration));
+        }
+
+        let len = value.chars().count();
+        if let Some(v) = facets.length.filter(|v| len != *v) {
+            return Err(format!("must have length {}", v));
+        }
+        if let Some(v) = facets.min_length.filter(|v| len < *v) {
+            return Err(format!("must have a length of at least {}", v));
+        }
+        if let Some(v) = facets.max_length.filter(|v| len > *v) {
+            return Err(format!("must have a length of at most {}", v));
+        }
+
+        if builtin.is_numeric() {
+            let number = value.parse::<f64>().unwrap_or(f64::NAN);
+            if let Some(v) = facets.min_inclusive.filter(|v| number < *v) {
+                return Err(format!("must be at least {}", v));
+            }
+            if let Some(v) = facets.max_inclusive.filter(|v| number > *v) {
+                return Err(format!("must be at most {}", v));
+            }
+            if let Some(v) = facets.min_exclusive.filter(|v| number <= *v) {
+                return Err(format!("must be greater than {}", v));
+            }
+            if let Some(v) = facets.max_exclusive.filter(|v| number >= *v) {
+                return Err(format!("must be less than {}", v));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the built in type a simple type is ultimately derived from
+    fn builtin_of(&self, ty: &TypeRef) -> Builtin {
+        match ty {
+            TypeRef::Builtin(v) => *v,
+            TypeRef::Named(name) => match &self.types[name] {
+                TypeDef::Simple(v) => self.builtin_of(&v.base),
+                TypeDef::Complex(_) => Builtin::AnyType,
+            },
+            TypeRef::Simple(v) => self.builtin_of(&v.base),
+            _ => Builtin::AnyType,
+        }
+    }
+
+    /// Collects the types of all elements declared in a content model by name
+    fn collect_decls<'a>(
+        &'a self,
+        particle: &'a Particle,
+        decls: &mut HashMap<&'a str, &'a TypeRef>,
+    ) {
+        match &particle.kind {
+            ParticleKind::Element(decl) => {
+                decls.insert(&decl.name, &decl.ty);
+            }
+            ParticleKind::Ref(name) => {
+                decls.insert(name, &self.elements[name].ty);
+            }
+            ParticleKind::Sequence(items)
+            | ParticleKind::Choice(items)
+            | ParticleKind::All(items) => items.iter().for_each(|v| self.collect_decls(v, decls)),
+            ParticleKind::Any => {}
+        }
+    }
+
+    fn element_name<'a>(&'a self, particle: &'a Particle) -> Option<&'a str> {
+        match &particle.kind {
+            ParticleKind::Element(decl) => Some(&decl.name),
+            ParticleKind::Ref(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Returns all positions in names where a match of the particle starting at pos can end
+    fn match_at(&self, particle: &Particle, names: &[&str], pos: usize) -> Vec<usize> {
+        let mut ends = Vec::new();
+        if particle.min == 0 {
+            ends.push(pos);
+        }
+
+        let mut frontier = vec![pos];
+        let mut count = 0;
+        while !frontier.is_empty() && particle.max.map_or(true, |max| count < max) {
+            count += 1;
+            let mut next = Vec::new();
+            for &p in &frontier {
+                for end in self.match_once(particle, names, p) {
+                    if !next.contains(&end) {
+                        next.push(end);
+                    }
+                }
+            }
+
+            if count >= particle.min {
+                // Once the minimum is reached only new positions need to be explored further
+                next.retain(|v| !ends.contains(v));
+                ends.extend(&next);
+            }
+            frontier = next;
+        }
+
+        ends
+    }
+
+    fn match_once(&self, particle: &Particle, names: &[&str], pos: usize) -> Vec<usize> {
+        match &particle.kind {
+            ParticleKind::Element(_) | ParticleKind::Ref(_) => match names.get(pos) {
+                Some(name) if Some(*name) == self.element_name(particle) => vec![pos + 1],
+                _ => Vec::new(),
+            },
+            ParticleKind::Any => match names.get(pos) {
+                Some(_) => vec![pos + 1],
+                None => Vec::new(),
+            },
+            ParticleKind::Sequence(items) => items.iter().fold(vec![pos], |ends, item| {
+                let mut next = Vec::new();
+                for p in ends {
+                    for end in self.match_at(item, names, p) {
+                        if !next.contains(&end) {
+                            next.push(end);
+                        }
+                    }
+                }
+                next
+            }),
+            ParticleKind::Choice(items) => {
+                let mut ends = Vec::new();
+                for end in items
+                    .iter()
+                    .flat_map(|item| self.match_at(item, names, pos))
+                {
+                    if !ends.contains(&end) {
+                        ends.push(end);
+                    }
+                }
+                ends
+            }
+            ParticleKind::All(items) => {
+                // Each element may appear at most once in any order
+                let mut used = vec![false; items.len()];
+                let mut p = pos;
+                while let Some(name) = names.get(p) {
+                    match items
+                        .iter()
+                        .enumerate()
+                        .position(|(i, v)| !used[i] && self.element_name(v) == Some(*name))
+                    {
+                        Some(i) => used[i] = true,
+                        None => break,
+                    }
+                    p += 1;
+                }
+
+                let complete = items
+                    .iter()
+                    .zip(&used)
+                    .all(|(item, used)| *used || item.min == 0);
+                match complete {
+                    true => vec![p],
+                    false => Vec::new(),
+                }
+            }
+        }
+    }
+
+    /// Describes a content model for diagnostics, e.g; (to{1,}, from, heading?)
+    fn describe(&self, particle: &Particle) -> String {
+        let join = |items: &[Particle], sep: &str| {
+            items
+                .iter()
+                .map(|v| self.describe(v))
+                .collect::<Vec<_>>()
+                .join(sep)
+        };
+
+        let base = match &particle.kind {
+            ParticleKind::Element(_) | ParticleKind::Ref(_) => {
+                self.element_name(particle).unwrap_or_default().to_owned()
+            }
+            ParticleKind::Any => "*".to_owned(),
+            ParticleKind::Sequence(items) => format!("({})", join(items, ", ")),
+            ParticleKind::Choice(items) => format!("({})", join(items, " | ")),
+            ParticleKind::All(items) => format!("({})", join(items, " & ")),
+        };
+
+        match (particle.min, particle.max) {
+            (1, Some(1)) => base,
+            (0, Some(1)) => format!("{}?", base),
+            (0, None) => format!("{}*", base),
+            (1, None) => format!("{}+", base),
+            (min, None) => format!("{}{{{},}}", base, min),
+            (min, Some(max)) => format!("{}{{{},{}}}", base, min, max),
+        }
+    }
+}
@@ -0,0 +1,978 @@
+//! This is a module providing a processor for a subset of XSLT 1.0
+//! Supported are templates with match patterns, priorities, modes and names, `apply-templates`,
+//! `call-template`, `value-of`, `for-each`, `sort`, `if`, `choose`, `variable`, `param`, `copy`,
+//! `copy-of`, `element`, `attribute` and `text`, literal result elements with attribute value
+//! templates, and most of the XPath 1.0 expression language
+//! Nodes keep their text as a single content after their child elements, so text in a template is
+//! output after the instructions of the same element. Use `xsl:text` to place text between elements
+//! Templates can be nested 256 deep, deeper recursion is reported as an error instead of overflowing
+//! the stack
+//! ```
+//! let stylesheet = simple_xml::from_string(
+//!     r#"<xsl:stylesheet version="1.0" xmlns:xsl="http://www.w3.org/1999/XSL/Transform">
+//!         <xsl:template match="/catalog">
+//!             <books count="{count(book)}">
+//!                 <xsl:apply-templates select="book[@price &lt; 20]"/>
+//!             </books>
+//!         </xsl:template>
+//!         <xsl:template match="book">
+//!             <title><xsl:value-of select="title"/></title>
+//!         </xsl:template>
+//!     </xsl:stylesheet>"#,
+//! )
+//! .unwrap();
+//! let source = simple_xml::from_string(
+//!     r#"<catalog>
+//!         <book price="15"><title>Dune</title></book>
+//!         <book price="45"><title>Hyperion</title></book>
+//!     </catalog>"#,
+//! )
+//! .unwrap();
+//!
+//! let output = simple_xml::xslt::transform(&stylesheet, &source).unwrap();
+//! assert_eq!(output.to_string(), r#"<books count="2"><title>Dune</title></books>"#);
+//! ```
+
+use std::cmp::Ordering;
+
+use crate::xpath::{self, Context, Expr, Item, Value};
+use crate::{entity, is_valid_name, Error, Node};
+
+/// The namespace of XSLT instructions
+pub const XSL_NAMESPACE: &str = "http://www.w3.org/1999/XSL/Transform";
+
+/// The maximum number of nested template instantiations, low enough for the 2 MiB stack of spawned threads
+const MAX_DEPTH: usize = 256;
+
+/// A compiled stylesheet which can transform any number of documents
+#[derive(Debug, Clone)]
+pub struct Stylesheet {
+    /// Templates ordered by descending priority, then by descending stylesheet order
+    templates: Vec<Template>,
+    /// Top level variables and parameters
+    globals: Vec<Instruction>,
+}
+
+#[derive(Debug, Clone)]
+struct Template {
+    pattern: Option<Expr>,
+    name: Option<String>,
+    mode: Option<String>,
+    priority: f64,
+    index: usize,
+    body: Vec<Instruction>,
+}
+
+#[derive(Debug, Clone)]
+enum Instruction {
+    /// Raw text, already escaped
+    Text(String),
+    Literal {
+        tag: String,
+        attributes: Vec<(String, Avt)>,
+        body: Vec<Instruction>,
+    },
+    ValueOf(Expr),
+    ApplyTemplates {
+        select: Option<Expr>,
+        mode: Option<String>,
+        sorts: Vec<Sort>,
+        params: Vec<(String, Binding)>,
+    },
+    CallTemplate {
+        name: String,
+        params: Vec<(String, Binding)>,
+    },
+    ForEach {
+        select: Expr,
+        sorts: Vec<Sort>,
+        body: Vec<Instruction>,
+    },
+    Choose {
+        branches: Vec<(Expr, Vec<Instruction>)>,
+        otherwise: Vec<Instruction>,
+    },
+    Variable(String, Binding),
+    Param(String, Binding),
+    CopyOf(Expr),
+    Copy(Vec<Instruction>),
+    Element(Avt, Vec<Instruction>),
+    Attribute(Avt, Vec<Instruction>),
+}
+
+/// The value of a variable or parameter
+#[derive(Debug, Clone)]
+enum Binding {
+    Select(Expr),
+    Body(Vec<Instruction>),
+}
+
+#[derive(Debug, Clone)]
+struct Sort {
+    select: Expr,
+    descending: bool,
+    numeric: bool,
+}
+
+/// An attribute value template, text with expressions in braces
+#[derive(Debug, Clone)]
+struct Avt(Vec<AvtPart>);
+
+#[derive(Debug, Clone)]
+enum AvtPart {
+    Text(String),
+    Expr(Expr),
+}
+
+fn invalid(message: String) -> Error {
+    Error::InvalidStylesheet(message)
+}
+
+/// Returns the decoded value of an attribute of a stylesheet element
+fn attribute(node: &Node, key: &str) -> Option<String> {
+    node.get_attribute(key).map(|v| entity::decode(v))
+}
+
+fn required(node: &Node, key: &str) -> Result<String, Error> {
+    attribute(node, key)
+        .ok_or_else(|| invalid(format!("<{}> is missing the {} attribute", node.tag, key)))
+}
+
+fn expression(node: &Node, key: &str) -> Result<Expr, Error> {
+    Expr::parse(&required(node, key)?)
+}
+
+/// Compiles stylesheet elements, which are recognized by the prefix bound to the XSLT namespace
+struct Compiler {
+    prefix: String,
+}
+
+impl Compiler {
+    fn instruction_name<'a>(&self, node: &'a Node) -> Option<&'a str> {
+        self.local_name(&node.tag)
+    }
+
+    /// Returns the local part of a name with the XSLT prefix
+    fn local_name<'a>(&self, name: &'a str) -> Option<&'a str> {
+        name.strip_prefix(self.prefix.as_str())
+            .and_then(|v| v.strip_prefix(':'))
+    }
+
+    /// Compiles the children and content of an element
+    fn body(&self, node: &Node) -> Result<Vec<Instruction>, Error> {
+        let mut body = node
+            .children()
+            .filter(|v| !matches!(self.instruction_name(v), Some("sort" | "with-param")))
+            .map(|v| self.instruction(v))
+            .collect::<Result<Vec<_>, Error>>()?;
+        if !node.content.is_empty() {
+            body.push(Instruction::Text(node.content.clone()));
+        }
+        Ok(body)
+    }
+
+    fn binding(&self, node: &Node) -> Result<Binding, Error> {
+        match attribute(node, "select") {
+            Some(v) => Ok(Binding::Select(Expr::parse(&v)?)),
+            None => Ok(Binding::Body(self.body(node)?)),
+        }
+    }
+
+    fn params(&self, node: &Node) -> Result<Vec<(String, Binding)>, Error> {
+        node.children()
+            .filter(|v| self.instruction_name(v) == Some("with-param"))
+            .map(|v| Ok((required(v, "name")?, self.binding(v)?)))
+            .collect()
+    }
+
+    fn sorts(&self, node: &Node) -> Result<Vec<Sort>, Error> {
+        node.children()
+            .filter(|v| self.instruction_name(v) == Some("sort"))
+            .map(|v| {
+                Ok(Sort {
+                    select: Expr::parse(&attribute(v, "select").unwrap_or_else(|| ".".to_owned()))?,
+                    descending: attribute(v, "order").as_deref() == Some("descending"),
+                    numeric: attribute(v, "data-type").as_deref() == Some("number"),
+                })
+            })
+            .collect()
+    }
+
+    fn instruction(&self, node: &Node) -> Result<Instruction, Error> {
+        let name = match self.instruction_name(node) {
+            Some(v) => v,
+            None => {
+                let xmlns = format!("xmlns:{}", self.prefix);
+                let attributes = node
+                    .attributes
+                    .iter()
+                    .filter(|(k, _)| **k != xmlns && self.local_name(k).is_none())
+                    .map(|(k, v)| Ok((k.clone(), Avt::parse(&entity::decode(v))?)))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                return Ok(Instruction::Literal {
+                    tag: node.tag.clone(),
+                    attributes,
+                    body: self.body(node)?,
+                });
+            }
+        };
+
+        Ok(match name {
+            "text" => Instruction::Text(node.content.clone()),
+            "value-of" => Instruction::ValueOf(expression(node, "select")?),
+            "apply-templates" => Instruction::ApplyTemplates {
+                select: attribute(node, "select")
+                    .map(|v| Expr::parse(&v))
+                    .transpose()?,
+                mode: attribute(node, "mode"),
+                sorts: self.sorts(node)?,
+                params: self.params(node)?,
+            },
+            "call-template" => Instruction::CallTemplate {
+                name: required(node, "name")?,
+                params: self.params(node)?,
+            },
+            "for-each" => Instruction::ForEach {
+                select: expression(node, "select")?,
+                sorts: self.sorts(node)?,
+                body: self.body(node)?,
+            },
+            "if" => Instruction::Choose {
+                branches: vec![(expression(node, "test")?, self.body(node)?)],
+                otherwise: Vec::new(),
+            },
+            "choose" => {
+                let mut branches = Vec::new();
+                let mut otherwise = Vec::new();
+                for child in node.children() {
+                    match self.instruction_name(child) {
+                        Some("when") => {
+                            branches.push((expression(child, "test")?, self.body(child)?))
+                        }
+                        Some("otherwise") => otherwise = self.body(child)?,
+                        _ => {
+                            return Err(invalid(format!(
+                                "unexpected <{}> in <{}>",
+                                child.tag, node.tag
+                            )))
+                        }
+                    }
+                }
+                Instruction::Choose {
+                    branches,
+                    otherwise,
+                }
+            }
+            "variable" => Instruction::Variable(required(node, "name")?, self.binding(node)?),
+            "param" => Instruction::Param(required(node, "name")?, self.binding(node)?),
+            "copy-of" => Instruction::CopyOf(expression(node, "select")?),
+            "copy" => Instruction::Copy(self.body(node)?),
+            "element" => {
+                Instruction::Element(Avt::parse(&required(node, "name")?)?, self.body(node)?)
+            }
+            "attribute" => {
+                Instruction::Attribute(Avt::parse(&required(node, "name")?)?, self.body(node)?)
+            }
+            _ => return Err(invalid(format!("unsupported instruction <{}>", node.tag))),
+        })
+    }
+}
+
+impl Avt {
+    fn parse(text: &str) -> Result<Avt, Error> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut rest = text;
+        while let Some(i) = rest.find(['{', '}']) {
+            literal.push_str(&rest[..i]);
+            let c = rest.as_bytes()[i];
+            rest = &rest[i + 1..];
+
+            // Doubled braces are literal braces
+            if rest.as_bytes().first() == Some(&c) {
+                literal.push(c as char);
+                rest = &rest[1..];
+                continue;
+            }
+            if c == b'}' {
+                return Err(invalid(format!("unmatched }} in {:?}", text)));
+            }
+
+            // Find the closing brace outside of string literals
+            let mut quote = None;
+            let end = rest.char_indices().find_map(|(i, c)| match (c, quote) {
+                ('"' | '\'', None) => {
+                    quote = Some(c);
+                    None
+                }
+                (c, Some(q)) if c == q => {
+                    quote = None;
+                    None
+                }
+                ('}', None) => Some(i),
+                _ => None,
+            });
+            let end = end.ok_or_else(|| invalid(format!("unmatched {{ in {:?}", text)))?;
+
+            if !literal.is_empty() {
+                parts.push(AvtPart::Text(std::mem::take(&mut literal)));
+            }
+            parts.push(AvtPart::Expr(Expr::parse(&rest[..end])?));
+            rest = &rest[end + 1..];
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            parts.push(AvtPart::Text(literal));
+        }
+        Ok(Avt(parts))
+    }
+}
+
+impl Stylesheet {
+    /// Compiles a stylesheet from its `xsl:stylesheet` or `xsl:transform` root element
+    /// Returns an Err of InvalidStylesheet if the stylesheet is malformed or uses unsupported instructions
+    pub fn parse(stylesheet: &Node) -> Result<Stylesheet, Error> {
+        let (prefix, name) = stylesheet
+            .tag
+            .split_once(':')
+            .unwrap_or(("", &stylesheet.tag));
+        let namespace = match prefix {
+            "" => stylesheet.get_attribute("xmlns"),
+            prefix => stylesheet.get_attribute(&format!("xmlns:{}", prefix)),
+        };
+        if !matches!(name, "stylesheet" | "transform")
+            || namespace.map(String::as_str) != Some(XSL_NAMESPACE)
+        {
+            return Err(invalid(format!(
+                "expected an xsl:stylesheet root element, found <{}>",
+                stylesheet.tag
+            )));
+        }
+
+        let compiler = Compiler {
+            prefix: prefix.to_owned(),
+        };
+        let mut templates = Vec::new();
+        let mut globals = Vec::new();
+        for (index, node) in stylesheet.children().enumerate() {
+            match compiler.instruction_name(node) {
+                Some("template") => {
+                    let name = attribute(node, "name");
+                    let mode = attribute(node, "mode");
+                    let body = compiler.body(node)?;
+                    let priority = attribute(node, "priority")
+                        .map(|v| {
+                            v.trim()
+                                .parse::<f64>()
+                                .map_err(|_| invalid(format!("invalid priority {:?}", v)))
+                        })
+                        .transpose()?;
+
+                    let pattern = match attribute(node, "match") {
+                        Some(pattern) => pattern,
+                        None if name.is_some() => {
+                            templates.push(Template {
+                                pattern: None,
+                                name,
+                                mode,
+                                priority: 0.0,
+                                index,
+                                body,
+                            });
+                            continue;
+                        }
+                        None => {
+                            return Err(invalid(
+                                "<xsl:template> needs a match or name attribute".to_owned(),
+                            ))
+                        }
+                    };
+
+                    // Each alternative of a union is a template of its own
+                    for (i, pattern) in Expr::parse(&pattern)?
+                        .alternatives()
+                        .into_iter()
+                        .enumerate()
+                    {
+                        templates.push(Template {
+                            priority: priority.unwrap_or_else(|| pattern.default_priority()),
+                            pattern: Some(pattern),
+                            name: name.clone().filter(|_| i == 0),
+                            mode: mode.clone(),
+                            index,
+                            body: body.clone(),
+                        });
+                    }
+                }
+                Some("variable" | "param") => globals.push(compiler.instruction(node)?),
+                // Output and whitespace settings are not supported and have no effect
+                Some("output" | "strip-space" | "preserve-space") => {}
+                _ => {
+                    return Err(invalid(format!(
+                        "unsupported top level element <{}>",
+                        node.tag
+                    )))
+                }
+            }
+        }
+
+        templates.sort_by(|a, b| {
+            b.priority
+                .partial_cmp(&a.priority)
+                .unwrap_or(Ordering::Equal)
+                .then(b.index.cmp(&a.index))
+        });
+
+        Ok(Stylesheet { templates, globals })
+    }
+
+    /// Transforms a document
+    /// Returns the single root element of the output, or a node with an empty tag containing the
+    /// output if it is not exactly one element
+    /// Returns an Err of InvalidStylesheet for errors while evaluating expressions
+    pub fn transform(&self, source: &Node) -> Result<Node, Error> {
+        let mut transformer = Transformer {
+            stylesheet: self,
+            root: source,
+            variables: Vec::new(),
+            globals: Vec::new(),
+            params: Vec::new(),
+            depth: 0,
+        };
+
+        let focus = Focus::new(Item::Document, 1, 1);
+        let mut output = vec![crate::new("", String::new())];
+        for global in &self.globals {
+            transformer.instruction(global, &focus, &mut output)?;
+        }
+        transformer.globals = std::mem::take(&mut transformer.variables);
+        transformer.apply(Item::Document, 1, 1, None, Vec::new(), &mut output)?;
+
+        let mut output = output.pop().expect("Output stack is never empty");
+        match output.child_count() == 1 && output.content.trim().is_empty() {
+            true => Ok(output.remove_at(0)),
+            false => Ok(output),
+        }
+    }
+}
+
+/// Compiles a stylesheet and transforms a document with it
+pub fn transform(stylesheet: &Node, source: &Node) -> Result<Node, Error> {
+    Stylesheet::parse(stylesheet)?.transform(source)
+}
+
+/// The item being processed and its position in the current node list
+struct Focus {
+    item: Item,
+    position: usize,
+    size: usize,
+    current: Item,
+}
+
+impl Focus {
+    fn new(item: Item, position: usize, size: usize) -> Focus {
+        Focus {
+            current: item.clone(),
+            item,
+            position,
+            size,
+        }
+    }
+}
+
+struct Transformer<'a> {
+    stylesheet: &'a Stylesheet,
+    root: &'a Node,
+    /// The variables in scope
+    variables: Vec<(String, Value)>,
+    /// The values of the top level variables
+    globals: Vec<(String, Value)>,
+    /// The parameters passed to the template being instantiated
+    params: Vec<(String, Value)>,
+    depth: usize,
+}
+
+impl Transformer<'_> {
+    fn context<'b>(&'b self, focus: &Focus, variables: &'b [(String, Value)]) -> Context<'b> {
+        Context {
+            root: self.root,
+            item: focus.item.clone(),
+            position: focus.position,
+            size: focus.size,
+            current: focus.current.clone(),
+            variables,
+        }
+    }
+
+    fn evaluate(&self, expr: &Expr, focus: &Focus) -> Result<Value, Error> {
+        expr.evaluate(&self.context(focus, &self.variables))
+    }
+
+    /// Executes instructions, writing to the element on top of the output stack
+    fn execute(
+        &mut self,
+        body: &[Instruction],
+        focus: &Focus,
+        output: &mut Vec<Node>,
+    ) -> Result<(), Error> {
+        let scope = self.variables.len();
+        for instruction in body {
+            self.instruction(instruction, focus, output)?;
+        }
+        // Variables are only visible to the following siblings and their descendants
+        self.variables.truncate(scope);
+        Ok(())
+    }
+
+    fn instruction(
+        &mut self,
+        instruction: &Instruction,
+        focus: &Focus,
+        output: &mut Vec<Node>,
+    ) -> Result<(), Error> {
+        match instruction {
+            Instruction::Text(text) => {
+                top(output).content.push_str(text);
+                Ok(())
+            }
+            Instruction::ValueOf(expr) => self.value_of(expr, focus, output),
+            Instruction::Literal {
+                tag,
+                attributes,
+                body,
+            } => self.literal(tag, attributes, body, focus, output),
+            Instruction::Element(name, body) => self.computed_element(name, body, focus, output),
+            Instruction::Attribute(name, body) => self.attribute(name, body, focus, output),
+            Instruction::ApplyTemplates {
+                select,
+                mode,
+                sorts,
+                params,
+            } => self.apply_templates(
+                select.as_ref(),
+                mode.as_deref(),
+                sorts,
+                params,
+                focus,
+                output,
+            ),
+            Instruction::CallTemplate { name, params } => {
+                self.call_template(name, params, focus, output)
+            }
+            Instruction::ForEach {
+                select,
+                sorts,
+                body,
+            } => self.for_each(select, sorts, body, focus, output),
+            Instruction::Choose {
+                branches,
+                otherwise,
+            } => self.choose(branches, otherwise, focus, output),
+            Instruction::Variable(name, binding) => self.variable(name, binding, false, focus),
+            Instruction::Param(name, binding) => self.variable(name, binding, true, focus),
+            Instruction::CopyOf(expr) => self.copy_of(expr, focus, output),
+            Instruction::Copy(body) => self.shallow_copy(body, focus, output),
+        }
+    }
+
+    fn value_of(&self, expr: &Expr, focus: &Focus, output: &mut [Node]) -> Result<(), Error> {
+        let value = self.evaluate(expr, focus)?.string(self.root);
+        top(output).content.push_str(&entity::escape_text(&value));
+        Ok(())
+    }
+
+    /// Binds a variable, parameters take the value passed to the template if there is one
+    fn variable(
+        &mut self,
+        name: &str,
+        binding: &Binding,
+        param: bool,
+        focus: &Focus,
+    ) -> Result<(), Error> {
+        let passed = self
+            .params
+            .iter()
+            .find(|(v, _)| v == name)
+            .filter(|_| param);
+        let value = match passed {
+            Some((_, value)) => value.clone(),
+            None => self.bind(binding, focus)?,
+        };
+        self.variables.push((name.to_owned(), value));
+        Ok(())
+    }
+
+    /// Adds a literal result element with its attribute value templates evaluated
+    fn literal(
+        &mut self,
+        tag: &str,
+        attributes: &[(String, Avt)],
+        body: &[Instruction],
+        focus: &Focus,
+        output: &mut Vec<Node>,
+    ) -> Result<(), Error> {
+        let mut node = crate::new(tag, String::new());
+        for (key, value) in attributes {
+            let value = self.avt(value, focus)?;
+            node.add_attribute(key, &entity::escape_attribute(&value));
+        }
+        self.element(node, body, focus, output)
+    }
+
+    /// Adds an element with a computed name
+    fn computed_element(
+        &mut self,
+        name: &Avt,
+        body: &[Instruction],
+        focus: &Focus,
+        output: &mut Vec<Node>,
+    ) -> Result<(), Error> {
+        let name = self.avt(name, focus)?;
+        if !is_valid_name(&name) {
+            return Err(invalid(format!("invalid element name {:?}", name)));
+        }
+        self.element(crate::new(&name, String::new()), body, focus, output)
+    }
+
+    fn attribute(
+        &mut self,
+        name: &Avt,
+        body: &[Instruction],
+        focus: &Focus,
+        output: &mut [Node],
+    ) -> Result<(), Error> {
+        let name = self.avt(name, focus)?;
+        if !is_valid_name(&name) {
+            return Err(invalid(format!("invalid attribute name {:?}", name)));
+        }
+        let value = self.fragment(body, focus)?.string(self.root);
+        // Attributes can only be added to elements
+        let node = top(output);
+        if !node.tag.is_empty() {
+            node.add_attribute(&name, &entity::escape_attribute(&value));
+        }
+        Ok(())
+    }
+
+    fn apply_templates(
+        &mut self,
+        select: Option<&Expr>,
+        mode: Option<&str>,
+        sorts: &[Sort],
+        params: &[(String, Binding)],
+        focus: &Focus,
+        output: &mut Vec<Node>,
+    ) -> Result<(), Error> {
+        let items = match select {
+            Some(select) => self.evaluate(select, focus)?.into_nodes()?,
+            None => xpath::children(self.root, &focus.item),
+        };
+        let items = self.sort(items, sorts, focus)?;
+        let params = self.bind_all(params, focus)?;
+        let size = items.len();
+        for (i, item) in items.into_iter().enumerate() {
+            self.apply(item, i + 1, size, mode, params.clone(), output)?;
+        }
+        Ok(())
+    }
+
+    fn call_template(
+        &mut self,
+        name: &str,
+        params: &[(String, Binding)],
+        focus: &Focus,
+        output: &mut Vec<Node>,
+    ) -> Result<(), Error> {
+        let stylesheet = self.stylesheet;
+        let template = stylesheet
+            .templates
+            .iter()
+            .find(|v| v.name.as_deref() == Some(name))
+            .ok_or_else(|| invalid(format!("no template named {:?}", name)))?;
+        let params = self.bind_all(params, focus)?;
+        self.instantiate(template, focus, params, output)
+    }
+
+    fn for_each(
+        &mut self,
+        select: &Expr,
+        sorts: &[Sort],
+        body: &[Instruction],
+        focus: &Focus,
+        output: &mut Vec<Node>,
+    ) -> Result<(), Error> {
+        let items = self.evaluate(select, focus)?.into_nodes()?;
+        let items = self.sort(items, sorts, focus)?;
+        let size = items.len();
+        for (i, item) in items.into_iter().enumerate() {
+            self.execute(body, &Focus::new(item, i + 1, size), output)?;
+        }
+        Ok(())
+    }
+
+    /// Executes the first branch with a true test, or otherwise if there is none
+    fn choose(
+        &mut self,
+        branches: &[(Expr, Vec<Instruction>)],
+        otherwise: &[Instruction],
+        focus: &Focus,
+        output: &mut Vec<Node>,
+    ) -> Result<(), Error> {
+        for (test, body) in branches {
+            if self.evaluate(test, focus)?.boolean() {
+                return self.execute(body, focus, output);
+            }
+        }
+        self.execute(otherwise, focus, output)
+    }
+
+    fn copy_of(&mut self, expr: &Expr, focus: &Focus, output: &mut [Node]) -> Result<(), Error> {
+        match self.evaluate(expr, focus)? {
+            Value::Nodes(items) => {
+                for item in items {
+                    self.copy(&item, output, true);
+                }
+            }
+            Value::Fragment(fragment) => {
+                let node = top(output);
+                let texts = fragment.texts();
+                for (text, child) in texts.iter().zip(fragment.children()) {
+                    node.content.push_str(text);
+                    node.push_node(child.clone());
+                }
+                node.content
+                    .push_str(texts.last().expect("Texts are never empty"));
+            }
+            value => {
+                let value = value.string(self.root);
+                top(output).content.push_str(&entity::escape_text(&value));
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies the current item without its attributes and children, then executes the body
+    fn shallow_copy(
+        &mut self,
+        body: &[Instruction],
+        focus: &Focus,
+        output: &mut Vec<Node>,
+    ) -> Result<(), Error> {
+        let location = match &focus.item {
+            Item::Element(location) => location,
+            Item::Document => return self.execute(body, focus, output),
+            item => {
+                self.copy(item, output, false);
+                return Ok(());
+            }
+        };
+        let node = match location.get(self.root) {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        let mut copy = crate::new(&node.tag, String::new());
+        // Namespace declarations are copied along with the element
+        for (key, value) in &node.attributes {
+            if key == "xmlns" || key.starts_with("xmlns:") {
+                copy.add_attribute(key, value);
+            }
+        }
+        self.element(copy, body, focus, output)
+    }
+
+    /// Adds an element to the output after executing its body
+    fn element(
+        &mut self,
+        node: Node,
+        body: &[Instruction],
+        focus: &Focus,
+        output: &mut Vec<Node>,
+    ) -> Result<(), Error> {
+        output.push(node);
+        let result = self.execute(body, focus, output);
+        let node = output.pop().expect("Output stack is never empty");
+        top(output).push_node(node);
+        result
+    }
+
+    /// Copies a source item to the output, with its descendants if deep
+    fn copy(&self, item: &Item, output: &mut [Node], deep: bool) {
+        let node = top(output);
+        match item {
+            Item::Document => {
+                if let Some(root) = deep.then_some(self.root) {
+                    node.push_node(root.clone());
+                }
+            }
+            Item::Element(location) => {
+                if let Some(source) = location.get(self.root) {
+                    node.push_node(source.clone());
+                }
+            }
+            Item::Attribute(location, name) => {
+                let value = location.get(self.root).and_then(|v| v.get_attribute(name));
+                if let (Some(value), false) = (value, node.tag.is_empty()) {
+                    node.add_attribute(name, value);
+                }
+            }
+            Item::Text(location) => {
+                if let Some(source) = location.get(self.root) {
+                    node.content.push_str(&source.content);
+                }
+            }
+        }
+    }
+
+    /// Evaluates instructions into a tree fragment
+    fn fragment(&mut self, body: &[Instruction], focus: &Focus) -> Result<Value, Error> {
+        let mut output = vec![crate::new("", String::new())];
+        self.execute(body, focus, &mut output)?;
+        Ok(Value::Fragment(
+            output.pop().expect("Output stack is never empty"),
+        ))
+    }
+
+    fn bind(&mut self, binding: &Binding, focus: &Focus) -> Result<Value, Error> {
+        match binding {
+            Binding::Select(expr) => self.evaluate(expr, focus),
+            Binding::Body(body) => self.fragment(body, focus),
+        }
+    }
+
+    fn bind_all(
+        &mut self,
+        params: &[(String, Binding)],
+        focus: &Focus,
+    ) -> Result<Vec<(String, Value)>, Error> {
+        params
+            .iter()
+            .map(|(name, binding)| Ok((name.clone(), self.bind(binding, focus)?)))
+            .collect()
+    }
+
+    fn avt(&self, avt: &Avt, focus: &Focus) -> Result<String, Error> {
+        avt.0.iter().try_fold(String::new(), |mut output, part| {
+            match part {
+                AvtPart::Text(text) => output.push_str(text),
+                AvtPart::Expr(expr) => {
+                    output.push_str(&self.evaluate(expr, focus)?.string(self.root))
+                }
+            }
+            Ok(output)
+        })
+    }
+
+    /// Sorts items by the sort keys, keeping document order for equal keys
+    fn sort(&self, items: Vec<Item>, sorts: &[Sort], focus: &Focus) -> Result<Vec<Item>, Error> {
+        if sorts.is_empty() {
+            return Ok(items);
+        }
+
+        let size = items.len();
+        let mut keyed = items
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let focus = Focus {
+                    item,
+                    position: i + 1,
+                    size,
+                    current: focus.current.clone(),
+                };
+                let keys = sorts
+                    .iter()
+                    .map(|sort| self.evaluate(&sort.select, &focus))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                Ok((keys, focus.item))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        keyed.sort_by(|a, b| {
+            sorts
+                .iter()
+                .zip(a.0.iter().zip(&b.0))
+                .map(|(sort, (a, b))| {
+                    let ordering = xpath::compare_keys(a, b, self.root, sort.numeric);
+                    match sort.descending {
+                        true => ordering.reverse(),
+                        false => ordering,
+                    }
+                })
+                .find(|v| *v != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+        Ok(keyed.into_iter().map(|(_, item)| item).collect())
+    }
+
+    /// Applies the best matching template to an item, or the built-in template if none match
+    fn apply(
+        &mut self,
+        item: Item,
+        position: usize,
+        size: usize,
+        mode: Option<&str>,
+        params: Vec<(String, Value)>,
+        output: &mut Vec<Node>,
+    ) -> Result<(), Error> {
+        let focus = Focus::new(item, position, size);
+        let context = self.context(&focus, &self.globals);
+        let stylesheet = self.stylesheet;
+
+        let mut found = None;
+        for template in &stylesheet.templates {
+            if let (Some(pattern), true) = (&template.pattern, template.mode.as_deref() == mode) {
+                if pattern.matches(&context, &focus.item)? {
+                    found = Some(template);
+                    break;
+                }
+            }
+        }
+
+        match (found, &focus.item) {
+            (Some(template), _) => self.instantiate(template, &focus, params, output),
+            (None, Item::Document | Item::Element(_)) => {
+                let children = xpath::children(self.root, &focus.item);
+                let size = children.len();
+                for (i, child) in children.into_iter().enumerate() {
+                    self.apply(child, i + 1, size, mode, Vec::new(), output)?;
+                }
+                Ok(())
+            }
+            (None, item) => {
+                let value = xpath::string_value(self.root, item);
+                top(output).content.push_str(&entity::escape_text(&value));
+                Ok(())
+            }
+        }
+    }
+
+    fn instantiate(
+        &mut self,
+        template: &Template,
+        focus: &Focus,
+        params: Vec<(String, Value)>,
+        output: &mut Vec<Node>,
+    ) -> Result<(), Error> {
+        if self.depth >= MAX_DEPTH {
+            return Err(invalid(format!(
+                "exceeded the maximum of {} nested templates",
+                MAX_DEPTH
+            )));
+        }
+
+        // Templates only see the top level variables and their own parameters
+        self.depth += 1;
+        let variables = std::mem::replace(&mut self.variables, self.globals.clone());
+        let params = std::mem::replace(&mut self.params, params);
+        let result = self.execute(&template.body, focus, output);
+        self.variables = variables;
+        self.params = params;
+        self.depth -= 1;
+        result
+    }
+}
+
+/// Returns the element being written
+fn top(output: &mut [Node]) -> &mut Node {
+    output.last_mut().expect("Output stack is never empty")
+}
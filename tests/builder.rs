@@ -0,0 +1,58 @@
+#[cfg(test)]
+mod tests {
+    use simple_xml::Node;
+
+    #[test]
+    fn build_in_order() {
+        let graph = Node::builder("graph")
+            .attr("directed", true)
+            .child(Node::builder("node").attr("id", "n1").text("Start"))
+            .child(Node::builder("edge").attr("from", "n1").attr("to", "n2"))
+            .children((2..4).map(|i| Node::builder("node").attr("id", format!("n{}", i))))
+            .build();
+
+        assert_eq!(graph.attributes["directed"], "true");
+        assert_eq!(graph["node"].len(), 3);
+        assert_eq!(graph["node"][0].content, "Start");
+        assert_eq!(graph["node"][2].attributes["id"], "n3");
+
+        let tags = graph.children().map(|n| n.tag.as_str()).collect::<Vec<_>>();
+        assert_eq!(tags, ["node", "edge", "node", "node"]);
+    }
+
+    #[test]
+    fn round_trip_keeps_order() {
+        let note = simple_xml::from_file("./examples/note.xml").expect("Failed to parse note");
+        let tags = note.children().map(|n| n.tag.as_str()).collect::<Vec<_>>();
+        assert_eq!(tags, ["to", "from", "heading", "body"]);
+
+        let reparsed = simple_xml::from_string(&note.to_string()).expect("Failed to reparse");
+        let tags = reparsed
+            .children()
+            .map(|n| n.tag.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(tags, ["to", "from", "heading", "body"]);
+    }
+
+    #[test]
+    fn check_names() {
+        assert!(simple_xml::is_valid_name("svg:path"));
+        assert!(simple_xml::is_valid_name("_a.b-c"));
+        assert!(!simple_xml::is_valid_name("1abc"));
+        assert!(!simple_xml::is_valid_name("a\"b"));
+        assert!(!simple_xml::is_valid_name(""));
+
+        assert!(Node::builder("note").attr("lang", "en").try_build().is_ok());
+
+        match Node::builder("note")
+            .child(Node::builder("to").attr("1st", "Tove"))
+            .try_build()
+        {
+            Err(simple_xml::Error::InvalidName(name)) if name == "1st" => {}
+            v => panic!("Expected InvalidName, got {:?}", v),
+        }
+
+        // Unchecked builds accept anything
+        assert_eq!(Node::builder("1abc").build().tag, "1abc");
+    }
+}
@@ -0,0 +1,150 @@
+#[cfg(test)]
+mod tests {
+    use simple_xml::c14n::{CanonicalOptions, Method};
+
+    fn canonical(string: &str, options: &CanonicalOptions) -> String {
+        let root = simple_xml::from_string(string).expect("Failed to parse");
+        let mut output = Vec::new();
+        root.write_canonical(&mut output, options).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn canonical_elements_and_attributes() {
+        // Adapted from the start and end tag example of the Canonical XML specification
+        let input = r#"<doc>
+            <e1   />
+            <e2   ></e2>
+            <e3   name="elem3"   id="elem3"   />
+            <e5 a:attr="out" b:attr="sorted" attr2="all" attr="I'm"
+                xmlns:b="http://www.ietf.org"
+                xmlns:a="http://www.w3.org"
+                xmlns="http://example.org"/>
+            <e6 xmlns="" xmlns:a="http://www.w3.org">
+                <e7 xmlns="http://www.ietf.org">
+                    <e8 xmlns="" xmlns:a="http://www.w3.org">
+                        <e9 xmlns="" xmlns:a="http://www.ietf.org"/>
+                    </e8>
+                </e7>
+            </e6>
+        </doc>"#;
+
+        assert_eq!(
+            canonical(input, &CanonicalOptions::default()),
+            concat!(
+                r#"<doc><e1></e1><e2></e2><e3 id="elem3" name="elem3"></e3>"#,
+                r#"<e5 xmlns="http://example.org" xmlns:a="http://www.w3.org" xmlns:b="http://www.ietf.org" "#,
+                r#"attr="I'm" attr2="all" b:attr="sorted" a:attr="out"></e5>"#,
+                r#"<e6 xmlns:a="http://www.w3.org"><e7 xmlns="http://www.ietf.org"><e8 xmlns="">"#,
+                r#"<e9 xmlns:a="http://www.ietf.org"></e9></e8></e7></e6></doc>"#,
+            )
+        );
+
+        // The output does not depend on the order of attributes
+        let a = canonical(r#"<a z="1" y="2" x="3" w="4"/>"#, &Default::default());
+        let b = canonical(r#"<a w="4" x="3" y="2" z="1"/>"#, &Default::default());
+        assert_eq!(a, b);
+        assert_eq!(a, r#"<a w="4" x="3" y="2" z="1"></a>"#);
+    }
+
+    #[test]
+    fn canonical_text() {
+        let options = CanonicalOptions::default();
+        assert_eq!(
+            canonical(
+                "<a x=\"&quot;1&quot; &lt; 2&#9;&#xA;\" y=\"a\tb\r\nc\">&lt;b&gt; &amp; &apos;c&apos; &#x41;<![CDATA[<d> & e]]>&#13;</a>",
+                &options
+            ),
+            "<a x=\"&quot;1&quot; &lt; 2&#x9;&#xA;\" y=\"a b c\">&lt;b&gt; &amp; 'c' A&lt;d&gt; &amp; e&#xD;</a>"
+        );
+        assert_eq!(
+            canonical("<a>one\r\ntwo\rthree &unknown; &#xZZ;</a>", &options),
+            "<a>one\ntwo\nthree &amp;unknown; &amp;#xZZ;</a>"
+        );
+        assert_eq!(
+            canonical(
+                "<a xml:lang=\"en\" xmlns:z=\"urn:a\" z:b=\"1\" c=\"2\"/>",
+                &options
+            ),
+            "<a xmlns:z=\"urn:a\" c=\"2\" xml:lang=\"en\" z:b=\"1\"></a>"
+        );
+    }
+
+    #[test]
+    fn canonical_mixed_content() {
+        // The expected output is from xmllint --c14n
+        let xml = "<doc xmlns=\"urn:d\">\n    <p>hello <b>bold</b> world<br/>!</p>\n    <list>\n        <item n=\"1\"/>\n    </list>\n</doc>";
+        let options = simple_xml::ParseOptions {
+            whitespace: simple_xml::Whitespace::Preserve,
+            ..Default::default()
+        };
+        let root = simple_xml::from_string_with_options(xml, &options).unwrap();
+        let mut output = Vec::new();
+        root.write_canonical(&mut output, &CanonicalOptions::default())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "<doc xmlns=\"urn:d\">\n    <p>hello <b>bold</b> world<br></br>!</p>\n    <list>\n        <item n=\"1\"></item>\n    </list>\n</doc>"
+        );
+
+        // Trimming drops the whitespace between elements, but keeps text in place
+        assert_eq!(
+            canonical(xml, &CanonicalOptions::default()),
+            "<doc xmlns=\"urn:d\"><p>hello<b>bold</b>world<br></br>!</p><list><item n=\"1\"></item></list></doc>"
+        );
+    }
+
+    #[test]
+    fn canonical_namespaces() {
+        // Adapted from the example of the Exclusive XML Canonicalization specification
+        let input = r#"<n0:local xmlns:n0="foo:bar" xmlns:n3="ftp://example.org"><n1:elem2 xmlns:n1="http://example.net" xml:lang="en"><n3:stuff xmlns:n3="ftp://example.org"/></n1:elem2></n0:local>"#;
+        let root = simple_xml::from_string(input).unwrap();
+        let elem2 = &root["n1:elem2"][0];
+        let write = |options: &CanonicalOptions| {
+            let mut output = Vec::new();
+            elem2.write_canonical(&mut output, options).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        let inherited = vec![
+            ("n0".to_owned(), "foo:bar".to_owned()),
+            ("n3".to_owned(), "ftp://example.org".to_owned()),
+        ];
+        let inclusive = CanonicalOptions {
+            inherited_namespaces: inherited.clone(),
+            ..Default::default()
+        };
+        assert_eq!(
+            write(&inclusive),
+            r#"<n1:elem2 xmlns:n0="foo:bar" xmlns:n1="http://example.net" xmlns:n3="ftp://example.org" xml:lang="en"><n3:stuff></n3:stuff></n1:elem2>"#
+        );
+
+        let exclusive = CanonicalOptions {
+            method: Method::Exclusive,
+            inherited_namespaces: inherited,
+            ..Default::default()
+        };
+        assert_eq!(
+            write(&exclusive),
+            r#"<n1:elem2 xmlns:n1="http://example.net" xml:lang="en"><n3:stuff xmlns:n3="ftp://example.org"></n3:stuff></n1:elem2>"#
+        );
+
+        let prefixes = CanonicalOptions {
+            inclusive_prefixes: vec!["n0".to_owned()],
+            ..exclusive.clone()
+        };
+        assert_eq!(
+            write(&prefixes),
+            r#"<n1:elem2 xmlns:n0="foo:bar" xmlns:n1="http://example.net" xml:lang="en"><n3:stuff xmlns:n3="ftp://example.org"></n3:stuff></n1:elem2>"#
+        );
+
+        // Default namespaces are only declared where used, and undeclared when needed
+        assert_eq!(
+            canonical(
+                r#"<a xmlns="urn:a" xmlns:b="urn:b"><b:c><d xmlns=""/></b:c></a>"#,
+                &CanonicalOptions::exclusive()
+            ),
+            r#"<a xmlns="urn:a"><b:c xmlns:b="urn:b"><d xmlns=""></d></b:c></a>"#
+        );
+    }
+}
@@ -0,0 +1,212 @@
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::process::{Command, Output, Stdio};
+
+    fn run(args: &[&str], stdin: Option<&str>) -> Output {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_simple-xml"))
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to run simple-xml");
+
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(stdin.unwrap_or("").as_bytes())
+            .unwrap();
+        child.wait_with_output().unwrap()
+    }
+
+    fn stdout(output: &Output) -> String {
+        String::from_utf8(output.stdout.clone()).unwrap()
+    }
+
+    #[test]
+    fn cli_fmt() {
+        let output = run(&["fmt"], Some("<a><b>text</b><c/></a>"));
+        assert!(output.status.success());
+        assert_eq!(stdout(&output), "<a>\n    <b>text</b>\n    <c/>\n</a>\n");
+
+        let output = run(&["fmt"], Some("<a>\n<b></a>"));
+        assert!(!output.status.success());
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.starts_with("simple-xml: <stdin>:2: "), "{}", stderr);
+    }
+
+    #[test]
+    fn cli_check() {
+        let output = run(
+            &["check", "./examples/note.xml", "./examples/graph.xml"],
+            None,
+        );
+        assert!(output.status.success());
+        assert_eq!(stdout(&output), "");
+
+        let output = run(&["check"], Some("<a>\n<b x=\"1\" x=\"2\">\n</a>"));
+        assert_eq!(output.status.code(), Some(1));
+        assert_eq!(
+            stdout(&output),
+            "<stdin>:2:10: error: Duplicate attribute \"x\"\n\
+             <stdin>:2:1: error: Missing closing tag for \"b\"\n"
+        );
+    }
+
+    #[test]
+    fn cli_query() {
+        let output = run(&["query", "/note/to/text()", "./examples/note.xml"], None);
+        assert!(output.status.success());
+        assert_eq!(stdout(&output), "Tove\n");
+
+        let output = run(
+            &["query", "//edge[@from='n4']", "./examples/graph.xml"],
+            None,
+        );
+        assert!(output.status.success());
+        assert!(stdout(&output).starts_with("<edge"));
+
+        let output = run(&["query", "/note/missing", "./examples/note.xml"], None);
+        assert!(!output.status.success());
+
+        let output = run(&["get-attr", "/note", "lang", "./examples/note.xml"], None);
+        assert_eq!(stdout(&output), "en_US\n");
+
+        // Values are printed with their references expanded
+        let xml = r#"<a b="&quot;1&quot;">Fish &amp; chips &#60;3</a>"#;
+        assert_eq!(
+            stdout(&run(&["query", "/a/text()"], Some(xml))),
+            "Fish & chips <3\n"
+        );
+        assert_eq!(stdout(&run(&["query", "/a/@b"], Some(xml))), "\"1\"\n");
+        assert_eq!(stdout(&run(&["get-attr", "/a", "b"], Some(xml))), "\"1\"\n");
+    }
+
+    #[test]
+    fn cli_encodings() {
+        let dir = std::env::temp_dir().join(format!("simple-xml-cli-enc-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let utf16 = dir.join("utf16.xml");
+        let bytes: Vec<u8> = "\u{feff}<a><b>ü</b></a>"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        std::fs::write(&utf16, bytes).unwrap();
+        let latin1 = dir.join("latin1.xml");
+        std::fs::write(
+            &latin1,
+            b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><a><b>\xfc</b></a>",
+        )
+        .unwrap();
+
+        for path in [&utf16, &latin1] {
+            let output = run(&["query", "/a/b/text()", path.to_str().unwrap()], None);
+            assert!(output.status.success());
+            assert_eq!(stdout(&output), "ü\n");
+            assert!(run(&["check", path.to_str().unwrap()], None)
+                .status
+                .success());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn cli_set() {
+        let path = std::env::temp_dir().join(format!("simple-xml-cli-{}.xml", std::process::id()));
+        std::fs::copy("./examples/note.xml", &path).unwrap();
+        let file = path.to_str().unwrap();
+
+        assert!(run(&["set", "/note/to", "Bert", file], None)
+            .status
+            .success());
+        assert!(run(&["set", "/note/@lang", "sv", file], None)
+            .status
+            .success());
+        assert!(!run(&["set", "/note/missing", "x", file], None)
+            .status
+            .success());
+
+        // Values are escaped, so the file stays well-formed
+        assert!(run(&["set", "/note/from", "A & <B>", file], None)
+            .status
+            .success());
+        assert!(run(&["set", "/note/@lang", "\"x\"", file], None)
+            .status
+            .success());
+        assert!(run(&["check", file], None).status.success());
+
+        let note = simple_xml::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(note["to"][0].content, "Bert");
+        assert_eq!(note["from"][0].content, "A &amp; &lt;B&gt;");
+        assert_eq!(note.attributes["lang"], "&quot;x&quot;");
+
+        // The declaration and encoding of the file are kept
+        std::fs::write(
+            &path,
+            b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>\n<a><b>\xe9</b></a>",
+        )
+        .unwrap();
+        assert!(run(&["set", "/a/@c", "\u{fc}", file], None)
+            .status
+            .success());
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(
+            bytes.starts_with(b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>\n<a c=\"\xfc\">")
+        );
+        assert_eq!(simple_xml::from_bytes(&bytes).unwrap()["b"][0].content, "é");
+
+        let output = run(&["set", "b", "2"], Some("<a><b>1</b></a>"));
+        assert_eq!(stdout(&output), "<a><b>2</b></a>");
+    }
+
+    #[test]
+    fn cli_set_in_place() {
+        let path = std::env::temp_dir().join(format!(
+            "simple-xml-cli-in-place-{}.xml",
+            std::process::id()
+        ));
+        let file = path.to_str().unwrap();
+        let original = "\u{feff}<?xml version=\"1.0\"?>\n<!DOCTYPE doc [<!ENTITY c \"Acme\">]>\n<!-- Notes -->\n<doc a=\"x\"  b=\"1\">\n  <?pi data?>\n  <p>hi <b>bye</b> <i>now</i></p>\n  <e/>\n  <f x=\"1\" />\n</doc>\n";
+        std::fs::write(&path, original).unwrap();
+
+        // Everything but the edited values is kept byte for byte
+        for (path, value) in [
+            ("/doc/@b", "2"),
+            ("/doc/@c", "&"),
+            ("/doc/p/b", "<later>"),
+            ("/doc/e", "text"),
+            ("/doc/f/@y", "2"),
+            ("/doc/f/text()", "3"),
+        ] {
+            assert!(run(&["set", path, value, file], None).status.success());
+        }
+        let edited = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            edited,
+            original
+                .replace("b=\"1\">", "b=\"2\" c=\"&amp;\">")
+                .replace("<b>bye</b>", "<b>&lt;later&gt;</b>")
+                .replace("<e/>", "<e>text</e>")
+                .replace("<f x=\"1\" />", "<f x=\"1\" y=\"2\">3</f>")
+        );
+
+        // Setting the content of an element with child elements would remove them
+        let output = run(&["set", "/doc/p", "x", file], None);
+        assert!(!output.status.success());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), edited);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn cli_usage() {
+        let output = run(&["query"], None);
+        assert_eq!(output.status.code(), Some(2));
+        assert!(String::from_utf8(output.stderr)
+            .unwrap()
+            .starts_with("Usage"));
+    }
+}
@@ -0,0 +1,144 @@
+#[cfg(test)]
+mod tests {
+    use simple_xml::diff::{diff_with_options, DiffOptions, Edit};
+    use simple_xml::patch;
+    use simple_xml::path::Location;
+
+    fn parse(string: &str) -> simple_xml::Node {
+        simple_xml::from_string(string).expect("Failed to parse")
+    }
+
+    fn describe(old: &str, new: &str) -> Vec<String> {
+        simple_xml::diff(&parse(old), &parse(new))
+            .iter()
+            .map(|v| v.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn diff_equal() {
+        let root = simple_xml::from_file("./examples/shiporder.xml").expect("Failed to parse");
+        assert!(simple_xml::diff(&root, &root.clone()).is_empty());
+        assert!(describe(r#"<a x="1" y="2"/>"#, r#"<a y="2" x="1"/>"#).is_empty());
+    }
+
+    #[test]
+    fn diff_attributes_and_content() {
+        assert_eq!(
+            describe(
+                r#"<a><b x="1" y="2">text</b></a>"#,
+                r#"<a><b y="3" z="4">other</b></a>"#
+            ),
+            [
+                "removed /b[1]/@x",
+                "changed /b[1]/@y from \"2\" to \"3\"",
+                "added /b[1]/@z = \"4\"",
+                "changed /b[1]/text() from \"text\" to \"other\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_elements() {
+        assert_eq!(
+            describe("<a><b/><c/><d/></a>", "<a><b/><e/><d/><f><g/></f></a>"),
+            ["removed /c[1]", "added /e[1]", "added /f[1]"]
+        );
+        assert_eq!(
+            describe("<a><b id=\"1\"/><b id=\"2\"/></a>", "<a><b id=\"2\"/></a>"),
+            ["removed /b[1]"]
+        );
+        assert_eq!(describe("<a/>", "<b/>"), ["removed /", "added /"]);
+
+        // Changes are reported at the location of the matched element
+        assert_eq!(
+            describe("<a><b/><c><d/></c></a>", "<a><c><d>x</d></c></a>"),
+            [
+                "changed /c[1]/d[1]/text() from \"\" to \"x\"",
+                "removed /b[1]"
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_moves() {
+        let old = parse("<a><b><c id=\"1\"/></b><d/><e/><f/></a>");
+        let new = parse("<a><b/><d><c id=\"1\"/></d><f/><e/></a>");
+        let edits = simple_xml::diff(&old, &new);
+        let location = |steps: &[(&str, usize)]| {
+            Location(steps.iter().map(|(t, i)| (t.to_string(), *i)).collect())
+        };
+
+        assert_eq!(
+            edits,
+            [
+                Edit::Moved {
+                    from: location(&[("e", 0)]),
+                    to: location(&[("e", 0)]),
+                },
+                Edit::Moved {
+                    from: location(&[("b", 0), ("c", 0)]),
+                    to: location(&[("d", 0), ("c", 0)]),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_long_lists() {
+        let list = |ids: &[usize]| {
+            let items: String = ids
+                .iter()
+                .map(|id| format!(r#"<item id="{0}">{0}</item>"#, id))
+                .collect();
+            parse(&format!("<list>{}</list>", items))
+        };
+        let round_trip = |old: &simple_xml::Node, new: &simple_xml::Node, edits: &[Edit]| {
+            let mut patched = old.clone();
+            patch::apply(&mut patched, &patch::from_diff(old, new, edits)).unwrap();
+            assert_eq!(&patched, new);
+        };
+
+        let ids: Vec<usize> = (0..100).collect();
+        let rotated: Vec<usize> = (1..100).chain([0]).collect();
+        let (old, new) = (list(&ids), list(&rotated));
+        let edits = simple_xml::diff(&old, &new);
+        assert_eq!(edits.len(), 1);
+        assert!(matches!(&edits[0], Edit::Moved { .. }));
+        round_trip(&old, &new, &edits);
+
+        // Lists too long for the matching table are matched on their unique elements
+        let ids: Vec<usize> = (0..2000).collect();
+        let mut swapped = ids.clone();
+        swapped.swap(0, 1999);
+        let (old, new) = (list(&ids), list(&swapped));
+        let edits = simple_xml::diff(&old, &new);
+        assert_eq!(
+            edits.iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+            [
+                "changed /item[1]/@id from \"0\" to \"1999\"",
+                "changed /item[1]/text() from \"0\" to \"1999\"",
+                "changed /item[2000]/@id from \"1999\" to \"0\"",
+                "changed /item[2000]/text() from \"1999\" to \"0\"",
+            ]
+        );
+        round_trip(&old, &new, &edits);
+    }
+
+    #[test]
+    fn diff_whitespace() {
+        let old = parse("<a><b>one  two</b><c> x </c></a>");
+        let new = parse("<a><b>one\n    two</b><c>x y</c></a>");
+        let ignore = DiffOptions {
+            ignore_whitespace: true,
+        };
+
+        assert_eq!(simple_xml::diff(&old, &new).len(), 2);
+        let edits = diff_with_options(&old, &new, &ignore);
+        assert_eq!(edits.len(), 1);
+        assert_eq!(
+            edits[0].to_string(),
+            "changed /c[1]/text() from \"x\" to \"x y\""
+        );
+    }
+}
@@ -0,0 +1,236 @@
+#[cfg(test)]
+mod tests {
+    use simple_xml::dsig::{self, Digest, Signature};
+    use simple_xml::Error;
+
+    /// SHA-256 as described in FIPS 180-4
+    fn sha256(data: &[u8]) -> Vec<u8> {
+        const K: [u32; 64] = [
+            0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4,
+            0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe,
+            0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f,
+            0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+            0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc,
+            0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+            0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116,
+            0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+            0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+            0xc67178f2,
+        ];
+        let mut h: [u32; 8] = [
+            0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+            0x5be0cd19,
+        ];
+
+        let mut message = data.to_vec();
+        message.push(0x80);
+        while message.len() % 64 != 56 {
+            message.push(0);
+        }
+        message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+        for chunk in message.chunks(64) {
+            let mut w = [0u32; 64];
+            for i in 0..16 {
+                w[i] = u32::from_be_bytes([
+                    chunk[i * 4],
+                    chunk[i * 4 + 1],
+                    chunk[i * 4 + 2],
+                    chunk[i * 4 + 3],
+                ]);
+            }
+            for i in 16..64 {
+                let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+                let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+                w[i] = w[i - 16]
+                    .wrapping_add(s0)
+                    .wrapping_add(w[i - 7])
+                    .wrapping_add(s1);
+            }
+
+            let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+            for i in 0..64 {
+                let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+                let ch = (e & f) ^ (!e & g);
+                let t1 = hh
+                    .wrapping_add(s1)
+                    .wrapping_add(ch)
+                    .wrapping_add(K[i])
+                    .wrapping_add(w[i]);
+                let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+                let maj = (a & b) ^ (a & c) ^ (b & c);
+                let t2 = s0.wrapping_add(maj);
+                hh = g;
+                g = f;
+                f = e;
+                e = d.wrapping_add(t1);
+                d = c;
+                c = b;
+                b = a;
+                a = t1.wrapping_add(t2);
+            }
+
+            for (v, x) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+                *v = v.wrapping_add(x);
+            }
+        }
+
+        h.iter().flat_map(|v| v.to_be_bytes()).collect()
+    }
+
+    struct Sha256;
+
+    impl Digest for Sha256 {
+        fn digest(&self, algorithm: &str, data: &[u8]) -> Option<Vec<u8>> {
+            match algorithm {
+                dsig::SHA256 => Some(sha256(data)),
+                _ => None,
+            }
+        }
+    }
+
+    /// An invoice signed independently of this crate, with the digests and the canonical SignedInfo
+    /// computed by the C14N implementation of libxml2 (`xmllint --c14n` and `xmllint --exc-c14n`),
+    /// and the signature value by RSA-SHA256 with the key in its KeyInfo
+    fn fixture() -> String {
+        std::fs::read_to_string("./examples/invoice_signed.xml").expect("Missing fixture")
+    }
+
+    /// Parses keeping the whitespace between elements, which is covered by the signature
+    fn parse(string: &str) -> simple_xml::Node {
+        let options = simple_xml::ParseOptions {
+            whitespace: simple_xml::Whitespace::Preserve,
+            ..Default::default()
+        };
+        simple_xml::from_string_with_options(string, &options).expect("Failed to parse")
+    }
+
+    fn base64(data: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut output = String::new();
+        for chunk in data.chunks(3) {
+            let bytes = [
+                chunk[0],
+                *chunk.get(1).unwrap_or(&0),
+                *chunk.get(2).unwrap_or(&0),
+            ];
+            let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+            for i in 0..4 {
+                match i <= chunk.len() {
+                    true => output.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char),
+                    false => output.push('='),
+                }
+            }
+        }
+        output
+    }
+
+    #[test]
+    fn sha256_vectors() {
+        let hex = |v: Vec<u8>| v.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        assert_eq!(
+            hex(sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hex(sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn verify_fixture() {
+        let root = parse(&fixture());
+        let signature = dsig::verify(&root, &Sha256).expect("Failed to verify");
+
+        assert_eq!(signature.references.len(), 2);
+        assert_eq!(signature.references[0].uri, "#seller");
+        assert_eq!(signature.value.len(), 256);
+        assert_eq!(signature.canonicalization.algorithm, dsig::EXC_C14N);
+
+        // The signed data is the same as canonicalized by libxml2
+        let signed_info = signature.canonical_signed_info(&root).unwrap();
+        assert_eq!(
+            base64(&sha256(&signed_info)),
+            "o2TedpVUe2H2a4sbZekL5i7tdT4+rIwVbxhJsZjORzA="
+        );
+        let signed_info = String::from_utf8(signed_info).unwrap();
+        assert!(signed_info.starts_with(
+            "<ds:SignedInfo xmlns:ds=\"http://www.w3.org/2000/09/xmldsig#\">\n            <ds:CanonicalizationMethod"
+        ));
+
+        // Without the whitespace between elements the digests do not match
+        let root = simple_xml::from_string(&fixture()).unwrap();
+        assert!(matches!(
+            dsig::verify(&root, &Sha256),
+            Err(Error::DigestMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn verify_tampered() {
+        let tampered = fixture().replace("120.50", "12.50");
+        let root = parse(&tampered);
+        assert!(
+            matches!(dsig::verify(&root, &Sha256), Err(Error::DigestMismatch(uri)) if uri.is_empty())
+        );
+
+        let tampered = fixture().replace("country=\"NL\"", "country=\"BE\"");
+        let root = parse(&tampered);
+        let e = dsig::verify(&root, &Sha256).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Digest of reference \"#seller\" does not match"
+        );
+
+        // Namespace declarations are part of the signed data
+        let tampered = fixture().replace("urn:example:components", "urn:example:other");
+        let root = parse(&tampered);
+        assert!(matches!(
+            dsig::verify(&root, &Sha256),
+            Err(Error::DigestMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn verify_errors() {
+        let unsupported = fixture().replace("xmlenc#sha256", "xmlenc#sha512");
+        let root = parse(&unsupported);
+        assert!(matches!(
+            dsig::verify(&root, &Sha256),
+            Err(Error::UnsupportedAlgorithm(v)) if v == dsig::SHA512
+        ));
+
+        let missing = fixture().replace("id=\"seller\"", "id=\"buyer\"");
+        let root = parse(&missing);
+        assert!(matches!(
+            dsig::verify(&root, &Sha256),
+            Err(Error::InvalidSignature(_))
+        ));
+
+        // A second element with the signed id could be verified in place of the one that is used
+        let wrapped = fixture().replace(
+            "<Total currency",
+            "<cac:Party id=\"seller\"><Name>Mallory</Name></cac:Party>\n    <Total currency",
+        );
+        let root = parse(&wrapped);
+        assert!(matches!(
+            dsig::verify(&root, &Sha256),
+            Err(Error::InvalidSignature(v)) if v.contains("duplicate id")
+        ));
+
+        // Signature elements must be in the signature namespace
+        let other = fixture().replace("2000/09/xmldsig#\"", "2000/09/other#\"");
+        let root = parse(&other);
+        assert!(matches!(
+            Signature::find(&root),
+            Err(Error::InvalidSignature(_))
+        ));
+
+        let root = simple_xml::from_file("./examples/note.xml").unwrap();
+        assert!(matches!(
+            dsig::verify(&root, &Sha256),
+            Err(Error::InvalidSignature(_))
+        ));
+    }
+}
@@ -0,0 +1,113 @@
+#[cfg(test)]
+mod tests {
+    use simple_xml::dtd::{self, ContentSpec, DefaultDecl, ExternalId, ViolationKind};
+
+    #[test]
+    fn parse_doctype() {
+        let document = simple_xml::document_from_file("./examples/doctype.xml")
+            .expect("Failed to parse doctype.xml");
+
+        let doctype = document.doctype.as_ref().expect("Missing doctype");
+        assert_eq!(doctype.name, "note");
+        assert_eq!(
+            doctype.external_id,
+            Some(ExternalId::System("note.dtd".into()))
+        );
+        let subset = doctype.internal_subset.as_ref().expect("Missing subset");
+        assert_eq!(
+            subset.attributes["note"][0].default,
+            DefaultDecl::Fixed("1.0".into())
+        );
+
+        assert_eq!(document.root["to"][0].content, "Tove");
+
+        // The doctype is skipped when only the root is loaded
+        let root = simple_xml::from_file("./examples/doctype.xml").unwrap();
+        assert_eq!(root.tag, "note");
+    }
+
+    #[test]
+    fn parse_dtd() {
+        let dtd = dtd::from_file("./examples/note.dtd").expect("Failed to parse note.dtd");
+
+        assert_eq!(dtd.elements.len(), 6);
+        assert_eq!(
+            dtd.elements["note"].to_string(),
+            "(to+, from, heading?, body)"
+        );
+        assert_eq!(dtd.elements["body"], ContentSpec::Mixed(vec!["em".into()]));
+        assert_eq!(dtd.attributes["note"].len(), 3);
+        assert_eq!(
+            dtd.entities["signature"],
+            dtd::EntityDecl::Internal("Jani".into())
+        );
+        assert!(dtd.notations.contains_key("png"));
+
+        match simple_xml::Dtd::parse("<!ELEMENT note (to, from | body)>") {
+            Err(simple_xml::Error::ParseError(
+                simple_xml::ParseError::InvalidDeclaration(_),
+                0,
+            )) => {}
+            v => panic!("Expected InvalidDeclaration, got {:?}", v),
+        }
+    }
+
+    #[test]
+    fn validate() {
+        let dtd = dtd::from_file("./examples/note.dtd").unwrap();
+        let document = simple_xml::document_from_file("./examples/doctype.xml").unwrap();
+        let violations = dtd::validate(&document, &dtd);
+        assert!(violations.is_empty(), "{:?}", violations);
+
+        let document = simple_xml::document_from_string(
+            r#"<!DOCTYPE note SYSTEM "note.dtd">
+            <note type="later">
+                <from>Jani</from>
+                <body>Hi <b>there</b></body>
+                <body>Again</body>
+            </note>"#,
+        )
+        .unwrap();
+
+        let violations = dtd::validate(&document, &dtd)
+            .into_iter()
+            .map(|v| (v.path, v.kind))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            violations,
+            [
+                (
+                    "/note".to_owned(),
+                    ViolationKind::InvalidContent {
+                        expected: "(to+, from, heading?, body)".into(),
+                        found: vec!["from".into(), "body".into(), "body".into()],
+                    }
+                ),
+                (
+                    "/note".to_owned(),
+                    ViolationKind::MissingAttribute("lang".into())
+                ),
+                (
+                    "/note".to_owned(),
+                    ViolationKind::InvalidAttributeValue {
+                        name: "type".into(),
+                        value: "later".into(),
+                        allowed: vec!["normal".into(), "urgent".into()],
+                    }
+                ),
+                (
+                    "/note/body[1]".to_owned(),
+                    ViolationKind::InvalidContent {
+                        expected: "(#PCDATA | em)*".into(),
+                        found: vec!["b".into()],
+                    }
+                ),
+                (
+                    "/note/body[1]/b[1]".to_owned(),
+                    ViolationKind::UndeclaredElement("b".into())
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,164 @@
+#[cfg(test)]
+mod tests {
+    use simple_xml::Node;
+
+    fn parse(string: &str) -> Node {
+        simple_xml::from_string(string).expect("Failed to parse")
+    }
+
+    fn tags(node: &Node) -> Vec<&str> {
+        node.children().map(|v| v.tag.as_str()).collect()
+    }
+
+    /// Checks that lookup by tag agrees with document order
+    fn assert_consistent(node: &Node) {
+        for (i, child) in node.children().enumerate() {
+            let index = node
+                .children()
+                .take(i)
+                .filter(|v| v.tag == child.tag)
+                .count();
+            assert!(std::ptr::eq(&node[&child.tag][index], child));
+        }
+        let mut distinct = tags(node);
+        distinct.sort();
+        distinct.dedup();
+        let count: usize = distinct.iter().map(|tag| node[tag].len()).sum();
+        assert_eq!(count, node.child_count());
+        assert_eq!(parse(&node.to_string()), *node);
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        let mut root = parse(r#"<r><a n="1"/><b/><a n="2"/></r>"#);
+        root.insert_child(0, simple_xml::new("c", String::new()));
+        root.insert_child(2, simple_xml::new("a", "new".to_owned()));
+        root.insert_child(5, simple_xml::new("d", String::new()));
+        assert_eq!(tags(&root), ["c", "a", "a", "b", "a", "d"]);
+        assert_eq!(root["a"][1].content, "new");
+        assert_consistent(&root);
+
+        let removed = root.remove_child("a", 2);
+        assert_eq!(removed.get_attribute("n").unwrap(), "2");
+        let removed = root.remove_child("c", 0);
+        assert_eq!(removed.tag, "c");
+        assert_eq!(tags(&root), ["a", "a", "b", "d"]);
+        assert!(root.get_nodes("c").is_none());
+        assert_consistent(&root);
+    }
+
+    #[test]
+    #[should_panic]
+    fn insert_out_of_bounds() {
+        parse("<r><a/></r>").insert_child(2, simple_xml::new("b", String::new()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn remove_missing() {
+        parse("<r><a/></r>").remove_child("a", 1);
+    }
+
+    #[test]
+    fn replace() {
+        let mut root = parse("<r><a>1</a><b/><a>2</a></r>");
+        let replaced = root.replace_child("a", 0, simple_xml::new("c", String::new()));
+        assert_eq!(replaced.content, "1");
+        assert_eq!(tags(&root), ["c", "b", "a"]);
+        assert_eq!(root["a"][0].content, "2");
+        assert_consistent(&root);
+
+        root.replace_child("b", 0, simple_xml::new("b", "new".to_owned()));
+        assert_eq!(root.to_string(), "<r><c/><b>new</b><a>2</a></r>");
+    }
+
+    #[test]
+    fn retain_and_sort() {
+        let mut root = parse(r#"<r><a n="3"/><b n="1"/><a n="2"/><c n="2"/><b n="5"/></r>"#);
+        root.retain_children(|v| v.get_attribute("n").unwrap() != "5");
+        assert_eq!(tags(&root), ["a", "b", "a", "c"]);
+        assert_consistent(&root);
+
+        root.sort_children_by(|a, b| a.get_attribute("n").cmp(&b.get_attribute("n")));
+        assert_eq!(tags(&root), ["b", "a", "c", "a"]);
+        assert_eq!(root["a"][0].get_attribute("n").unwrap(), "2");
+        assert_eq!(root["a"][1].get_attribute("n").unwrap(), "3");
+        assert_consistent(&root);
+
+        root.retain_children(|v| v.tag == "x");
+        assert_eq!(root.child_count(), 0);
+        assert_eq!(root.to_string(), "<r/>");
+    }
+
+    #[test]
+    fn take_children() {
+        let mut root = parse("<r><a/><b/><a/>text</r>");
+        let children = root.take_children();
+        assert_eq!(
+            children.iter().map(|v| v.tag.as_str()).collect::<Vec<_>>(),
+            ["a", "b", "a"]
+        );
+        assert_eq!(root.child_count(), 0);
+        assert!(root.get_nodes("a").is_none());
+        assert_eq!(root.to_string(), "<r>text</r>");
+    }
+
+    #[test]
+    fn wrap_and_unwrap() {
+        let mut root = parse(r#"<r><a/><b/><a n="2"/></r>"#);
+        root.wrap(
+            "a",
+            1,
+            Node::builder("w")
+                .attr("x", "1")
+                .child(simple_xml::new("first", String::new()))
+                .build(),
+        );
+        assert_eq!(tags(&root), ["a", "b", "w"]);
+        assert_eq!(tags(&root["w"][0]), ["first", "a"]);
+        assert_eq!(root["w"][0]["a"][0].get_attribute("n").unwrap(), "2");
+        assert_consistent(&root);
+
+        let moved = root.unwrap("w", 0);
+        assert_eq!(moved, 2);
+        assert_eq!(tags(&root), ["a", "b", "first", "a"]);
+        assert_eq!(root["a"][1].get_attribute("n").unwrap(), "2");
+        assert_consistent(&root);
+
+        let mut root = parse("<r>end<p>one<i>two</i>three</p>four</r>");
+        assert_eq!(root.unwrap("p", 0), 1);
+        assert_eq!(root.to_string(), "<r>endone<i>two</i>threefour</r>");
+        assert_eq!(root.content, "endonethreefour");
+    }
+
+    #[test]
+    fn mixed_content() {
+        let mut root = parse("<p>Fish,<b>and</b>chips<i/>!</p>");
+        assert_eq!(root.content, "Fish,chips!");
+        assert_eq!(root.to_string(), "<p>Fish,<b>and</b>chips<i/>!</p>");
+        assert_eq!(
+            root.to_string_pretty(),
+            "<p>Fish,<b>and</b>chips<i/>!</p>\n"
+        );
+
+        // Text before a removed node joins the text before the next one
+        let removed = root.remove_child("b", 0);
+        assert_eq!(removed.content, "and");
+        assert_eq!(root.to_string(), "<p>Fish,chips<i/>!</p>");
+        root.insert_child(0, simple_xml::new("u", String::new()));
+        root.replace_child("i", 0, simple_xml::new("s", String::new()));
+        assert_eq!(root.to_string(), "<p><u/>Fish,chips<s/>!</p>");
+        assert_consistent(&root);
+
+        root.sort_children_by(|a, b| b.tag.cmp(&a.tag));
+        assert_eq!(root.to_string(), "<p><u/>Fish,chips<s/>!</p>");
+        root.retain_children(|v| v.tag == "s");
+        assert_eq!(root.to_string(), "<p>Fish,chips<s/>!</p>");
+
+        // Once the content is changed, it follows the child nodes
+        root.content = "new".to_owned();
+        assert_eq!(root.to_string(), "<p><s/>new</p>");
+        root.take_children();
+        assert_eq!(root.to_string(), "<p>new</p>");
+    }
+}
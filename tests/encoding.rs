@@ -0,0 +1,160 @@
+#[cfg(test)]
+mod tests {
+    use simple_xml::encoding;
+    use simple_xml::{Encoding, Error, ParseError};
+
+    #[test]
+    fn detect() {
+        let detect = |bytes: &[u8]| encoding::detect(bytes).expect("Failed to detect");
+        assert_eq!(detect(b"<a/>"), (Encoding::Utf8, 0));
+        assert_eq!(detect(b"\xef\xbb\xbf<a/>"), (Encoding::Utf8, 3));
+        assert_eq!(detect(b"\xff\xfe<\0a\0/\0>\0"), (Encoding::Utf16Le, 2));
+        assert_eq!(detect(b"\xfe\xff\0<\0a\0/\0>"), (Encoding::Utf16Be, 2));
+        assert_eq!(detect(b"<\0?\0x\0m\0l\0"), (Encoding::Utf16Le, 0));
+        assert_eq!(
+            detect(b"<?xml version='1.0' encoding = 'latin1' ?><a/>"),
+            (Encoding::Latin1, 0)
+        );
+        assert_eq!(
+            detect(b"<?xml version=\"1.0\" encoding=\"Windows-1252\"?><a/>"),
+            (Encoding::Windows1252, 0)
+        );
+        // A declaration read as ASCII can not be in UTF-16
+        assert_eq!(
+            detect(b"<?xml version=\"1.0\" encoding=\"UTF-16\"?><a/>"),
+            (Encoding::Utf8, 0)
+        );
+
+        match encoding::detect(b"<?xml version=\"1.0\" encoding=\"EBCDIC\"?><a/>") {
+            Err(Error::ParseError(ParseError::UnsupportedEncoding(label), 0)) => {
+                assert_eq!(label, "EBCDIC")
+            }
+            v => panic!("Expected UnsupportedEncoding, got {:?}", v),
+        }
+    }
+
+    #[test]
+    fn decode() {
+        let (string, encoding) = encoding::decode(
+            b"<?xml version=\"1.0\" encoding=\"windows-1252\"?><a>\x80 \x93x\x94 \xe9</a>",
+        )
+        .unwrap();
+        assert_eq!(encoding, Encoding::Windows1252);
+        assert_eq!(simple_xml::from_string(&string).unwrap().content, "€ “x” é");
+
+        let (string, _) =
+            encoding::decode(b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><a>\x80\xe9</a>")
+                .unwrap();
+        assert_eq!(simple_xml::from_string(&string).unwrap().content, "\u{80}é");
+
+        let utf16: Vec<u8> = "\u{feff}<a>𝄞</a>"
+            .encode_utf16()
+            .flat_map(u16::to_be_bytes)
+            .collect();
+        let (string, encoding) = encoding::decode(&utf16).unwrap();
+        assert_eq!(encoding, Encoding::Utf16Be);
+        assert_eq!(string, "<a>𝄞</a>");
+
+        // An unpaired surrogate on the second line
+        let invalid = b"\xff\xfe<\0a\0>\0\n\0\x00\xd8x\0";
+        match encoding::decode(invalid) {
+            Err(Error::ParseError(ParseError::InvalidUtf16(10), 1)) => {}
+            v => panic!("Expected InvalidUtf16 at byte 10, got {:?}", v),
+        }
+        assert!(matches!(
+            encoding::decode(b"\xff\xfe<\0a"),
+            Err(Error::ParseError(ParseError::InvalidUtf16(4), 0))
+        ));
+    }
+
+    #[test]
+    fn encode() {
+        let root = simple_xml::from_string(r#"<a b="ä€">ö — 😀</a>"#).unwrap();
+        assert_eq!(
+            root.to_bytes(Encoding::Latin1),
+            b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><a b=\"\xe4&#x20ac;\">\xf6 &#x2014; &#x1f600;</a>"
+        );
+        assert_eq!(
+            root.to_bytes(Encoding::Windows1252),
+            b"<?xml version=\"1.0\" encoding=\"windows-1252\"?><a b=\"\xe4\x80\">\xf6 \x97 &#x1f600;</a>"
+        );
+
+        for encoding in [
+            Encoding::Utf8,
+            Encoding::Utf16Le,
+            Encoding::Utf16Be,
+            Encoding::Latin1,
+            Encoding::Windows1252,
+        ] {
+            let (string, detected) = encoding::decode(&root.to_bytes(encoding)).unwrap();
+            assert_eq!(detected, encoding);
+            let decoded = simple_xml::from_string(&string).unwrap();
+            assert_eq!(decoded.tag, "a");
+            if matches!(
+                encoding,
+                Encoding::Utf8 | Encoding::Utf16Le | Encoding::Utf16Be
+            ) {
+                assert_eq!(decoded, root);
+            }
+        }
+    }
+
+    #[test]
+    fn files() {
+        let dir = std::env::temp_dir().join("simple_xml_encoding");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("latin1.xml");
+        std::fs::write(
+            &path,
+            b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>\n<!DOCTYPE a>\n<a>caf\xe9</a>",
+        )
+        .unwrap();
+
+        assert_eq!(simple_xml::from_file(&path).unwrap().content, "café");
+        let document = simple_xml::document_from_file(&path).unwrap();
+        assert_eq!(document.encoding, Encoding::Latin1);
+
+        let copy = dir.join("copy.xml");
+        document
+            .root
+            .save_to_file_with_encoding(&copy, document.encoding)
+            .unwrap();
+        assert_eq!(
+            std::fs::read(&copy).unwrap(),
+            b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><a>caf\xe9</a>"
+        );
+        assert_eq!(simple_xml::from_file(&copy).unwrap(), document.root);
+    }
+
+    #[test]
+    fn from_bytes() {
+        let bytes: Vec<u8> =
+            b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><a b=\"\xfc\"/>".to_vec();
+        let root = simple_xml::from_bytes(&bytes).unwrap();
+        assert_eq!(root.get_attribute("b").unwrap(), "\u{fc}");
+        assert_eq!(
+            simple_xml::from_bytes(&root.to_bytes(Encoding::Utf16Le)).unwrap(),
+            root
+        );
+
+        // Offsets count from the start of the input, including the byte order mark
+        for (bytes, offset, line) in [
+            (&b"<a>\xc3</a>"[..], 3, 0),
+            (&b"\xef\xbb\xbf<a>\n\n\xe2\x82</a>"[..], 8, 2),
+            (&b"<a>ok</a>\x80"[..], 9, 0),
+        ] {
+            match simple_xml::from_bytes(bytes) {
+                Err(Error::ParseError(ParseError::InvalidUtf8(v), ln)) => {
+                    assert_eq!((v, ln), (offset, line), "{:?}", bytes)
+                }
+                v => panic!("Expected InvalidUtf8 for {:?}, got {:?}", bytes, v),
+            }
+        }
+
+        let options = simple_xml::ParseOptions {
+            max_depth: 1,
+            ..Default::default()
+        };
+        assert!(simple_xml::from_bytes_with_options(b"<a><b/></a>", &options).is_err());
+    }
+}
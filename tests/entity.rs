@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod tests {
+    use simple_xml::EntityLimits;
+
+    const LAUGHS: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE lolz [
+    <!ENTITY lol "lol">
+    <!ENTITY lol1 "&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;">
+    <!ENTITY lol2 "&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;">
+    <!ENTITY lol3 "&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;">
+    <!ENTITY lol4 "&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;">
+    <!ENTITY lol5 "&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;">
+    <!ENTITY lol6 "&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;">
+    <!ENTITY lol7 "&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;">
+    <!ENTITY lol8 "&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;">
+    <!ENTITY lol9 "&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;">
+]>
+<lolz>&lol9;</lolz>"#;
+
+    #[test]
+    fn expand() {
+        let document = simple_xml::document_from_string(
+            r#"<!DOCTYPE note [
+                <!ENTITY company "Acme">
+                <!ENTITY logo SYSTEM "logo.png">
+            ]>
+            <note from="&company; &lt;sales&gt;">
+                <body>&company; &#169; &undeclared; &logo; &</body>
+            </note>"#,
+        )
+        .expect("Failed to parse document");
+
+        assert_eq!(document.root.attributes["from"], "Acme &lt;sales&gt;");
+        assert_eq!(
+            document.root["body"][0].content,
+            "Acme &#169; &undeclared; &logo; &"
+        );
+    }
+
+    #[test]
+    fn expand_mixed_content() {
+        let document = simple_xml::document_from_string(
+            r#"<!DOCTYPE p [<!ENTITY c "Acme">]><p>&c; says <b>hi</b> to &c; <i/>there</p>"#,
+        )
+        .expect("Failed to parse document");
+
+        assert_eq!(
+            document.root.to_string(),
+            "<p>Acme says<b>hi</b>to Acme<i/>there</p>"
+        );
+    }
+
+    #[test]
+    fn limit_size() {
+        match simple_xml::document_from_string(LAUGHS) {
+            Err(simple_xml::Error::EntityExpansionTooLarge(size)) => {
+                assert_eq!(size, EntityLimits::default().max_expanded_size)
+            }
+            v => panic!("Expected EntityExpansionTooLarge, got {:?}", v),
+        }
+
+        // Smaller documents of the same shape are fine
+        let small = LAUGHS.replace("<lolz>&lol9;</lolz>", "<lolz>&lol2;</lolz>");
+        let document = simple_xml::document_from_string(&small).unwrap();
+        assert_eq!(document.root.content.len(), 300);
+    }
+
+    #[test]
+    fn limit_depth() {
+        let limits = EntityLimits {
+            max_depth: 3,
+            ..Default::default()
+        };
+
+        let shallow = LAUGHS.replace("<lolz>&lol9;</lolz>", "<lolz>&lol2;</lolz>");
+        assert!(simple_xml::document_from_string_with_limits(&shallow, &limits).is_ok());
+
+        let deep = LAUGHS.replace("<lolz>&lol9;</lolz>", "<lolz>&lol3;</lolz>");
+        match simple_xml::document_from_string_with_limits(&deep, &limits) {
+            Err(simple_xml::Error::EntityDepthExceeded(name)) => assert_eq!(name, "lol"),
+            v => panic!("Expected EntityDepthExceeded, got {:?}", v),
+        }
+    }
+
+    #[test]
+    fn recursive() {
+        match simple_xml::document_from_string(
+            r#"<!DOCTYPE a [
+                <!ENTITY a "&b;">
+                <!ENTITY b "&a;">
+            ]>
+            <a>&a;</a>"#,
+        ) {
+            Err(simple_xml::Error::RecursiveEntity(name)) => assert_eq!(name, "a"),
+            v => panic!("Expected RecursiveEntity, got {:?}", v),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::{Rng, SeedableRng};
+
+    const ITERATIONS: usize = 20_000;
+
+    const TOKENS: [&str; 40] = [
+        "<",
+        ">",
+        "/",
+        "</",
+        "/>",
+        "=",
+        "\"",
+        "'",
+        "a",
+        "b",
+        "é",
+        "😀",
+        " ",
+        "\u{3000}",
+        "\n",
+        "<!--",
+        "-->",
+        "<?",
+        "?>",
+        "<?xml",
+        "<!DOCTYPE",
+        "[",
+        "]",
+        "<!ENTITY",
+        "<!ELEMENT",
+        "<!ATTLIST",
+        "%",
+        "&",
+        ";",
+        "&#",
+        "&#x",
+        "<![CDATA[",
+        "]]>",
+        "(",
+        ")",
+        "*",
+        "|",
+        "#PCDATA",
+        "SYSTEM",
+        "x=\"1\"",
+    ];
+
+    /// Parses the input, failing with the input if the parser panics
+    fn parse(input: &str) {
+        let result = std::panic::catch_unwind(|| {
+            let _ = simple_xml::from_string(input);
+            let _ = simple_xml::document_from_string(input);
+            let _ = simple_xml::from_string_lenient(input);
+        });
+
+        if result.is_err() {
+            panic!("Parsing panicked on input {:?}", input);
+        }
+    }
+
+    #[test]
+    fn fuzz_known_panics() {
+        for input in [
+            "<>",
+            "< >",
+            ">",
+            "<a>",
+            "<é>",
+            "<a/é>",
+            "<a key=/>",
+            "<a key=>",
+            "<a key=\">",
+            "<a key=é>",
+            "</a>",
+            "<",
+            "<!--",
+            "<!DOCTYPE",
+        ] {
+            parse(input);
+            assert!(simple_xml::from_string(input).is_err(), "{:?}", input);
+        }
+
+        // Multi byte whitespace between attributes
+        let root = simple_xml::from_string("<a\u{3000}b=\"1\"/>").unwrap();
+        assert_eq!(root.attributes["b"], "1");
+    }
+
+    #[test]
+    fn fuzz_random_bytes() {
+        let mut rng = StdRng::seed_from_u64(0x786d6c);
+        for _ in 0..ITERATIONS {
+            let len = rng.gen_range(0..64);
+            let bytes: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            parse(&String::from_utf8_lossy(&bytes));
+        }
+    }
+
+    #[test]
+    fn fuzz_random_tokens() {
+        let mut rng = StdRng::seed_from_u64(0x786d6c);
+        for _ in 0..ITERATIONS {
+            let len = rng.gen_range(0..32);
+            let input: String = (0..len)
+                .map(|_| *TOKENS.choose(&mut rng).unwrap())
+                .collect();
+            parse(&input);
+        }
+    }
+
+    #[test]
+    fn fuzz_mutated_examples() {
+        let examples: Vec<String> = ["note.xml", "doctype.xml", "graph.xml", "addressbook.rng"]
+            .iter()
+            .map(|v| std::fs::read_to_string(format!("./examples/{}", v)).unwrap())
+            .collect();
+
+        let mut rng = StdRng::seed_from_u64(0x786d6c);
+        for _ in 0..ITERATIONS / 10 {
+            let mut input: Vec<char> = examples.choose(&mut rng).unwrap().chars().collect();
+            for _ in 0..rng.gen_range(1..8) {
+                let i = rng.gen_range(0..input.len());
+                match rng.gen_range(0..3) {
+                    0 => {
+                        input.remove(i);
+                    }
+                    1 => input.insert(
+                        i,
+                        *['<', '>', '/', '"', '=', '&', 'é']
+                            .choose(&mut rng)
+                            .unwrap(),
+                    ),
+                    _ => input.truncate(i),
+                }
+                if input.is_empty() {
+                    break;
+                }
+            }
+            parse(&input.into_iter().collect::<String>());
+        }
+    }
+}
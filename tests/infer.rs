@@ -0,0 +1,175 @@
+// Generated by `to_rust` for the samples in `tests::tasks`
+#[cfg(test)]
+#[rustfmt::skip]
+#[path = "inferred/tasks.rs"]
+mod generated;
+
+// Generated by `to_rust` for examples/graph.xml, which has a <node> element
+#[cfg(test)]
+#[rustfmt::skip]
+#[path = "inferred/graph.rs"]
+mod graph;
+
+// Generated by `to_rust` for `tests::NAMES`
+#[cfg(test)]
+#[rustfmt::skip]
+#[path = "inferred/names.rs"]
+mod names;
+
+#[cfg(test)]
+mod tests {
+    use super::{generated, graph, names};
+    use simple_xml::infer::{self, ValueType};
+    #[cfg(feature = "xsd")]
+    use simple_xml::xsd;
+
+    fn tasks() -> Vec<simple_xml::Node> {
+        let samples = [
+            r#"<tasks owner="ada">
+                <task id="1" state="open" done="false"><title>Write parser</title><due>2024-03-01</due><estimate>1.5</estimate></task>
+                <task id="2" state="closed" done="true"><title>Write tests</title><estimate>2</estimate>
+                    <task id="3" state="open" done="false"><title>Fuzz parser</title><estimate>0.5</estimate></task>
+                </task>
+            </tasks>"#,
+            r#"<tasks><task id="4" state="open" done="true"><title>Release</title><estimate>1</estimate></task></tasks>"#,
+        ];
+
+        samples
+            .iter()
+            .map(|v| simple_xml::from_string(v).expect("Failed to parse sample"))
+            .collect()
+    }
+
+    #[test]
+    fn infer_structure() {
+        let samples = tasks();
+        let schema = infer::infer(&samples);
+        assert_eq!(schema.roots, ["tasks"]);
+
+        let root = &schema.elements["tasks"];
+        assert!(root.attributes["owner"].optional);
+        assert_eq!(root.children.len(), 1);
+        assert!(root.children[0].repeated);
+        assert!(!root.children[0].optional);
+
+        let task = &schema.elements["task"];
+        assert!(task.ordered);
+        assert_eq!(task.attributes["id"].ty, ValueType::Integer);
+        assert!(!task.attributes["id"].optional);
+        assert_eq!(task.attributes["done"].ty, ValueType::Boolean);
+        assert_eq!(
+            task.attributes["state"].ty,
+            ValueType::Enum(vec!["closed".into(), "open".into()])
+        );
+
+        let children: Vec<_> = task
+            .children
+            .iter()
+            .map(|v| (v.name.as_str(), v.optional, v.repeated))
+            .collect();
+        assert_eq!(
+            children,
+            [
+                ("title", false, false),
+                ("due", true, false),
+                ("estimate", false, false),
+                ("task", true, false),
+            ]
+        );
+
+        assert_eq!(schema.elements["due"].content, Some(ValueType::Date));
+        assert_eq!(schema.elements["estimate"].content, Some(ValueType::Float));
+        assert_eq!(schema.elements["title"].content, Some(ValueType::String));
+        assert_eq!(schema.elements["task"].content, None);
+    }
+
+    #[test]
+    fn infer_unordered() {
+        let card = simple_xml::from_file("./examples/addressbook.xml").unwrap();
+        let schema = infer::infer([&card]);
+
+        let card = &schema.elements["card"];
+        assert!(!card.ordered);
+        assert!(card.children.iter().all(|v| !v.repeated));
+        assert!(card.children.iter().any(|v| v.name == "age" && v.optional));
+    }
+
+    #[test]
+    #[cfg(feature = "xsd")]
+    fn infer_xsd() {
+        let mut samples = tasks();
+        samples.push(simple_xml::from_file("./examples/shiporder.xml").unwrap());
+        samples.push(simple_xml::from_file("./examples/addressbook.xml").unwrap());
+
+        let inferred = infer::infer(&samples);
+        assert_eq!(inferred.roots, ["tasks", "shiporder", "addressBook"]);
+
+        let schema = xsd::Schema::from_node(&inferred.to_xsd()).expect("Invalid inferred schema");
+        for sample in &samples {
+            assert_eq!(schema.validate(sample), []);
+        }
+
+        let invalid = simple_xml::from_string(
+            r#"<tasks><task id="x" state="blocked" done="true"><title>Release</title></task></tasks>"#,
+        )
+        .unwrap();
+        assert_eq!(schema.validate(&invalid).len(), 3);
+    }
+
+    #[test]
+    fn infer_rust() {
+        let samples = tasks();
+        let schema = infer::infer(&samples);
+        assert_eq!(schema.to_rust(), include_str!("inferred/tasks.rs"));
+
+        let tasks = generated::Tasks::from_node(&samples[0]).expect("Failed to read tasks");
+        assert_eq!(tasks.owner.as_deref(), Some("ada"));
+        assert_eq!(tasks.task.len(), 2);
+        assert_eq!(tasks.task[0].due.as_deref(), Some("2024-03-01"));
+        assert_eq!(tasks.task[1].estimate, 2.0);
+
+        let nested = tasks.task[1].task.as_ref().expect("Missing nested task");
+        assert_eq!(nested.id, 3);
+        assert!(!nested.done);
+        assert_eq!(nested.title, "Fuzz parser");
+
+        let invalid =
+            simple_xml::from_string(r#"<tasks><task id="1" state="open" done="yes"/></tasks>"#)
+                .unwrap();
+        assert!(generated::Tasks::from_node(&invalid).is_err());
+    }
+
+    #[test]
+    fn infer_rust_graph() {
+        let sample = simple_xml::from_file("./examples/graph.xml").unwrap();
+        let schema = infer::infer(std::slice::from_ref(&sample));
+        assert_eq!(schema.to_rust(), include_str!("inferred/graph.rs"));
+
+        let graph = graph::Graph::from_node(&sample).expect("Failed to read graph");
+        assert_eq!(graph.node.len(), 4);
+        assert_eq!(graph.node[0].label.as_deref(), Some("Start"));
+        assert_eq!(graph.edge[4].from, "n4");
+    }
+
+    const NAMES: &str = r#"<root>
+        <foo-bar a="1"/><foo_bar b="2"/><option self="3"><type>t</type></option>
+        <Self super="4"/><vec crate="5" macro="6"/><string-list gen="7"/>
+    </root>"#;
+
+    #[test]
+    fn infer_rust_names() {
+        let sample = simple_xml::from_string(NAMES).unwrap();
+        let schema = infer::infer(std::slice::from_ref(&sample));
+        assert_eq!(schema.to_rust(), include_str!("inferred/names.rs"));
+
+        let root = names::Root::from_node(&sample).expect("Failed to read names");
+        assert_eq!(root.foo_bar.a, 1);
+        assert_eq!(root.foo_bar_2.b, 2);
+        assert_eq!(root.option.self_, 3);
+        assert_eq!(root.option.r#type, "t");
+        assert_eq!(root.self_.super_, 4);
+        assert_eq!(root.vec.crate_, 5);
+        assert_eq!(root.vec.r#macro, 6);
+        assert_eq!(root.string_list.r#gen, 7);
+    }
+}
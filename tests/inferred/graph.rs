@@ -0,0 +1,67 @@
+/// <edge>
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    /// One of ["n1", "n2", "n3", "n4"]
+    pub from: String,
+    pub id: String,
+    /// One of ["n1", "n2", "n3", "n4"]
+    pub to: String,
+}
+
+impl Edge {
+    pub fn from_node(node: &simple_xml::Node) -> Result<Self, simple_xml::Error> {
+        Ok(Edge {
+            from: node.attr_as("from")?,
+            id: node.attr_as("id")?,
+            to: node.attr_as("to")?,
+        })
+    }
+}
+
+/// <graph>
+#[derive(Debug, Clone, PartialEq)]
+pub struct Graph {
+    pub node: Vec<Node>,
+    pub init: Init,
+    pub edge: Vec<Edge>,
+}
+
+impl Graph {
+    pub fn from_node(node: &simple_xml::Node) -> Result<Self, simple_xml::Error> {
+        Ok(Graph {
+            node: node["node"].iter().map(Node::from_node).collect::<Result<_, _>>()?,
+            init: { let n = &node.try_get_nodes("init")?[0]; Init::from_node(n)? },
+            edge: node["edge"].iter().map(Edge::from_node).collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// <init>
+#[derive(Debug, Clone, PartialEq)]
+pub struct Init {
+    pub r#ref: String,
+}
+
+impl Init {
+    pub fn from_node(node: &simple_xml::Node) -> Result<Self, simple_xml::Error> {
+        Ok(Init {
+            r#ref: node.attr_as("ref")?,
+        })
+    }
+}
+
+/// <node>
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub id: String,
+    pub label: Option<String>,
+}
+
+impl Node {
+    pub fn from_node(node: &simple_xml::Node) -> Result<Self, simple_xml::Error> {
+        Ok(Node {
+            id: node.attr_as("id")?,
+            label: match node["label"].first() { Some(n) => Some(n.content_as()?), None => None },
+        })
+    }
+}
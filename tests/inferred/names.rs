@@ -0,0 +1,111 @@
+/// <Self>
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfElement {
+    pub super_: i64,
+}
+
+impl SelfElement {
+    pub fn from_node(node: &simple_xml::Node) -> Result<Self, simple_xml::Error> {
+        Ok(SelfElement {
+            super_: node.attr_as("super")?,
+        })
+    }
+}
+
+/// <foo-bar>
+#[derive(Debug, Clone, PartialEq)]
+pub struct FooBar {
+    pub a: i64,
+}
+
+impl FooBar {
+    pub fn from_node(node: &simple_xml::Node) -> Result<Self, simple_xml::Error> {
+        Ok(FooBar {
+            a: node.attr_as("a")?,
+        })
+    }
+}
+
+/// <foo_bar>
+#[derive(Debug, Clone, PartialEq)]
+pub struct FooBar2 {
+    pub b: i64,
+}
+
+impl FooBar2 {
+    pub fn from_node(node: &simple_xml::Node) -> Result<Self, simple_xml::Error> {
+        Ok(FooBar2 {
+            b: node.attr_as("b")?,
+        })
+    }
+}
+
+/// <option>
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptionElement {
+    pub self_: i64,
+    pub r#type: String,
+}
+
+impl OptionElement {
+    pub fn from_node(node: &simple_xml::Node) -> Result<Self, simple_xml::Error> {
+        Ok(OptionElement {
+            self_: node.attr_as("self")?,
+            r#type: { let n = &node.try_get_nodes("type")?[0]; n.content_as()? },
+        })
+    }
+}
+
+/// <root>
+#[derive(Debug, Clone, PartialEq)]
+pub struct Root {
+    pub foo_bar: FooBar,
+    pub foo_bar_2: FooBar2,
+    pub option: OptionElement,
+    pub self_: SelfElement,
+    pub vec: VecElement,
+    pub string_list: StringList,
+}
+
+impl Root {
+    pub fn from_node(node: &simple_xml::Node) -> Result<Self, simple_xml::Error> {
+        Ok(Root {
+            foo_bar: { let n = &node.try_get_nodes("foo-bar")?[0]; FooBar::from_node(n)? },
+            foo_bar_2: { let n = &node.try_get_nodes("foo_bar")?[0]; FooBar2::from_node(n)? },
+            option: { let n = &node.try_get_nodes("option")?[0]; OptionElement::from_node(n)? },
+            self_: { let n = &node.try_get_nodes("Self")?[0]; SelfElement::from_node(n)? },
+            vec: { let n = &node.try_get_nodes("vec")?[0]; VecElement::from_node(n)? },
+            string_list: { let n = &node.try_get_nodes("string-list")?[0]; StringList::from_node(n)? },
+        })
+    }
+}
+
+/// <string-list>
+#[derive(Debug, Clone, PartialEq)]
+pub struct StringList {
+    pub r#gen: i64,
+}
+
+impl StringList {
+    pub fn from_node(node: &simple_xml::Node) -> Result<Self, simple_xml::Error> {
+        Ok(StringList {
+            r#gen: node.attr_as("gen")?,
+        })
+    }
+}
+
+/// <vec>
+#[derive(Debug, Clone, PartialEq)]
+pub struct VecElement {
+    pub crate_: i64,
+    pub r#macro: i64,
+}
+
+impl VecElement {
+    pub fn from_node(node: &simple_xml::Node) -> Result<Self, simple_xml::Error> {
+        Ok(VecElement {
+            crate_: node.attr_as("crate")?,
+            r#macro: node.attr_as("macro")?,
+        })
+    }
+}
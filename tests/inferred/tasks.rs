@@ -0,0 +1,43 @@
+/// <task>
+#[derive(Debug, Clone, PartialEq)]
+pub struct Task {
+    pub done: bool,
+    pub id: i64,
+    /// One of ["closed", "open"]
+    pub state: String,
+    pub title: String,
+    /// A date formatted as YYYY-MM-DD
+    pub due: Option<String>,
+    pub estimate: f64,
+    pub task: Option<Box<Task>>,
+}
+
+impl Task {
+    pub fn from_node(node: &simple_xml::Node) -> Result<Self, simple_xml::Error> {
+        Ok(Task {
+            done: node.attr_as("done")?,
+            id: node.attr_as("id")?,
+            state: node.attr_as("state")?,
+            title: { let n = &node.try_get_nodes("title")?[0]; n.content_as()? },
+            due: match node["due"].first() { Some(n) => Some(n.content_as()?), None => None },
+            estimate: { let n = &node.try_get_nodes("estimate")?[0]; n.content_as()? },
+            task: match node["task"].first() { Some(n) => Some(Box::new(Task::from_node(n)?)), None => None },
+        })
+    }
+}
+
+/// <tasks>
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tasks {
+    pub owner: Option<String>,
+    pub task: Vec<Task>,
+}
+
+impl Tasks {
+    pub fn from_node(node: &simple_xml::Node) -> Result<Self, simple_xml::Error> {
+        Ok(Tasks {
+            owner: match node.get_attribute("owner") { Some(_) => Some(node.attr_as("owner")?), None => None },
+            task: node["task"].iter().map(Task::from_node).collect::<Result<_, _>>()?,
+        })
+    }
+}
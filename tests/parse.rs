@@ -78,4 +78,362 @@ mod tests {
         assert_eq!(graph["edge"][4].attributes["from"], "n4");
         assert_eq!(graph["edge"][4].attributes["to"], "n3");
     }
+
+    #[test]
+    fn parse_limits() {
+        use simple_xml::error::Limit;
+        use simple_xml::{Error, ParseError, ParseOptions};
+
+        fn limit(string: &str, options: &ParseOptions) -> Option<Limit> {
+            match simple_xml::from_string_with_options(string, options) {
+                Err(Error::ParseError(ParseError::LimitExceeded(limit, _), _)) => Some(limit),
+                Err(e) => panic!("Expected LimitExceeded, got {:?}", e),
+                Ok(_) => None,
+            }
+        }
+
+        // Deep nesting is rejected instead of overflowing the stack
+        let deep = "<a>".repeat(1_000_000) + &"</a>".repeat(1_000_000);
+        assert_eq!(limit(&deep, &ParseOptions::default()), Some(Limit::Depth));
+
+        let options = ParseOptions {
+            max_depth: 3,
+            max_attributes: 2,
+            max_name_length: 8,
+            max_text_size: 16,
+            max_nodes: 4,
+            ..Default::default()
+        };
+        assert_eq!(limit("<a><b><c/></b></a>", &options), None);
+        assert_eq!(
+            limit("<a><b><c><d/></c></b></a>", &options),
+            Some(Limit::Depth)
+        );
+        assert_eq!(limit(r#"<a x="1" y="2"/>"#, &options), None);
+        assert_eq!(
+            limit(r#"<a x="1" y="2" z="3"/>"#, &options),
+            Some(Limit::Attributes)
+        );
+        assert_eq!(limit("<abcdefghi/>", &options), Some(Limit::NameLength));
+        assert_eq!(
+            limit(r#"<a abcdefghi="1"/>"#, &options),
+            Some(Limit::NameLength)
+        );
+        assert_eq!(limit("<a>0123456789abcdef</a>", &options), None);
+        assert_eq!(
+            limit("<a>0123456789abcdefg</a>", &options),
+            Some(Limit::TextSize)
+        );
+        assert_eq!(limit("<a><b/><b/><b/></a>", &options), None);
+        assert_eq!(
+            limit("<a><b/><b/><b/><b/></a>", &options),
+            Some(Limit::Nodes)
+        );
+
+        match simple_xml::from_string_with_options("<a>\n<b>\n<c><d/></c></b></a>", &options) {
+            Err(Error::ParseError(ParseError::LimitExceeded(Limit::Depth, 3), 2)) => {}
+            v => panic!("Expected depth limit on line 2, got {:?}", v),
+        }
+    }
+
+    #[test]
+    fn parse_nested_content_model() {
+        use simple_xml::{Error, ParseError};
+
+        // Deeply nested groups are rejected instead of overflowing the stack
+        let groups = 200_000;
+        let doctype = format!(
+            "<!DOCTYPE a [<!ELEMENT a {}b{}>]><a/>",
+            "(".repeat(groups),
+            ")".repeat(groups)
+        );
+        assert!(matches!(
+            simple_xml::document_from_string(&doctype),
+            Err(Error::ParseError(ParseError::InvalidDeclaration(_), _))
+        ));
+        assert!(!simple_xml::check_well_formed(&doctype).is_empty());
+
+        let doctype = format!(
+            "<!DOCTYPE a [<!ELEMENT a {}b{}><!ELEMENT b EMPTY>]><a><b/></a>",
+            "(".repeat(100),
+            ")".repeat(100)
+        );
+        let document = simple_xml::document_from_string(&doctype).unwrap();
+        let dtd = document.doctype.as_ref().unwrap().internal_subset.as_ref();
+        assert_eq!(simple_xml::dtd::validate(&document, dtd.unwrap()), []);
+    }
+
+    #[test]
+    fn parse_lenient() {
+        use simple_xml::ParseError;
+
+        let note = std::fs::read_to_string("./examples/note.xml").unwrap();
+        let (root, diagnostics) = simple_xml::from_string_lenient(&note);
+        assert_eq!(diagnostics, []);
+        let strict = simple_xml::from_string(&note).unwrap();
+        assert_eq!(root.attributes, strict.attributes);
+        assert_eq!(root["body"][0].content, strict["body"][0].content);
+
+        let (root, diagnostics) = simple_xml::from_string_lenient(
+            "<html>\n<body class=main hidden><p>Fish & chips</b><p>Peas</body>\n<!-- unterminated",
+        );
+        assert_eq!(root.tag, "html");
+        let body = &root["body"][0];
+        assert_eq!(body.attributes["class"], "main");
+        assert_eq!(body.attributes["hidden"], "");
+        assert_eq!(body["p"][0].content, "Fish & chips</b>");
+        assert_eq!(body["p"][0]["p"][0].content, "Peas");
+        assert_eq!(root.content, "<!-- unterminated");
+
+        let errors: Vec<_> = diagnostics
+            .iter()
+            .map(|v| (v.line, v.column, v.error.clone()))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                (2, 7, ParseError::MissingQuotes("class=main".into())),
+                (2, 18, ParseError::MissingAttributeValue("hidden".into())),
+                (2, 33, ParseError::InvalidReference("&".into())),
+                (2, 40, ParseError::UnexpectedClosingTag("b".into())),
+                (2, 44, ParseError::MissingClosingTag("p".into())),
+                (2, 25, ParseError::MissingClosingTag("p".into())),
+                (3, 1, ParseError::MissingClosingDelimiter),
+                (1, 1, ParseError::MissingClosingTag("html".into())),
+            ]
+        );
+        assert_eq!(
+            diagnostics[7].to_string(),
+            "1:1: error: Missing closing tag for \"html\""
+        );
+
+        // Multiple top level elements are returned inside a node without a tag
+        let (root, diagnostics) = simple_xml::from_string_lenient("<a/> between <b></b> after");
+        assert_eq!(root.tag, "");
+        assert_eq!(root.content, "between after");
+        assert_eq!(
+            root.children().map(|v| v.tag.as_str()).collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+        assert_eq!(diagnostics.len(), 3);
+        assert!(diagnostics
+            .iter()
+            .all(|v| v.error == ParseError::ContentOutsideRoot));
+
+        // Limits stop lenient parsing, keeping what was read
+        let options = simple_xml::ParseOptions {
+            max_depth: 2,
+            ..Default::default()
+        };
+        let (root, diagnostics) =
+            simple_xml::from_string_lenient_with_options("<a><b>text<c/></b></a>", &options);
+        assert_eq!(root["b"][0].content, "text");
+        assert!(matches!(
+            diagnostics[..],
+            [simple_xml::Diagnostic {
+                error: ParseError::LimitExceeded(_, 2),
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn check_well_formed() {
+        use simple_xml::{ParseError, Severity};
+
+        let note = std::fs::read_to_string("./examples/note.xml").unwrap();
+        assert_eq!(simple_xml::check_well_formed(&note), []);
+        let doctype = std::fs::read_to_string("./examples/doctype.xml").unwrap();
+        assert_eq!(simple_xml::check_well_formed(&doctype), []);
+
+        let diagnostics = simple_xml::check_well_formed(
+            "<1note to=\"a\" to=\"b\" x\"y=\"1\">\n\
+             <p>&lt; &#x41; &#0; &amp &nbsp; \u{1}\u{7F}</p>\n\
+             <q>Unclosed</p>\n\
+             </1note>",
+        );
+
+        let errors: Vec<_> = diagnostics
+            .iter()
+            .map(|v| (v.line, v.column, v.severity, v.error.clone()))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                (
+                    2,
+                    33,
+                    Severity::Error,
+                    ParseError::IllegalCharacter('\u{1}')
+                ),
+                (
+                    2,
+                    34,
+                    Severity::Warning,
+                    ParseError::DiscouragedCharacter('\u{7F}')
+                ),
+                (
+                    1,
+                    2,
+                    Severity::Error,
+                    ParseError::InvalidName("1note".into())
+                ),
+                (
+                    1,
+                    15,
+                    Severity::Error,
+                    ParseError::DuplicateAttribute("to".into())
+                ),
+                (
+                    1,
+                    22,
+                    Severity::Error,
+                    ParseError::InvalidName("x\"y".into())
+                ),
+                (
+                    2,
+                    16,
+                    Severity::Error,
+                    ParseError::InvalidReference("&#0;".into())
+                ),
+                (
+                    2,
+                    21,
+                    Severity::Error,
+                    ParseError::InvalidReference("&amp".into())
+                ),
+                (
+                    2,
+                    26,
+                    Severity::Error,
+                    ParseError::UndeclaredEntity("nbsp".into())
+                ),
+                (
+                    3,
+                    12,
+                    Severity::Error,
+                    ParseError::UnexpectedClosingTag("p".into())
+                ),
+                (
+                    3,
+                    1,
+                    Severity::Error,
+                    ParseError::MissingClosingTag("q".into())
+                ),
+            ]
+        );
+
+        // Entities declared in the document type declaration are known
+        let declared = "<!DOCTYPE a [<!ENTITY nbsp \"&#160;\">]><a>&nbsp;&copy;</a>";
+        let diagnostics = simple_xml::check_well_formed(declared);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].error,
+            ParseError::UndeclaredEntity("copy".into())
+        );
+
+        // Entities may be declared in an external subset which is not read
+        let external = "<!DOCTYPE a SYSTEM \"a.dtd\"><a>&copy;</a>";
+        let diagnostics = simple_xml::check_well_formed(external);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+
+        let diagnostics = simple_xml::check_well_formed("  ");
+        assert_eq!(diagnostics[0].error, ParseError::MissingRoot);
+    }
+
+    #[test]
+    fn parse_invalid_names() {
+        use simple_xml::{Error, ParseError, ParseOptions};
+
+        for (input, expected, line) in [
+            ("<1abc/>", ParseError::InvalidName("1abc".into()), 0),
+            ("<a\n b&c=\"1\"/>", ParseError::InvalidName("b&c".into()), 1),
+            (
+                "<a>\n<-b></-b></a>",
+                ParseError::InvalidName("-b".into()),
+                1,
+            ),
+            (
+                "<a x=\"1\" x=\"2\"/>",
+                ParseError::DuplicateAttribute("x".into()),
+                0,
+            ),
+        ] {
+            match simple_xml::from_string(input) {
+                Err(Error::ParseError(e, ln)) => {
+                    assert_eq!(e, expected);
+                    assert_eq!(ln, line, "{:?}", input);
+                }
+                v => panic!("Expected {:?} for {:?}, got {:?}", expected, input, v),
+            }
+        }
+
+        // Attributes separated by more than one whitespace are valid
+        let root = simple_xml::from_string("<a\n    x=\"1\"   y=\"2\"/>").unwrap();
+        assert_eq!(root.attributes["x"], "1");
+        assert_eq!(root.attributes["y"], "2");
+
+        let relaxed = ParseOptions {
+            check_names: false,
+            check_duplicate_attributes: false,
+            ..Default::default()
+        };
+        let root = simple_xml::from_string_with_options("<1abc x=\"1\" x=\"2\"/>", &relaxed)
+            .expect("Failed to parse without checks");
+        assert_eq!(root.tag, "1abc");
+        assert_eq!(root.attributes["x"], "2");
+    }
+
+    #[test]
+    fn parse_whitespace() {
+        use simple_xml::{ParseOptions, Whitespace};
+
+        let parse = |string: &str, whitespace| {
+            let options = ParseOptions {
+                whitespace,
+                ..Default::default()
+            };
+            simple_xml::from_string_with_options(string, &options).expect("Failed to parse")
+        };
+
+        let xml = "<a>\n    <b>  1  2\t\n3 </b>\n    text <c/>  </a>";
+        let root = parse(xml, Whitespace::Preserve);
+        assert_eq!(root["b"][0].content, "  1  2\t\n3 ");
+        assert_eq!(root.content, "\n    \n    text   ");
+        assert_eq!(
+            root.to_string_pretty_with(Whitespace::Preserve),
+            root.to_string()
+        );
+
+        let root = parse(xml, Whitespace::Trim);
+        assert_eq!(root["b"][0].content, "1  2\t\n3");
+        assert_eq!(root.content, "text");
+
+        let root = parse(xml, Whitespace::Normalize);
+        assert_eq!(root["b"][0].content, "1 2 3");
+        assert_eq!(root.content, "text");
+        assert_eq!(
+            parse(
+                &root.to_string_pretty_with(Whitespace::Normalize),
+                Whitespace::Normalize
+            ),
+            root
+        );
+
+        // xml:space="preserve" applies to the whole subtree until reset with xml:space="default"
+        let xml = r#"<a>
+            <pre xml:space="preserve"> x <i> y </i><d xml:space="default"> z </d></pre>
+            <p> w </p>
+        </a>"#;
+        let root = parse(xml, Whitespace::Trim);
+        let pre = &root["pre"][0];
+        assert_eq!(pre.content, " x ");
+        assert_eq!(pre["i"][0].content, " y ");
+        assert_eq!(pre["d"][0].content, "z");
+        assert_eq!(root["p"][0].content, "w");
+
+        let pretty = root.to_string_pretty();
+        assert!(pretty.contains("<i> y </i><d"));
+        assert_eq!(parse(&pretty, Whitespace::Trim), root);
+    }
 }
@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod tests {
+    use simple_xml::patch;
+    use simple_xml::Error;
+
+    fn parse(string: &str) -> simple_xml::Node {
+        simple_xml::from_string(string).expect("Failed to parse")
+    }
+
+    #[test]
+    fn apply_operations() {
+        let mut root = parse(r#"<a x="1"><b>one</b><c/><b>two</b></a>"#);
+        let diff = parse(
+            r#"<diff>
+                <add sel="/a/c" pos="before"><d/></add>
+                <add sel="/a/c" pos="after"><e/><f/></add>
+                <add sel="/a" pos="prepend"><g/></add>
+                <add sel="/a/c"><h/></add>
+                <add sel="/a" type="@y">2</add>
+                <replace sel="/a/@x">3</replace>
+                <replace sel="/a/b[2]/text()">three</replace>
+                <replace sel="/a/b[1]"><i/></replace>
+                <remove sel="/a/f"/>
+            </diff>"#,
+        );
+
+        patch::apply(&mut root, &diff).expect("Failed to apply patch");
+        assert_eq!(
+            root.children().map(|v| v.tag.as_str()).collect::<Vec<_>>(),
+            ["g", "i", "d", "c", "e", "b"]
+        );
+        assert_eq!(root["c"][0]["h"].len(), 1);
+        assert_eq!(root["b"][0].content, "three");
+        assert_eq!(root.get_attribute("x").unwrap(), "3");
+        assert_eq!(root.get_attribute("y").unwrap(), "2");
+        assert!(root.get_nodes("f").is_none());
+        assert_eq!(root.to_string().matches("<f").count(), 0);
+
+        let diff = parse(r#"<diff><remove sel="/a/@x"/><remove sel="/a/b/text()"/></diff>"#);
+        patch::apply(&mut root, &diff).expect("Failed to apply patch");
+        assert!(root.get_attribute("x").is_none());
+        assert_eq!(root["b"][0].content, "");
+
+        let diff = parse(r#"<diff><replace sel="/a"><z/></replace></diff>"#);
+        patch::apply(&mut root, &diff).expect("Failed to apply patch");
+        assert_eq!(root.tag, "z");
+    }
+
+    #[test]
+    fn apply_mixed_content() {
+        let mut root = parse("<a>t1<b/>t2</a>");
+        let diff = parse(
+            r#"<diff>
+                <add sel="/a/b" pos="before"><c/></add>
+                <add sel="/a/b" pos="after"><d/></add>
+                <add sel="/a" pos="prepend">p<e/></add>
+                <add sel="/a">x<f/>y</add>
+            </diff>"#,
+        );
+
+        patch::apply(&mut root, &diff).expect("Failed to apply patch");
+        assert_eq!(root.to_string(), "<a>p<e/>t1<c/><b/><d/>t2x<f/>y</a>");
+
+        let diff = parse(r#"<diff><remove sel="/a/text()"/><remove sel="/a/c"/></diff>"#);
+        patch::apply(&mut root, &diff).expect("Failed to apply patch");
+        assert_eq!(root.to_string(), "<a><e/><b/><d/><f/></a>");
+    }
+
+    #[test]
+    fn apply_atomically() {
+        let original = parse(r#"<a><b/><b/></a>"#);
+        let mut root = original.clone();
+
+        let failing = |diff: &str| {
+            let mut root = original.clone();
+            let result = patch::apply(&mut root, &parse(diff));
+            assert_eq!(root, original);
+            result.expect_err("Patch should fail")
+        };
+
+        let e = failing(r#"<diff><remove sel="/a/b[1]"/><remove sel="/a/c"/></diff>"#);
+        assert!(matches!(e, Error::SelectorNotFound(sel) if sel == "/a/c"));
+        let e =
+            failing(r#"<diff><add sel="/a/b[1]" type="@x">1</add><remove sel="/a/@y"/></diff>"#);
+        assert!(matches!(e, Error::SelectorNotFound(sel) if sel == "/a/@y"));
+        let e = failing(r#"<diff><remove sel="/a/b"/></diff>"#);
+        assert!(matches!(e, Error::InvalidPatch(_)));
+        assert_eq!(
+            e.to_string(),
+            "Invalid patch: <remove> selector \"/a/b\" matches more than one node"
+        );
+
+        assert!(matches!(
+            failing(r#"<diff><remove sel="/a"/></diff>"#),
+            Error::InvalidPatch(_)
+        ));
+        assert!(matches!(
+            failing(r#"<diff><move sel="/a"/></diff>"#),
+            Error::InvalidPatch(_)
+        ));
+        assert!(matches!(
+            failing(r#"<diff><remove/></diff>"#),
+            Error::InvalidPatch(_)
+        ));
+        assert!(matches!(
+            failing(r#"<diff><remove sel="/a/["/></diff>"#),
+            Error::InvalidPath(_)
+        ));
+
+        patch::apply(&mut root, &parse(r#"<diff><remove sel="/a/b[2]"/></diff>"#)).unwrap();
+        assert_eq!(root.child_count(), 1);
+    }
+
+    #[test]
+    fn patch_from_diff() {
+        let pairs = [
+            (
+                r#"<a><b id="1"/><c><d/></c><b id="2">x</b></a>"#,
+                r#"<a><c><d>y</d><e/></c><b id="2" n="0">z</b><b id="1"/></a>"#,
+            ),
+            ("<a><b/><b/><c/><b/></a>", "<a><c/><b/><d/><b/><b/><b/></a>"),
+            ("<a><b><c/></b><d/></a>", "<a><b/><d><c/></d></a>"),
+            (r#"<a x="1">text</a>"#, r#"<b y="2"/>"#),
+        ];
+
+        for (old, new) in pairs {
+            let (old, new) = (parse(old), parse(new));
+            let diff = patch::from_diff(&old, &new, &simple_xml::diff(&old, &new));
+            let mut patched = old.clone();
+            patch::apply(&mut patched, &diff).expect("Failed to apply generated patch");
+            assert_eq!(patched, new, "Patch {}", diff);
+
+            // The patch survives serialization
+            let mut patched = old.clone();
+            patch::apply(&mut patched, &parse(&diff.to_string())).unwrap();
+            assert_eq!(patched, new);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+#[cfg(test)]
+mod tests {
+    use simple_xml::path::{Path, Target};
+
+    fn tags(nodes: Vec<&simple_xml::Node>) -> Vec<String> {
+        nodes
+            .iter()
+            .map(|v| match v.get_attribute("id") {
+                Some(id) => format!("{}#{}", v.tag, id),
+                None => v.tag.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn select_paths() {
+        let root = simple_xml::from_string(
+            r#"<a id="0">
+                <b id="1"><c id="2"/><c id="3" x="y"/></b>
+                <d id="4"><b id="5"><c id="6"/></b></d>
+                <b id="7"/>
+            </a>"#,
+        )
+        .unwrap();
+
+        let select = |path: &str| tags(root.select(path).expect("Invalid path"));
+        assert_eq!(select("/a"), ["a#0"]);
+        assert_eq!(select("/b"), Vec::<String>::new());
+        assert_eq!(select("b"), ["b#1", "b#7"]);
+        assert_eq!(select("/a/b[2]"), ["b#7"]);
+        assert_eq!(select("/a/b[last()]"), ["b#7"]);
+        assert_eq!(select("/a/*"), ["b#1", "d#4", "b#7"]);
+        assert_eq!(select("//c"), ["c#2", "c#3", "c#6"]);
+        assert_eq!(select("//b/c[1]"), ["c#2", "c#6"]);
+        assert_eq!(select("//c[@x]"), ["c#3"]);
+        assert_eq!(select("//c[@id='6']/../.."), ["d#4"]);
+        assert_eq!(select("d//c"), ["c#6"]);
+        assert_eq!(select("//*[@id=\"3\"]/."), ["c#3"]);
+
+        let path = Path::parse("//b/@id").unwrap();
+        assert_eq!(path.target(), &Target::Attribute("id".into()));
+        assert_eq!(path.values(&root), ["1", "5", "7"]);
+
+        let locations = Path::parse("//c[@id='6']").unwrap().locate(&root);
+        assert_eq!(locations[0].to_string(), "/d[1]/b[1]/c[1]");
+
+        // Paths are evaluated as XPath
+        assert_eq!(select("/a[0]"), Vec::<String>::new());
+        assert_eq!(select("//c[not(@x)][last()]"), ["c#2", "c#6"]);
+        assert_eq!(select("//c[../@id = 5] | /a/b[c]"), ["b#1", "c#6"]);
+        assert_eq!(
+            select("//b[count(c) = 2]/following-sibling::*"),
+            ["d#4", "b#7"]
+        );
+        assert_eq!(select("//c[@id='3']/ancestor::*"), ["a#0", "b#1"]);
+        assert_eq!(select("(//c)[2]/.."), ["b#1"]);
+        assert_eq!(select("/"), ["a#0"]);
+        assert_eq!(
+            Path::parse("//@id").unwrap().values(&root),
+            ["0", "1", "2", "3", "4", "5", "6", "7"]
+        );
+
+        for invalid in [
+            "",
+            "/a/@id/b",
+            "/a[",
+            "/a/b c",
+            "a/text()/b",
+            "node[1]]",
+            "node[1]é]",
+            "node[1]x[2]",
+            "1 + 1",
+            "count(//c)",
+            "//c[$x]",
+            "//c[unknown()]",
+            "//c[count(1)]",
+            "//c[contains(@id)]",
+            "//c/@* | //b",
+        ] {
+            assert!(
+                matches!(Path::parse(invalid), Err(simple_xml::Error::InvalidPath(_))),
+                "{:?}",
+                invalid
+            );
+        }
+
+        let nested = format!("//c[{}1{}]", "(".repeat(100_000), ")".repeat(100_000));
+        assert!(Path::parse(&nested).is_err());
+        let chained = format!("//c[{}]", vec!["1"; 100_000].join("+"));
+        assert!(Path::parse(&chained).is_err());
+    }
+}
@@ -0,0 +1,126 @@
+#[cfg(test)]
+mod tests {
+    use simple_xml::relaxng::{DatatypeLibrary, Schema, ViolationKind};
+
+    #[cfg(feature = "xsd")]
+    const INVALID: &str = r#"<addressBook>
+        <card priority="urgent" id="4">
+            <name>John Smith</name>
+            <email>john</email>
+        </card>
+        <card>
+            <email>fred@example.com</email>
+            <age>-1</age>
+        </card>
+        <person/>
+        <card><name>Jane</name><email>jane@example.com</email></card>
+    </addressBook>"#;
+
+    #[cfg(feature = "xsd")]
+    fn check(schema: &Schema) {
+        let book = simple_xml::from_file("./examples/addressbook.xml").unwrap();
+        assert_eq!(schema.validate(&book), []);
+
+        let violations = schema
+            .validate(&simple_xml::from_string(INVALID).unwrap())
+            .into_iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            violations,
+            [
+                "/addressBook/card[1]: Attribute \"id\" is not allowed",
+                "/addressBook/card[1]: Invalid value \"urgent\" for attribute \"priority\"",
+                "/addressBook/card[1]/email[1]: Text \"john\" is not allowed here",
+                "/addressBook/card[2]/age[1]: Text \"-1\" is not allowed here",
+                "/addressBook/card[2]: Element is incomplete, expected one of [\"name\"]",
+                "/addressBook/person[1]: Element \"person\" is not allowed here, expected one of [\"card\"]",
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "xsd")]
+    fn xml_syntax() {
+        check(
+            &simple_xml::relaxng::from_file("./examples/addressbook.rng")
+                .expect("Failed to load schema"),
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "xsd")]
+    fn compact_syntax() {
+        check(
+            &simple_xml::relaxng::from_file("./examples/addressbook.rnc")
+                .expect("Failed to load schema"),
+        );
+    }
+
+    #[test]
+    fn datatype_library() {
+        struct Even;
+
+        impl DatatypeLibrary for Even {
+            fn contains(&self, datatype: &str) -> bool {
+                datatype == "even"
+            }
+
+            fn allows(&self, _: &str, _: &[(String, String)], value: &str) -> bool {
+                value.parse::<u32>().is_ok_and(|v| v % 2 == 0)
+            }
+
+            fn equal(&self, _: &str, a: &str, b: &str) -> bool {
+                a.parse::<u32>().ok() == b.parse::<u32>().ok()
+            }
+        }
+
+        let mut schema = Schema::from_compact(
+            r#"datatypes num = "urn:numbers"
+            element pair { attribute count { num:even }, element item { text }+ }"#,
+        )
+        .expect("Failed to load schema");
+
+        let pair = simple_xml::from_string(r#"<pair count="2"><item/><item/></pair>"#).unwrap();
+        match &schema.validate(&pair)[..] {
+            [v] => assert_eq!(
+                v.kind,
+                ViolationKind::UnknownDatatypeLibrary("urn:numbers".into())
+            ),
+            v => panic!("Expected UnknownDatatypeLibrary, got {:?}", v),
+        }
+
+        schema.add_library("urn:numbers", Even);
+        assert_eq!(schema.validate(&pair), []);
+
+        let odd = simple_xml::from_string(r#"<pair count="3"><item/></pair>"#).unwrap();
+        assert_eq!(
+            schema.validate(&odd)[0].kind,
+            ViolationKind::InvalidAttributeValue {
+                name: "count".into(),
+                value: "3".into()
+            }
+        );
+
+        let missing = simple_xml::from_string(r#"<pair><item/></pair>"#).unwrap();
+        assert_eq!(
+            schema.validate(&missing)[0].kind,
+            ViolationKind::MissingAttribute("count".into())
+        );
+    }
+
+    #[test]
+    fn invalid_schema() {
+        for schema in [
+            "element a { b }",
+            "element a { text, empty | text }",
+            "start = a a = a | element b { empty }",
+        ] {
+            match Schema::from_compact(schema) {
+                Err(simple_xml::Error::InvalidSchema(_)) => {}
+                v => panic!("Expected InvalidSchema for {:?}, got {:?}", schema, v),
+            }
+        }
+    }
+}
@@ -0,0 +1,46 @@
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn parse_attributes_and_content() {
+        let root = simple_xml::from_file("./examples/cube.dae").expect("Failed to parse cube.dae");
+        let source = &root["library_geometries"][0]["geometry"][0]["mesh"][0]["source"][0];
+        let array = &source["float_array"][0];
+
+        let count: usize = array.attr_as("count").unwrap();
+        let positions: Vec<f32> = array.content_as_vec().unwrap();
+        assert_eq!(positions.len(), count);
+        assert_eq!(positions[..3], [1.0, 1.0, 1.0]);
+
+        let person =
+            simple_xml::from_file("./examples/person.xml").expect("Failed to parse person");
+        assert_eq!(person["age"][0].content_as::<u32>().unwrap(), 17);
+        assert_eq!(person["friends"][0].attr_as::<u8>("count").unwrap(), 12);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let person =
+            simple_xml::from_file("./examples/person.xml").expect("Failed to parse person");
+
+        match person["partners"][0].attr_as::<u32>("sex") {
+            Err(simple_xml::Error::AttributeParse(tag, key, _)) => {
+                assert_eq!(tag, "partners");
+                assert_eq!(key, "sex");
+            }
+            v => panic!("Expected AttributeParse, got {:?}", v),
+        }
+
+        match person["partners"][0].attr_as::<u32>("missing") {
+            Err(simple_xml::Error::AttributeNotFound(_, _)) => {}
+            v => panic!("Expected AttributeNotFound, got {:?}", v),
+        }
+
+        match person["friends"][0].content_as_vec::<f32>() {
+            Err(simple_xml::Error::ContentParse(tag, e)) => {
+                assert_eq!(tag, "friends");
+                assert!(e.downcast_ref::<std::num::ParseFloatError>().is_some());
+            }
+            v => panic!("Expected ContentParse, got {:?}", v),
+        }
+    }
+}
@@ -0,0 +1,234 @@
+#[cfg(test)]
+mod tests {
+    use simple_xml::{Fold, Node, Visitor, Walk};
+
+    fn parse(string: &str) -> Node {
+        simple_xml::from_string(string).expect("Failed to parse")
+    }
+
+    /// Records the callbacks as a list of events
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+        skip: Option<&'static str>,
+        stop: Option<&'static str>,
+    }
+
+    impl Visitor for Recorder {
+        fn enter_element(&mut self, node: &Node) -> Walk {
+            self.events.push(format!("<{}>", node.tag));
+            match Some(node.tag.as_str()) {
+                tag if tag == self.skip => Walk::SkipChildren,
+                tag if tag == self.stop => Walk::Stop,
+                _ => Walk::Continue,
+            }
+        }
+
+        fn leave_element(&mut self, node: &Node) {
+            self.events.push(format!("</{}>", node.tag));
+        }
+
+        fn text(&mut self, node: &Node, text: &str) {
+            self.events.push(format!("{}:{}", node.tag, text));
+        }
+    }
+
+    const DOCUMENT: &str = "<a><b>one<c/></b><d>two</d><e/>three</a>";
+
+    #[test]
+    fn walk() {
+        let root = parse(DOCUMENT);
+        let mut recorder = Recorder::default();
+        root.walk(&mut recorder);
+        assert_eq!(
+            recorder.events,
+            [
+                "<a>", "<b>", "b:one", "<c>", "</c>", "</b>", "<d>", "d:two", "</d>", "<e>",
+                "</e>", "a:three", "</a>"
+            ]
+        );
+
+        let mut recorder = Recorder {
+            skip: Some("b"),
+            stop: Some("e"),
+            ..Default::default()
+        };
+        root.walk(&mut recorder);
+        assert_eq!(
+            recorder.events,
+            ["<a>", "<b>", "</b>", "<d>", "d:two", "</d>", "<e>"]
+        );
+    }
+
+    #[test]
+    fn walk_deep_tree() {
+        let depth = 100_000;
+        let mut node = simple_xml::new("leaf", "end".to_owned());
+        for _ in 0..depth {
+            let mut parent = simple_xml::new("n", String::new());
+            parent.add_node(node);
+            node = parent;
+        }
+
+        struct Counter(usize, usize);
+        impl Visitor for Counter {
+            fn enter_element(&mut self, _: &Node) -> Walk {
+                self.0 += 1;
+                Walk::Continue
+            }
+            fn text(&mut self, _: &Node, _: &str) {
+                self.1 += 1;
+            }
+        }
+        let mut counter = Counter(0, 0);
+        node.walk(&mut counter);
+        assert_eq!((counter.0, counter.1), (depth + 1, 1));
+
+        let node = node.fold(&mut Upper).expect("Root was dropped");
+        assert_eq!(node.tag, "N");
+        // Dropping nodes is recursive and would overflow the stack
+        std::mem::forget(node);
+    }
+
+    /// Uppercases tags and content
+    struct Upper;
+
+    impl Fold for Upper {
+        fn fold_element(&mut self, mut node: Node) -> Option<Node> {
+            node.tag = node.tag.to_uppercase();
+            Some(node)
+        }
+
+        fn fold_text(&mut self, _: &str, text: String) -> String {
+            text.to_uppercase()
+        }
+    }
+
+    #[test]
+    fn fold() {
+        let root = parse(DOCUMENT);
+        let upper = root.clone().fold(&mut Upper).unwrap();
+        assert_eq!(
+            upper.to_string(),
+            "<A><B>ONE<C/></B><D>TWO</D><E/>THREE</A>"
+        );
+
+        struct Rewrite;
+        impl Fold for Rewrite {
+            fn fold_element(&mut self, node: Node) -> Option<Node> {
+                match node.tag.as_str() {
+                    "c" => None,
+                    "d" => Some(Node::builder("f").attr("from", "d").build()),
+                    _ => Some(node),
+                }
+            }
+
+            fn fold_attribute(&mut self, tag: &str, name: &str, value: String) -> Option<String> {
+                match (tag, name) {
+                    (_, "drop") => None,
+                    ("b", _) => Some(format!("{}!", value)),
+                    _ => Some(value),
+                }
+            }
+        }
+
+        let root = parse(r#"<a drop="1"><b x="1" drop="2">one<c/></b><d>two</d><e/></a>"#);
+        let rewritten = root.fold(&mut Rewrite).unwrap();
+        assert_eq!(
+            rewritten
+                .children()
+                .map(|v| v.tag.as_str())
+                .collect::<Vec<_>>(),
+            ["b", "f", "e"]
+        );
+        assert!(rewritten.attributes.is_empty());
+        assert_eq!(rewritten["b"][0].attributes.len(), 1);
+        assert_eq!(rewritten["b"][0].get_attribute("x").unwrap(), "1!");
+        assert_eq!(rewritten["b"][0].child_count(), 0);
+        assert_eq!(rewritten["f"][0].get_attribute("from").unwrap(), "d");
+
+        // Text around a dropped element is joined into a single run
+        let root = parse("<a>one<c/>two<b/>three</a>");
+        let rewritten = root.fold(&mut Rewrite).unwrap().fold(&mut Upper).unwrap();
+        assert_eq!(rewritten.to_string(), "<A>ONETWO<B/>THREE</A>");
+
+        struct DropAll;
+        impl Fold for DropAll {
+            fn fold_element(&mut self, _: Node) -> Option<Node> {
+                None
+            }
+        }
+        assert!(parse(DOCUMENT).fold(&mut DropAll).is_none());
+    }
+
+    fn tags<'a>(nodes: impl Iterator<Item = &'a Node>) -> Vec<&'a str> {
+        nodes.map(|v| v.tag.as_str()).collect()
+    }
+
+    const TREE: &str = "<r><a><b><c/></b><d/></a><e><f/></e><a/></r>";
+
+    #[test]
+    fn descendants() {
+        let root = parse(TREE);
+        assert_eq!(
+            tags(root.descendants()),
+            ["a", "b", "c", "d", "e", "f", "a"]
+        );
+        assert_eq!(tags(root.bfs()), ["a", "e", "a", "b", "d", "f", "c"]);
+        assert_eq!(tags(root["a"][0].descendants()), ["b", "c", "d"]);
+        assert_eq!(root["e"][0]["f"][0].descendants().count(), 0);
+
+        let with_depth: Vec<_> = root
+            .descendants_with_depth()
+            .map(|(depth, location, node)| (depth, location.to_string(), node.tag.as_str()))
+            .collect();
+        assert_eq!(
+            with_depth,
+            [
+                (1, "/a[1]".to_owned(), "a"),
+                (2, "/a[1]/b[1]".to_owned(), "b"),
+                (3, "/a[1]/b[1]/c[1]".to_owned(), "c"),
+                (2, "/a[1]/d[1]".to_owned(), "d"),
+                (1, "/e[1]".to_owned(), "e"),
+                (2, "/e[1]/f[1]".to_owned(), "f"),
+                (1, "/a[2]".to_owned(), "a"),
+            ]
+        );
+        for (_, location, node) in root.descendants_with_depth() {
+            assert!(std::ptr::eq(location.get(&root).unwrap(), node));
+        }
+    }
+
+    #[test]
+    fn find() {
+        let root = parse(r#"<r><a id="1"><b id="2"/></a><b id="3"/></r>"#);
+        let found = root.find(|v| v.tag == "b").unwrap();
+        assert_eq!(found.get_attribute("id").unwrap(), "2");
+        assert!(root.find(|v| v.tag == "r").is_none());
+
+        let ids: Vec<_> = root
+            .find_all(|v| v.attributes.contains_key("id"))
+            .into_iter()
+            .map(|v| v.get_attribute("id").unwrap().as_str())
+            .collect();
+        assert_eq!(ids, ["1", "2", "3"]);
+        assert!(root.find_all(|v| v.tag == "x").is_empty());
+    }
+
+    #[test]
+    fn descendants_of_deep_tree() {
+        let depth = 100_000;
+        let mut node = simple_xml::new("leaf", String::new());
+        for _ in 0..depth {
+            let mut parent = simple_xml::new("n", String::new());
+            parent.add_node(node);
+            node = parent;
+        }
+
+        assert_eq!(node.descendants().count(), depth);
+        assert_eq!(node.bfs().count(), depth);
+        assert_eq!(node.find(|v| v.tag == "leaf").unwrap().tag, "leaf");
+        // Dropping nodes is recursive and would overflow the stack
+        std::mem::forget(node);
+    }
+}
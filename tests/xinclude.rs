@@ -0,0 +1,226 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use simple_xml::xinclude;
+    use simple_xml::Error;
+
+    fn files(entries: &[(&str, &str)]) -> HashMap<PathBuf, String> {
+        entries
+            .iter()
+            .map(|(path, content)| (PathBuf::from(path), content.to_string()))
+            .collect()
+    }
+
+    fn tags(node: &simple_xml::Node) -> Vec<&str> {
+        node.children().map(|v| v.tag.as_str()).collect()
+    }
+
+    #[test]
+    fn relative_includes() {
+        let files = files(&[
+            (
+                "app/main.xml",
+                r#"<config xmlns:xi="http://www.w3.org/2001/XInclude">
+                    <first/>
+                    <xi:include href="parts/db.xml"/>
+                    <last/>
+                </config>"#,
+            ),
+            (
+                "app/parts/db.xml",
+                r#"<db xmlns:x="http://www.w3.org/2001/XInclude">
+                    <x:include href="../shared/credentials.xml"/>
+                    <x:include href="../shared/../shared/host.txt" parse="text"/>
+                </db>"#,
+            ),
+            ("app/shared/credentials.xml", r#"<user name="admin"/>"#),
+            ("app/shared/host.txt", "db.local & co"),
+        ]);
+
+        let root = xinclude::load("app/main.xml", &files).expect("Failed to include");
+        assert_eq!(tags(&root), ["first", "db", "last"]);
+        let db = &root["db"][0];
+        assert_eq!(db["user"][0].get_attribute("name").unwrap(), "admin");
+        assert_eq!(db.content, "db.local &amp; co");
+        assert_eq!(root.to_string().matches("include").count(), 0);
+    }
+
+    #[test]
+    fn mixed_content() {
+        let files = files(&[
+            (
+                "main.xml",
+                r#"<p xmlns:xi="http://www.w3.org/2001/XInclude">Hello,<xi:include href="name.txt" parse="text"/>: see<xi:include href="link.xml"/>or<b>this</b></p>"#,
+            ),
+            ("name.txt", "Ann"),
+            ("link.xml", "<a>here</a>"),
+        ]);
+
+        let root = xinclude::load("main.xml", &files).expect("Failed to include");
+        assert_eq!(
+            root.to_string(),
+            r#"<p xmlns:xi="http://www.w3.org/2001/XInclude">Hello,Ann: see<a>here</a>or<b>this</b></p>"#
+        );
+    }
+
+    #[test]
+    fn fallback() {
+        let files = files(&[(
+            "main.xml",
+            r#"<a xmlns:xi="http://www.w3.org/2001/XInclude">
+                <xi:include href="missing.xml">
+                    <xi:fallback><b/><xi:include href="also-missing.txt" parse="text"><xi:fallback>none</xi:fallback></xi:include></xi:fallback>
+                </xi:include>
+                <xi:include href="main.xml" xpointer="element(nothing)"><xi:fallback><c/></xi:fallback></xi:include>
+            </a>"#,
+        )]);
+
+        let root = xinclude::load("main.xml", &files).expect("Failed to include");
+        assert_eq!(tags(&root), ["b", "c"]);
+        assert_eq!(root.content, "none");
+
+        // Without a fallback the resource error is returned
+        let files = self::files(&[(
+            "main.xml",
+            r#"<a><include xmlns="http://www.w3.org/2001/XInclude" href="missing.xml"/></a>"#,
+        )]);
+        assert!(matches!(
+            xinclude::load("main.xml", &files),
+            Err(Error::IOError(_))
+        ));
+    }
+
+    #[test]
+    fn xpointers() {
+        let files = files(&[
+            (
+                "main.xml",
+                r#"<a xmlns:xi="http://www.w3.org/2001/XInclude">
+                    <xi:include href="lib.xml" xpointer="element(/1/2)"/>
+                    <xi:include href="lib.xml" xpointer="element(colors/2)"/>
+                    <xi:include href="lib.xml" xpointer="sizes"/>
+                    <xi:include href="lib.xml" xpointer="element(missing) element(/1/1/1)"/>
+                    <xi:include xpointer="element(local)"/>
+                    <local xml:id="local"><value>1</value></local>
+                </a>"#,
+            ),
+            (
+                "lib.xml",
+                r#"<lib>
+                    <colors id="colors"><red/><green/></colors>
+                    <sizes xml:id="sizes"><small/></sizes>
+                </lib>"#,
+            ),
+        ]);
+
+        let root = xinclude::load("main.xml", &files).expect("Failed to include");
+        assert_eq!(
+            tags(&root),
+            ["sizes", "green", "sizes", "red", "local", "local"]
+        );
+        assert_eq!(root["local"][0]["value"][0].content, "1");
+    }
+
+    #[test]
+    fn root_include() {
+        let files = files(&[
+            (
+                "main.xml",
+                r#"<xi:include xmlns:xi="http://www.w3.org/2001/XInclude" href="real.xml"/>"#,
+            ),
+            ("real.xml", "<real/>"),
+        ]);
+        let root = xinclude::load("main.xml", &files).expect("Failed to include");
+        assert_eq!(root.tag, "real");
+    }
+
+    #[test]
+    fn inclusion_loops() {
+        let files = files(&[
+            (
+                "a.xml",
+                r#"<a xmlns:xi="http://www.w3.org/2001/XInclude"><xi:include href="dir/b.xml"/></a>"#,
+            ),
+            (
+                "dir/b.xml",
+                r#"<b xmlns:xi="http://www.w3.org/2001/XInclude"><xi:include href="../a.xml"/></b>"#,
+            ),
+            (
+                "self.xml",
+                r#"<a xmlns:xi="http://www.w3.org/2001/XInclude"><b id="b"><xi:include xpointer="b"/></b></a>"#,
+            ),
+        ]);
+        assert!(matches!(
+            xinclude::load("a.xml", &files),
+            Err(Error::InclusionLoop(path)) if path == "a.xml"
+        ));
+        assert!(matches!(
+            xinclude::load("self.xml", &files),
+            Err(Error::InclusionLoop(_))
+        ));
+
+        // Including the same file twice is not a loop
+        let files = self::files(&[
+            (
+                "a.xml",
+                r#"<a xmlns:xi="http://www.w3.org/2001/XInclude"><xi:include href="b.xml"/><xi:include href="b.xml"/></a>"#,
+            ),
+            ("b.xml", "<b/>"),
+        ]);
+        let root = xinclude::load("a.xml", &files).expect("Failed to include");
+        assert_eq!(tags(&root), ["b", "b"]);
+    }
+
+    #[test]
+    fn invalid_includes() {
+        let invalid = |include: &str| {
+            let files = files(&[
+                (
+                    "main.xml",
+                    &format!(
+                        r#"<a xmlns:xi="http://www.w3.org/2001/XInclude">{}</a>"#,
+                        include
+                    ),
+                ),
+                ("b.txt", "text"),
+            ]);
+            matches!(
+                xinclude::load("main.xml", &files),
+                Err(Error::InvalidInclude(_))
+            )
+        };
+        assert!(invalid(r#"<xi:include/>"#));
+        assert!(invalid(r#"<xi:include href="b.txt#x"/>"#));
+        assert!(invalid(r#"<xi:include href="b.txt" parse="binary"/>"#));
+        assert!(invalid(
+            r#"<xi:include href="b.txt" parse="text" xpointer="x"/>"#
+        ));
+        assert!(invalid(
+            r#"<xi:include href="b.txt"><xi:include href="b.txt"/></xi:include>"#
+        ));
+        assert!(!invalid(r#"<xi:include href="b.txt" parse="text"/>"#));
+    }
+
+    #[test]
+    fn files_on_disk() {
+        let dir = std::env::temp_dir().join("simple_xml_xinclude");
+        std::fs::create_dir_all(dir.join("parts")).unwrap();
+        std::fs::write(
+            dir.join("main.xml"),
+            r#"<a xmlns:xi="http://www.w3.org/2001/XInclude"><xi:include href="parts/b.xml"/></a>"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("parts/b.xml"),
+            b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><b>\xe9</b>",
+        )
+        .unwrap();
+
+        let root = xinclude::load(dir.join("main.xml"), &xinclude::FileResolver)
+            .expect("Failed to include");
+        assert_eq!(tags(&root), ["b"]);
+        assert_eq!(root["b"][0].content, "é");
+    }
+}
@@ -0,0 +1,207 @@
+#[cfg(test)]
+mod tests {
+    use simple_xml::xsd::{self, ViolationKind};
+
+    fn violations(schema: &xsd::Schema, xml: &str) -> Vec<(String, ViolationKind)> {
+        schema
+            .validate(&simple_xml::from_string(xml).expect("Failed to parse xml"))
+            .into_iter()
+            .map(|v| (v.path, v.kind))
+            .collect()
+    }
+
+    #[test]
+    fn validate_shiporder() {
+        let schema = xsd::from_file("./examples/shiporder.xsd").expect("Failed to load schema");
+        let order = simple_xml::from_file("./examples/shiporder.xml").unwrap();
+        assert_eq!(schema.validate(&order), []);
+
+        let invalid = r#"<shiporder orderid="88992x" extra="1">
+            <shipto>
+                <name>Ola Nordmann</name>
+                <address>Langgt 23</address>
+                <city>4000 Stavanger</city>
+                <country>Denmark</country>
+            </shipto>
+            <orderperson>John Smith</orderperson>
+            <shipdate>2023-02-29</shipdate>
+            <item>
+                <title>Empire Burlesque</title>
+                <quantity>101</quantity>
+                <price>ten</price>
+            </item>
+        </shiporder>"#;
+
+        let schema_violations = violations(&schema, invalid);
+        assert_eq!(schema_violations.len(), 3, "{:?}", schema_violations);
+        assert_eq!(schema_violations[0].0, "/shiporder");
+        assert!(matches!(
+            &schema_violations[0].1,
+            ViolationKind::InvalidAttributeValue { name, .. } if name == "orderid"
+        ));
+        assert_eq!(
+            schema_violations[1],
+            (
+                "/shiporder".to_owned(),
+                ViolationKind::UndeclaredAttribute("extra".into())
+            )
+        );
+        assert!(matches!(
+            &schema_violations[2].1,
+            ViolationKind::InvalidContent { expected, .. }
+                if expected == "(orderperson, shipto, (shipdate | pickup), item+)"
+        ));
+
+        // Fix the order so the children are validated
+        let reordered = invalid
+            .replace("<orderperson>John Smith</orderperson>", "")
+            .replace("<shipto>", "<orderperson>John Smith</orderperson><shipto>");
+        let paths = violations(&schema, &reordered)
+            .into_iter()
+            .map(|(path, kind)| format!("{}: {}", path, kind))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            paths[2..],
+            [
+                "/shiporder/shipto[1]/country[1]: Value \"Denmark\" is not one of [\"Norway\", \"Sweden\"]",
+                "/shiporder/shipdate[1]: Value \"2023-02-29\" is not a valid date",
+                "/shiporder/item[1]/quantity[1]: Value \"101\" must be at most 100",
+                "/shiporder/item[1]/price[1]: Value \"ten\" is not a valid decimal",
+            ]
+        );
+    }
+
+    #[test]
+    fn all_and_occurs() {
+        let schema = xsd::Schema::from_node(
+            &simple_xml::from_string(
+                r#"<xsd:schema xmlns:xsd="http://www.w3.org/2001/XMLSchema">
+                    <xsd:element name="person">
+                        <xsd:complexType>
+                            <xsd:all>
+                                <xsd:element name="name" type="xsd:string"/>
+                                <xsd:element name="age" type="xsd:int" minOccurs="0"/>
+                            </xsd:all>
+                        </xsd:complexType>
+                    </xsd:element>
+                    <xsd:element name="list">
+                        <xsd:complexType>
+                            <xsd:sequence minOccurs="2" maxOccurs="3">
+                                <xsd:element ref="person"/>
+                            </xsd:sequence>
+                        </xsd:complexType>
+                    </xsd:element>
+                </xsd:schema>"#,
+            )
+            .unwrap(),
+        )
+        .expect("Failed to load schema");
+
+        assert_eq!(
+            violations(&schema, "<person><age>4</age><name>Adam</name></person>"),
+            []
+        );
+        assert_eq!(
+            violations(&schema, "<person><age>4</age></person>").len(),
+            1
+        );
+
+        let person = "<person><name>Adam</name></person>";
+        assert_eq!(
+            violations(&schema, &format!("<list>{}</list>", person)).len(),
+            1
+        );
+        assert_eq!(
+            violations(&schema, &format!("<list>{}</list>", person.repeat(3))),
+            []
+        );
+        assert_eq!(
+            violations(&schema, &format!("<list>{}</list>", person.repeat(4))).len(),
+            1
+        );
+
+        match violations(&schema, "<people/>")[..] {
+            [(_, ViolationKind::UnknownRoot(ref name))] if name == "people" => {}
+            ref v => panic!("Expected UnknownRoot, got {:?}", v),
+        }
+    }
+
+    #[test]
+    fn date_time_values() {
+        let schema = xsd::Schema::from_node(
+            &simple_xml::from_string(
+                r#"<xsd:schema xmlns:xsd="http://www.w3.org/2001/XMLSchema">
+                    <xsd:element name="at" type="xsd:dateTime"/>
+                </xsd:schema>"#,
+            )
+            .unwrap(),
+        )
+        .expect("Failed to load schema");
+        let valid = |value: &str| violations(&schema, &format!("<at>{}</at>", value)).is_empty();
+
+        for value in [
+            "2024-02-29T13:20:00",
+            "2024-02-29T23:59:59.999Z",
+            "2024-02-29T24:00:00",
+            "2024-02-29T24:00:00.000+01:00",
+        ] {
+            assert!(valid(value), "{:?}", value);
+        }
+        for value in [
+            "2023-02-29T13:20:00",
+            "2024-02-29T24:30:00",
+            "2024-02-29T24:00:01",
+            "2024-02-29T24:00:00.5",
+            "2024-02-29T13:60:00",
+            "2024-02-29T13:20",
+        ] {
+            assert!(!valid(value), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn invalid_schema() {
+        let schema = simple_xml::from_string(
+            r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+                <xs:element name="note" type="notetype"/>
+            </xs:schema>"#,
+        )
+        .unwrap();
+
+        match xsd::Schema::from_node(&schema) {
+            Err(simple_xml::Error::InvalidSchema(msg)) => assert!(msg.contains("notetype")),
+            v => panic!("Expected InvalidSchema, got {:?}", v),
+        }
+    }
+
+    #[test]
+    fn circular_derivation() {
+        let circular = [
+            r#"<xs:simpleType name="a"><xs:restriction base="b"/></xs:simpleType>
+            <xs:simpleType name="b"><xs:restriction base="a"/></xs:simpleType>"#,
+            r#"<xs:simpleType name="a"><xs:restriction base="a"/></xs:simpleType>"#,
+            r#"<xs:simpleType name="a"><xs:restriction>
+                <xs:simpleType><xs:restriction base="a"/></xs:simpleType>
+            </xs:restriction></xs:simpleType>"#,
+            r#"<xs:complexType name="a"><xs:simpleContent><xs:extension base="b"/></xs:simpleContent></xs:complexType>
+            <xs:complexType name="b"><xs:complexContent><xs:extension base="a"/></xs:complexContent></xs:complexType>"#,
+        ];
+
+        for types in circular {
+            let schema = simple_xml::from_string(&format!(
+                r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+                    <xs:element name="value" type="a"/>
+                    {}
+                </xs:schema>"#,
+                types
+            ))
+            .unwrap();
+
+            match xsd::Schema::from_node(&schema) {
+                Err(simple_xml::Error::InvalidSchema(msg)) => assert!(msg.contains("Circular")),
+                v => panic!("Expected InvalidSchema, got {:?}", v),
+            }
+        }
+    }
+}
@@ -0,0 +1,252 @@
+#[cfg(test)]
+mod tests {
+    use simple_xml::xslt::{self, Stylesheet};
+    use simple_xml::Error;
+
+    fn parse(string: &str) -> simple_xml::Node {
+        simple_xml::from_string(string).expect("Failed to parse")
+    }
+
+    fn stylesheet(templates: &str) -> simple_xml::Node {
+        parse(&format!(
+            r#"<xsl:stylesheet version="1.0" xmlns:xsl="http://www.w3.org/1999/XSL/Transform">{}</xsl:stylesheet>"#,
+            templates
+        ))
+    }
+
+    fn transform(templates: &str, source: &str) -> String {
+        xslt::transform(&stylesheet(templates), &parse(source))
+            .expect("Failed to transform")
+            .to_string()
+    }
+
+    const LIBRARY: &str = r#"<library>
+        <book year="1965" lang="en"><title>Dune</title><price>9.5</price></book>
+        <book year="1989" lang="en"><title>Hyperion</title><price>12</price></book>
+        <book year="1961" lang="pl"><title>Solaris</title><price>7.25</price></book>
+    </library>"#;
+
+    #[test]
+    fn template_priorities() {
+        let templates = r#"
+            <xsl:template match="/"><out><xsl:apply-templates select="library/book"/></out></xsl:template>
+            <xsl:template match="book"><any/></xsl:template>
+            <xsl:template match="book[@lang = 'pl']"><polish/></xsl:template>
+            <xsl:template match="library/book" priority="-1"><never/></xsl:template>
+            <xsl:template match="book[1] | book[title = 'Hyperion']" priority="2"><first/></xsl:template>
+        "#;
+        assert_eq!(
+            transform(templates, LIBRARY),
+            "<out><first/><first/><polish/></out>"
+        );
+
+        // The last of equally ranked templates is used
+        let templates = r#"
+            <xsl:template match="/"><out><xsl:apply-templates select="//title"/></out></xsl:template>
+            <xsl:template match="title"><a/></xsl:template>
+            <xsl:template match="title"><b/></xsl:template>
+        "#;
+        assert_eq!(transform(templates, LIBRARY), "<out><b/><b/><b/></out>");
+    }
+
+    #[test]
+    fn builtin_templates_and_modes() {
+        let templates = r#"
+            <xsl:template match="price"/>
+            <xsl:template match="title" mode="upper">
+                <xsl:value-of select="translate(., 'abcdefghijklmnopqrstuvwxyz', 'ABCDEFGHIJKLMNOPQRSTUVWXYZ')"/>
+            </xsl:template>
+            <xsl:template match="/library">
+                <titles><xsl:apply-templates/><xsl:apply-templates select="book/title" mode="upper"/></titles>
+            </xsl:template>
+        "#;
+        assert_eq!(
+            transform(templates, LIBRARY),
+            "<titles>DuneHyperionSolarisDUNEHYPERIONSOLARIS</titles>"
+        );
+    }
+
+    #[test]
+    fn value_of_and_attribute_value_templates() {
+        let templates = r#"
+            <xsl:template match="/library">
+                <summary count="{count(book)}" total="{sum(book/price)}" braces="{{{book[2]/@year}}}">
+                    <xsl:value-of select="concat(book[last()]/title, ' &amp; ', substring(book[1]/title, 2, 2))"/>
+                </summary>
+            </xsl:template>
+        "#;
+        let output = xslt::transform(&stylesheet(templates), &parse(LIBRARY)).unwrap();
+        assert_eq!(output.get_attribute("count").unwrap(), "3");
+        assert_eq!(output.get_attribute("total").unwrap(), "28.75");
+        assert_eq!(output.get_attribute("braces").unwrap(), "{1989}");
+        assert_eq!(output.content, "Solaris &amp; un");
+    }
+
+    #[test]
+    fn for_each_with_sort() {
+        let templates = r#"
+            <xsl:template match="/">
+                <books>
+                    <xsl:for-each select="library/book">
+                        <xsl:sort select="price" data-type="number" order="descending"/>
+                        <book n="{position()} of {last()}"><xsl:value-of select="title"/></book>
+                    </xsl:for-each>
+                </books>
+            </xsl:template>
+        "#;
+        assert_eq!(
+            transform(templates, LIBRARY),
+            concat!(
+                r#"<books><book n="1 of 3">Hyperion</book><book n="2 of 3">Dune</book>"#,
+                r#"<book n="3 of 3">Solaris</book></books>"#
+            )
+        );
+
+        let templates = r#"
+            <xsl:template match="/library">
+                <books><xsl:apply-templates select="book"><xsl:sort select="@lang" order="descending"/><xsl:sort select="title"/></xsl:apply-templates></books>
+            </xsl:template>
+            <xsl:template match="book"><xsl:copy-of select="title"/></xsl:template>
+        "#;
+        assert_eq!(
+            transform(templates, LIBRARY),
+            "<books><title>Solaris</title><title>Dune</title><title>Hyperion</title></books>"
+        );
+    }
+
+    #[test]
+    fn conditions() {
+        let templates = r#"
+            <xsl:template match="/library">
+                <books>
+                    <xsl:for-each select="book">
+                        <xsl:choose>
+                            <xsl:when test="@year &lt; 1962"><old/></xsl:when>
+                            <xsl:when test="price > 10"><expensive/></xsl:when>
+                            <xsl:otherwise><other/></xsl:otherwise>
+                        </xsl:choose>
+                        <xsl:if test="not(@lang = 'en')"><translated/></xsl:if>
+                    </xsl:for-each>
+                </books>
+            </xsl:template>
+        "#;
+        assert_eq!(
+            transform(templates, LIBRARY),
+            "<books><other/><expensive/><old/><translated/></books>"
+        );
+    }
+
+    #[test]
+    fn variables_and_parameters() {
+        let templates = r#"
+            <xsl:variable name="currency" select="'EUR'"/>
+            <xsl:template match="/library">
+                <xsl:variable name="cheapest">
+                    <xsl:for-each select="book"><xsl:sort select="price" data-type="number"/>
+                        <xsl:if test="position() = 1"><xsl:value-of select="title"/></xsl:if>
+                    </xsl:for-each>
+                </xsl:variable>
+                <prices cheapest="{$cheapest}">
+                    <xsl:apply-templates select="book">
+                        <xsl:with-param name="rate" select="2"/>
+                    </xsl:apply-templates>
+                    <xsl:call-template name="total"/>
+                </prices>
+            </xsl:template>
+            <xsl:template match="book">
+                <xsl:param name="rate" select="1"/>
+                <price currency="{$currency}"><xsl:value-of select="price * $rate"/></price>
+            </xsl:template>
+            <xsl:template name="total">
+                <xsl:param name="rate" select="1"/>
+                <total><xsl:value-of select="sum(book/price) * $rate"/></total>
+            </xsl:template>
+        "#;
+        assert_eq!(
+            transform(templates, LIBRARY),
+            concat!(
+                r#"<prices cheapest="Solaris"><price currency="EUR">19</price>"#,
+                r#"<price currency="EUR">24</price><price currency="EUR">14.5</price>"#,
+                "<total>28.75</total></prices>"
+            )
+        );
+    }
+
+    #[test]
+    fn copy_and_computed_nodes() {
+        let templates = r#"
+            <xsl:template match="@* | node()">
+                <xsl:copy><xsl:apply-templates select="@* | node()"/></xsl:copy>
+            </xsl:template>
+            <xsl:template match="price">
+                <xsl:element name="{name()}-eur">
+                    <xsl:attribute name="rounded"><xsl:value-of select="round(.)"/></xsl:attribute>
+                    <xsl:value-of select="."/>
+                </xsl:element>
+            </xsl:template>
+            <xsl:template match="@lang"/>
+        "#;
+        let output = xslt::transform(&stylesheet(templates), &parse(LIBRARY)).unwrap();
+        assert_eq!(output.tag, "library");
+        let book = &output["book"][2];
+        assert_eq!(book.get_attribute("year").unwrap(), "1961");
+        assert!(book.get_attribute("lang").is_none());
+        assert_eq!(book["title"][0].content, "Solaris");
+        assert_eq!(book["price-eur"][0].content, "7.25");
+        assert_eq!(book["price-eur"][0].get_attribute("rounded").unwrap(), "7");
+    }
+
+    #[test]
+    fn mixed_output() {
+        let templates = r#"
+            <xsl:template match="/">
+                <xsl:variable name="name"><xsl:text>a-</xsl:text><b>name</b><xsl:text>-here</xsl:text></xsl:variable>
+                <p><xsl:text>Read:</xsl:text><i>this</i><xsl:text>,and:</xsl:text><xsl:copy-of select="$name"/><xsl:text>.</xsl:text></p>
+            </xsl:template>
+        "#;
+        assert_eq!(
+            transform(templates, LIBRARY),
+            "<p>Read:<i>this</i>,and:a-<b>name</b>-here.</p>"
+        );
+    }
+
+    #[test]
+    fn text_output() {
+        let templates = r#"
+            <xsl:template match="/"><xsl:for-each select="//title"><xsl:value-of select="."/><xsl:text>;</xsl:text></xsl:for-each></xsl:template>
+        "#;
+        let output = xslt::transform(&stylesheet(templates), &parse(LIBRARY)).unwrap();
+        assert_eq!(output.tag, "");
+        assert_eq!(output.content, "Dune;Hyperion;Solaris;");
+    }
+
+    #[test]
+    fn invalid_stylesheets() {
+        let invalid = |templates: &str| {
+            matches!(
+                Stylesheet::parse(&stylesheet(templates)),
+                Err(Error::InvalidStylesheet(_))
+            )
+        };
+        assert!(invalid(r#"<xsl:template/>"#));
+        assert!(invalid(r#"<xsl:template match="a["/>"#));
+        assert!(invalid(
+            r#"<xsl:template match="a"><xsl:unknown/></xsl:template>"#
+        ));
+        assert!(invalid(
+            r#"<xsl:template match="a"><b c="{d"/></xsl:template>"#
+        ));
+
+        let root = parse(r#"<stylesheet xmlns="urn:other"/>"#);
+        assert!(matches!(
+            Stylesheet::parse(&root),
+            Err(Error::InvalidStylesheet(_))
+        ));
+
+        // Infinite recursion is stopped
+        let templates =
+            r#"<xsl:template match="/"><xsl:apply-templates select="."/></xsl:template>"#;
+        let result = xslt::transform(&stylesheet(templates), &parse("<a/>"));
+        assert!(matches!(result, Err(Error::InvalidStylesheet(_))));
+    }
+}